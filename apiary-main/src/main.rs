@@ -32,6 +32,7 @@ fn main() {
 
         match event {
             Event::MainEventsCleared => {
+                app.wait_for_next_frame();
                 window.request_redraw();
             }
             Event::RedrawRequested(_) => {
@@ -1,9 +1,18 @@
 use crate::error::ApiaryResult;
+use crate::frame_pacing::FramePacer;
 use crate::input;
-use crate::input::InputResource;
+use crate::input::{
+    CursorResource, InputBackend, InputBindings, InputConsumption, InputEvent, InputRecorder,
+    InputReplay, InputResource, KeyChord, KeyboardKey, ShortcutKeyFilter, TimestampedInputEvent,
+    WinitInputBackend,
+};
 use crate::rendering::{rendering_destroy, rendering_init};
-use crate::scenes::{create_scene, SceneManager};
-use crate::time::{PeriodicEvent, TimeState};
+use crate::scenes::{
+    default_scene_registry, SceneFile, SceneFileWatcher, SceneLoadingResource, SceneManager,
+    SceneRegistry, SceneTransitionResource,
+};
+use crate::time::{ClockResource, TimeState};
+use crate::timers::TimerResource;
 use legion::{Resources, World};
 //use puffin_egui::puffin;
 use rafx::api::{RafxApi, RafxExtents2D, RafxSwapchainHelper};
@@ -29,6 +38,28 @@ use std::time::Instant;
 use structopt::StructOpt;
 use winit::event_loop::ControlFlow;
 
+/// Debug hotkey that logs asset manager metrics
+const DUMP_ASSET_METRICS_SHORTCUT: &str = "dump_asset_metrics";
+
+/// Exits the app. Like every shortcut it is read from the base input, so it doesn't fire while
+/// egui has keyboard focus, for example while typing into a text field. This matches the
+/// behaviour before shortcuts existed, when Escape was only checked for events egui didn't want.
+const QUIT_SHORTCUT: &str = "quit";
+
+/// Debug hotkey that saves the meshes and lights in the world to `ApiaryArgs::save_scene`
+const SAVE_SCENE_SHORTCUT: &str = "save_scene";
+
+/// Switch to the next/previous scene in the `SceneRegistry`
+const NEXT_SCENE_SHORTCUT: &str = "next_scene";
+const PREVIOUS_SCENE_SHORTCUT: &str = "previous_scene";
+
+/// Repeating timer that logs the frame rate
+const PRINT_FPS_TIMER: &str = "print_fps";
+
+/// Input context that consumes input egui wants, so it doesn't also drive the scene
+#[cfg(feature = "egui")]
+const EGUI_INPUT_CONTEXT: &str = "egui";
+
 pub struct ApiaryApp {
     api: RafxApi,
 
@@ -36,8 +67,15 @@ pub struct ApiaryApp {
     world: World,
     scene_manager: SceneManager,
 
-    print_time_event: PeriodicEvent,
     font: Handle<FontAsset>,
+
+    input_recorder: Option<InputRecorder>,
+    input_replay: Option<InputReplay>,
+    input_backend: WinitInputBackend,
+    shortcut_key_filter: ShortcutKeyFilter,
+    gamepad_backend: Option<Box<dyn InputBackend>>,
+
+    save_scene_path: PathBuf,
 }
 
 #[derive(StructOpt)]
@@ -52,6 +90,44 @@ pub struct ApiaryArgs {
     #[structopt(name = "external-daemon", long)]
     pub external_daemon: bool,
 
+    /// Path to the RON file describing input action and axis bindings
+    #[structopt(
+        name = "bindings",
+        long,
+        parse(from_os_str),
+        default_value = "config/input_bindings.ron"
+    )]
+    pub bindings: PathBuf,
+
+    /// Record all input to this file, it is written when the app exits
+    #[structopt(name = "record-input", long, parse(from_os_str))]
+    pub record_input: Option<PathBuf>,
+
+    /// Replay input previously captured with --record-input instead of using live input
+    #[structopt(name = "replay-input", long, parse(from_os_str))]
+    pub replay_input: Option<PathBuf>,
+
+    /// Where the save scene hotkey (Ctrl+S) writes the current scene
+    #[structopt(
+        name = "save-scene",
+        long,
+        parse(from_os_str),
+        default_value = "saved_scene.ron"
+    )]
+    pub save_scene: PathBuf,
+
+    /// Name of the scene to start in. Defaults to the first registered scene.
+    #[structopt(name = "scene", long)]
+    pub scene: Option<String>,
+
+    /// Cap the frame rate while the window is in use. Uncapped if not set.
+    #[structopt(name = "target-fps", long)]
+    pub target_fps: Option<f32>,
+
+    /// Cap the frame rate while the window is unfocused or minimized. 0 leaves it uncapped.
+    #[structopt(name = "low-power-fps", long, default_value = "10")]
+    pub low_power_fps: f32,
+
     #[structopt(flatten)]
     pub daemon_args: AssetDaemonArgs,
 }
@@ -233,6 +309,7 @@ pub struct DebugUiState {
     show_render_options: bool,
     show_asset_list: bool,
     show_tonemap_debug: bool,
+    show_time_controls: bool,
 
     #[cfg(feature = "profile-with-puffin")]
     show_profiler: bool,
@@ -251,21 +328,108 @@ impl ApiaryApp {
         let t0 = Instant::now();
 
         //
-        // Update time
+        // Apply window input received since the previous frame
+        //
+        if self.input_replay.is_none() {
+            let mut input_resource = self.resources.get_mut::<InputResource>().unwrap();
+            let input_events = input_resource.poll_backend(&mut self.input_backend);
+
+            if let Some(input_recorder) = &mut self.input_recorder {
+                for input_event in &input_events {
+                    input_recorder.record_event(input_event);
+                }
+            }
+        }
+
+        //
+        // Poll gamepads. These are fed like any other live input so they are recorded and are
+        // ignored while replaying.
+        //
+        if let Some(gamepad_backend) = &mut self.gamepad_backend {
+            if self.input_replay.is_none() {
+                let mut input_resource = self.resources.get_mut::<InputResource>().unwrap();
+                let gamepad_events = input_resource.poll_backend(gamepad_backend.as_mut());
+
+                if let Some(input_recorder) = &mut self.input_recorder {
+                    for gamepad_event in &gamepad_events {
+                        input_recorder.record_event(gamepad_event);
+                    }
+                }
+            } else {
+                // Discarded so they aren't applied once the replay finishes
+                gamepad_backend.poll_events(&mut Vec::default());
+            }
+        }
+
+        //
+        // Update time, feeding recorded input instead of live input if replaying
         //
         {
-            self.resources.get_mut::<TimeState>().unwrap().update();
+            let mut time_state = self.resources.get_mut::<TimeState>().unwrap();
+            let frame_start = time_state.current_instant();
+            let mut replay_frame = self
+                .input_replay
+                .as_mut()
+                .and_then(|input_replay| input_replay.next_frame());
+
+            // A recording only reproduces the session if every frame lines up with the one it was
+            // recorded in
+            if let Some(frame) = &replay_frame {
+                let expected_frame = time_state.update_count() + 1;
+                if frame.frame != expected_frame {
+                    log::error!(
+                        "Input replay is out of step (recorded frame {}, replaying frame {}), switching to live input",
+                        frame.frame,
+                        expected_frame
+                    );
+                    self.input_replay = None;
+                    replay_frame = None;
+                }
+            }
+
+            if let Some(replay_frame) = replay_frame {
+                time_state.update_with_elapsed(replay_frame.elapsed);
+
+                let mut input_resource = self.resources.get_mut::<InputResource>().unwrap();
+                for event in replay_frame.timestamped_events(frame_start) {
+                    input_resource.handle_event_at(&event.event, event.timestamp);
+                }
+            } else {
+                if self.input_replay.take().is_some() {
+                    log::info!("Input replay finished, switching to live input");
+                }
+
+                time_state.update();
+            }
+
+            if let Some(input_recorder) = &mut self.input_recorder {
+                input_recorder.end_frame(
+                    time_state.update_count(),
+                    frame_start,
+                    time_state.previous_update_time(),
+                );
+
+                // Also saved on exit, this keeps the recording if the app crashes
+                let now = self.resources.get::<ClockResource>().unwrap().now();
+                if let Err(e) = input_recorder.save_if_due(now) {
+                    log::error!(
+                        "Failed to save input recording to {:?}: {:?}",
+                        input_recorder.path(),
+                        e
+                    );
+                }
+            }
         }
 
         //
-        // Print FPS
+        // Fire timers, including the one that prints FPS
         //
         {
             let time_state = self.resources.get::<TimeState>().unwrap();
-            if self.print_time_event.try_take_event(
-                time_state.current_instant(),
-                std::time::Duration::from_secs_f32(1.0),
-            ) {
+            let mut timer_resource = self.resources.get_mut::<TimerResource>().unwrap();
+            timer_resource.update(&*time_state);
+
+            if timer_resource.fired_named(PRINT_FPS_TIMER) {
                 log::info!("FPS: {}", time_state.updates_per_second());
                 //renderer.dump_stats();
             }
@@ -280,24 +444,31 @@ impl ApiaryApp {
             };
         }
 
+        //
+        // Apply scene transitions requested last frame, before anything touches the world
+        //
         {
-            if self.scene_manager.has_next_scene() {
-                self.scene_manager
-                    .try_cleanup_current_scene(&mut self.world, &self.resources);
-
-                {
-                    // NOTE(dvd): Legion leaks memory because the entity IDs aren't reset when the
-                    // world is cleared and the entity location map will grow without bounds.
-                    self.world = World::default();
+            profiling::scope!("apply scene transitions");
+            self.scene_manager
+                .apply_pending_transitions(&mut self.world, &self.resources);
+        }
 
-                    // NOTE(dvd): The Renderer maintains some per-frame temporary data to avoid
-                    // allocating each frame. We can clear this between scene transitions.
-                    let mut renderer = self.resources.get_mut::<Renderer>().unwrap();
-                    renderer.clear_temporary_work();
-                }
+        //
+        // Reload scene files that changed on disk
+        //
+        {
+            let changed_scene_files = {
+                let clock = self.resources.get::<ClockResource>().unwrap();
+                let mut scene_file_watcher = self.resources.get_mut::<SceneFileWatcher>().unwrap();
+                scene_file_watcher.poll_changes(clock.now())
+            };
 
-                //self.scene_manager.go_next_scene();
-                //.try_create_next_scene(&mut self.world, &self.resources);
+            if !changed_scene_files.is_empty() {
+                self.scene_manager.scene_files_changed(
+                    &mut self.world,
+                    &self.resources,
+                    &changed_scene_files,
+                );
             }
         }
 
@@ -331,21 +502,145 @@ impl ApiaryApp {
 
         {
             let mut text_resource = self.resources.get_mut::<TextResource>().unwrap();
+            let scene_registry = self.resources.get::<SceneRegistry>().unwrap();
 
+            let scene_name = scene_registry.current_name().unwrap_or("none");
             text_resource.add_text(
-                "Use Left/Right arrow keys to switch demos".to_string(),
+                format!(
+                    "Scene: {} - Use Left/Right arrow keys to switch demos",
+                    scene_name
+                ),
                 glam::Vec3::new(100.0, 400.0, 0.0),
                 &self.font,
                 20.0,
                 glam::Vec4::new(1.0, 1.0, 1.0, 1.0),
             );
+
+            // Scene files that failed to reload keep their previous contents, say why
+            let scene_file_watcher = self.resources.get::<SceneFileWatcher>().unwrap();
+            for (i, (path, error)) in scene_file_watcher.errors().enumerate() {
+                text_resource.add_text(
+                    format!("Failed to load {}: {}", path.display(), error),
+                    glam::Vec3::new(100.0, 430.0 + 25.0 * i as f32, 0.0),
+                    &self.font,
+                    20.0,
+                    glam::Vec4::new(1.0, 0.3, 0.3, 1.0),
+                );
+            }
+        }
+
+        //
+        // Recognise touch gestures and update virtual axes from this frame's input
+        //
+        {
+            let time_state = self.resources.get::<TimeState>().unwrap();
+            let mut input_resource = self.resources.get_mut::<InputResource>().unwrap();
+            input_resource.update_gestures(time_state.previous_update_dt());
+            input_resource.update_axes(time_state.previous_update_dt());
         }
 
+        //
+        // Debug hotkeys
+        //
+        let quit_requested = {
+            let input_resource = self.resources.get::<InputResource>().unwrap();
+            if input_resource.shortcut_just_pressed(DUMP_ASSET_METRICS_SHORTCUT) {
+                let metrics = self.resources.get::<AssetManager>().unwrap().metrics();
+                log::info!("{:#?}", metrics);
+            }
+
+            if input_resource.shortcut_just_pressed(SAVE_SCENE_SHORTCUT) {
+                let scene_file = SceneFile::from_world(&self.world, &self.resources);
+                match scene_file.save(&self.save_scene_path) {
+                    Ok(()) => log::info!("Saved scene to {:?}", self.save_scene_path),
+                    Err(e) => log::error!(
+                        "Failed to save scene to {:?}: {:?}",
+                        self.save_scene_path,
+                        e
+                    ),
+                }
+            }
+
+            // The scene cycling keys are the cursor keys, leave them to text fields
+            let is_text_input_active = input_resource.contexts().is_text_input_active();
+
+            if !is_text_input_active && input_resource.shortcut_just_pressed(NEXT_SCENE_SHORTCUT) {
+                let mut scene_registry = self.resources.get_mut::<SceneRegistry>().unwrap();
+                let mut transitions = self.resources.get_mut::<SceneTransitionResource>().unwrap();
+                scene_registry.request_next(&mut transitions);
+            }
+
+            if !is_text_input_active
+                && input_resource.shortcut_just_pressed(PREVIOUS_SCENE_SHORTCUT)
+            {
+                let mut scene_registry = self.resources.get_mut::<SceneRegistry>().unwrap();
+                let mut transitions = self.resources.get_mut::<SceneTransitionResource>().unwrap();
+                scene_registry.request_previous(&mut transitions);
+            }
+
+            input_resource.shortcut_just_pressed(QUIT_SHORTCUT)
+        };
+
         {
+            let fired_timer_events = self
+                .resources
+                .get::<TimerResource>()
+                .unwrap()
+                .fired_events()
+                .to_vec();
+            if !fired_timer_events.is_empty() {
+                self.scene_manager.timers_fired(
+                    &mut self.world,
+                    &mut self.resources,
+                    &fired_timer_events,
+                );
+            }
+
+            let fixed_ticks = self
+                .resources
+                .get::<TimeState>()
+                .unwrap()
+                .fixed_ticks_this_update();
+            for _ in 0..fixed_ticks {
+                self.scene_manager
+                    .fixed_update_scene(&mut self.world, &mut self.resources);
+            }
+
             self.scene_manager
                 .update_scene(&mut self.world, &mut self.resources);
         }
 
+        //
+        // Loading overlay while the scene reports that it is still loading assets
+        //
+        {
+            let progress = self
+                .resources
+                .get_mut::<SceneLoadingResource>()
+                .unwrap()
+                .take_progress();
+            if let Some(progress) = progress {
+                let viewports = self.resources.get::<ViewportsResource>().unwrap();
+                let mut text_resource = self.resources.get_mut::<TextResource>().unwrap();
+                text_resource.add_text(
+                    format!(
+                        "Loading... {}/{} ({:.0}%)",
+                        progress.loaded,
+                        progress.total,
+                        progress.fraction() * 100.0
+                    ),
+                    glam::Vec3::new(
+                        viewports.main_window_size.width as f32 / 2.0 - 150.0,
+                        viewports.main_window_size.height as f32 / 2.0,
+                        0.0,
+                    ),
+                    &self.font,
+                    40.0,
+                    glam::Vec4::new(1.0, 1.0, 1.0, 1.0),
+                );
+            }
+        }
+
         #[cfg(feature = "egui")]
         {
             let ctx = self
@@ -353,7 +648,8 @@ impl ApiaryApp {
                 .get::<EguiContextResource>()
                 .unwrap()
                 .context();
-            let time_state = self.resources.get::<TimeState>().unwrap();
+            let mut time_state = self.resources.get_mut::<TimeState>().unwrap();
+            let mut frame_pacer = self.resources.get_mut::<FramePacer>().unwrap();
             let mut debug_ui_state = self.resources.get_mut::<DebugUiState>().unwrap();
             let mut render_options = self.resources.get_mut::<RenderOptions>().unwrap();
             let tonemap_debug_data = self
@@ -361,6 +657,9 @@ impl ApiaryApp {
                 .get::<BasicPipelineTonemapDebugData>()
                 .unwrap();
             let asset_manager = self.resources.get::<AssetResource>().unwrap();
+            let mut scene_registry = self.resources.get_mut::<SceneRegistry>().unwrap();
+            let mut scene_transitions =
+                self.resources.get_mut::<SceneTransitionResource>().unwrap();
 
             egui::TopBottomPanel::top("top_panel").show(&ctx, |ui| {
                 egui::menu::bar(ui, |ui| {
@@ -369,6 +668,7 @@ impl ApiaryApp {
 
                         ui.checkbox(&mut debug_ui_state.show_asset_list, "Asset List");
                         ui.checkbox(&mut debug_ui_state.show_tonemap_debug, "Tonemap Debug");
+                        ui.checkbox(&mut debug_ui_state.show_time_controls, "Time Controls");
 
                         #[cfg(feature = "profile-with-puffin")]
                         if ui
@@ -383,6 +683,16 @@ impl ApiaryApp {
                         }
                     });
 
+                    egui::menu::menu(ui, "Scenes", |ui| {
+                        for index in 0..scene_registry.len() {
+                            let is_current = scene_registry.current_index() == Some(index);
+                            let name = scene_registry.name(index).unwrap().to_string();
+                            if ui.selectable_label(is_current, name).clicked() {
+                                scene_registry.request_switch(index, &mut scene_transitions);
+                            }
+                        }
+                    });
+
                     ui.with_layout(egui::Layout::right_to_left(), |ui| {
                         ui.label(format!("Frame: {}", time_state.update_count()));
                         ui.separator();
@@ -390,10 +700,99 @@ impl ApiaryApp {
                             "FPS: {:.1}",
                             time_state.updates_per_second_smoothed()
                         ));
+
+                        let frame_time_history = time_state.frame_time_history();
+                        if let Some(stats) = frame_time_history.stats() {
+                            ui.separator();
+                            ui.label(format!(
+                                "ms: p50 {:.1} p95 {:.1} p99 {:.1} max {:.1} hitches {}",
+                                stats.p50.as_secs_f32() * 1000.0,
+                                stats.p95.as_secs_f32() * 1000.0,
+                                stats.p99.as_secs_f32() * 1000.0,
+                                stats.max.as_secs_f32() * 1000.0,
+                                stats.hitch_count
+                            ));
+
+                            use egui::plot::{HLine, Line, Plot, Value, Values};
+                            let frame_time_values =
+                                frame_time_history
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(i, frame_time)| {
+                                        Value::new(i as f64, frame_time.as_secs_f64() * 1000.0)
+                                    });
+                            let budget_line =
+                                HLine::new(frame_time_history.budget().as_secs_f64() * 1000.0);
+                            ui.add(
+                                Plot::new("frame_time_graph")
+                                    .line(Line::new(Values::from_values_iter(frame_time_values)))
+                                    .hline(budget_line)
+                                    .include_x(0.0)
+                                    .include_x(frame_time_history.capacity() as f64)
+                                    .include_y(0.0)
+                                    .width(240.0)
+                                    .height(18.0)
+                                    .show_axes([false, false]),
+                            );
+                        }
                     });
                 })
             });
 
+            if debug_ui_state.show_time_controls {
+                let fixed_tick_duration = time_state.fixed_timestep().tick_duration();
+                egui::Window::new("Time Controls")
+                    .open(&mut debug_ui_state.show_time_controls)
+                    .show(&ctx, |ui| {
+                        let game_time_context = time_state.game_time_context_mut();
+                        ui.label(format!(
+                            "Game time: {:.2}s",
+                            game_time_context.total_time().as_secs_f32()
+                        ));
+
+                        let mut paused = game_time_context.is_paused();
+                        if ui.checkbox(&mut paused, "Paused").changed() {
+                            game_time_context.set_paused(paused);
+                        }
+
+                        // Steps by one fixed tick so a paused simulation advances exactly once
+                        if ui.add_enabled(paused, egui::Button::new("Step")).clicked() {
+                            game_time_context.request_step(fixed_tick_duration);
+                        }
+
+                        let mut time_scale = game_time_context.time_scale();
+                        if ui
+                            .add(egui::Slider::new(&mut time_scale, 0.0..=4.0).text("Time scale"))
+                            .changed()
+                        {
+                            game_time_context.set_time_scale(time_scale);
+                        }
+
+                        ui.separator();
+                        ui.label("Frame pacing");
+
+                        let mut limit_fps = frame_pacer.target_fps().is_some();
+                        let mut target_fps = frame_pacer.target_fps().unwrap_or(60.0);
+                        ui.checkbox(&mut limit_fps, "Limit FPS");
+                        ui.add_enabled(
+                            limit_fps,
+                            egui::Slider::new(&mut target_fps, 10.0..=240.0).text("Target FPS"),
+                        );
+                        frame_pacer.set_target_fps(if limit_fps { Some(target_fps) } else { None });
+
+                        let mut low_power_fps = frame_pacer.low_power_fps().unwrap_or(0.0);
+                        if ui
+                            .add(
+                                egui::Slider::new(&mut low_power_fps, 0.0..=60.0)
+                                    .text("Low power FPS (0 = uncapped)"),
+                            )
+                            .changed()
+                        {
+                            frame_pacer.set_low_power_fps(Some(low_power_fps));
+                        }
+                    });
+            }
+
             if debug_ui_state.show_tonemap_debug {
                 egui::Window::new("Tonemap Debug")
                     .open(&mut debug_ui_state.show_tonemap_debug)
@@ -490,6 +889,24 @@ impl ApiaryApp {
                 puffin_egui::profiler_window(&ctx);
             }
 
+            // Input egui wants this frame is kept from the scene on the next frame
+            let mut input_resource = self.resources.get_mut::<InputResource>().unwrap();
+            input_resource.contexts_mut().set_consumption(
+                EGUI_INPUT_CONTEXT,
+                InputConsumption {
+                    keyboard: ctx.wants_keyboard_input(),
+                    text: ctx.wants_keyboard_input(),
+                    mouse_buttons: ctx.wants_pointer_input(),
+                    mouse_wheel: ctx.wants_pointer_input(),
+                    touch: ctx.wants_pointer_input(),
+                    ..InputConsumption::NONE
+                },
+            );
+
+            // Release the cursor while egui wants it
+            let mut cursor_resource = self.resources.get_mut::<CursorResource>().unwrap();
+            cursor_resource.set_pointer_wanted_by_ui(ctx.wants_pointer_input());
+
             let mut render_config_resource =
                 self.resources.get_mut::<RendererConfigResource>().unwrap();
             render_config_resource
@@ -497,6 +914,18 @@ impl ApiaryApp {
                 .enable_visibility_update = render_options.enable_visibility_update;
         }
 
+        //
+        // Apply the cursor mode requested by the scene to the window
+        //
+        {
+            let mut cursor_resource = self.resources.get_mut::<CursorResource>().unwrap();
+            if let Some(cursor_mode) = cursor_resource.take_pending_change() {
+                if let Err(e) = input::apply_cursor_mode(window, cursor_mode) {
+                    log::warn!("Failed to set cursor mode {:?}: {:?}", cursor_mode, e);
+                }
+            }
+        }
+
         {
             let render_options = self.resources.get::<RenderOptions>().unwrap();
 
@@ -525,6 +954,14 @@ impl ApiaryApp {
             mesh_render_options.enable_lighting = render_options.enable_lighting;
         }
 
+        //
+        // Clear input that only lasts a single frame
+        //
+        {
+            let mut input_resource = self.resources.get_mut::<InputResource>().unwrap();
+            input_resource.end_frame();
+        }
+
         //
         // Close egui input for this frame
         //
@@ -617,7 +1054,11 @@ impl ApiaryApp {
         );
 
         profiling::finish_frame!();
-        Ok(ControlFlow::Poll)
+        if quit_requested {
+            Ok(ControlFlow::Exit)
+        } else {
+            Ok(ControlFlow::Poll)
+        }
     }
 
     pub fn init(args: &ApiaryArgs, window: &winit::window::Window) -> ApiaryResult<Self> {
@@ -626,13 +1067,67 @@ impl ApiaryApp {
         let api = unsafe { RafxApi::new(window, &Default::default())? };
 
         let mut resources = Resources::default();
-        resources.insert(TimeState::new());
-        resources.insert(InputResource::new());
+        // Everything time-dependent reads time through this so it can be replaced by a ManualClock
+        let clock = ClockResource::real();
+        resources.insert(TimeState::new(clock.clone()));
+        resources.insert(clock);
+
+        let mut input_resource = InputResource::new();
+        match InputBindings::load(&args.bindings) {
+            Ok(bindings) => input_resource.set_bindings(bindings),
+            Err(e) => log::warn!(
+                "Failed to load input bindings from {:?}: {:?}",
+                args.bindings,
+                e
+            ),
+        }
+        input_resource
+            .shortcuts_mut()
+            .register(DUMP_ASSET_METRICS_SHORTCUT, KeyChord::new(KeyboardKey::M));
+        input_resource
+            .shortcuts_mut()
+            .register(QUIT_SHORTCUT, KeyChord::new(KeyboardKey::Escape));
+        input_resource.shortcuts_mut().register(
+            SAVE_SCENE_SHORTCUT,
+            KeyChord::new(KeyboardKey::S).with_ctrl(),
+        );
+        input_resource
+            .shortcuts_mut()
+            .register(NEXT_SCENE_SHORTCUT, KeyChord::new(KeyboardKey::Right));
+        input_resource
+            .shortcuts_mut()
+            .register(PREVIOUS_SCENE_SHORTCUT, KeyChord::new(KeyboardKey::Left));
+
+        #[cfg(feature = "egui")]
+        input_resource
+            .contexts_mut()
+            .push(EGUI_INPUT_CONTEXT, InputConsumption::NONE);
+        // winit only reports changes to the scale factor, so start from the window's current one
+        let initial_scale_factor = TimestampedInputEvent {
+            timestamp: resources.get::<ClockResource>().unwrap().now(),
+            event: InputEvent::ScaleFactorChanged(window.scale_factor() as f32),
+        };
+        input_resource.handle_event_at(&initial_scale_factor.event, initial_scale_factor.timestamp);
+        resources.insert(input_resource);
+        resources.insert(CursorResource::new());
+        let mut timer_resource = TimerResource::default();
+        timer_resource.start_repeating(
+            &*resources.get::<TimeState>().unwrap(),
+            PRINT_FPS_TIMER,
+            TimeState::APP_TIME_CONTEXT,
+            std::time::Duration::from_secs_f32(1.0),
+        );
+        resources.insert(timer_resource);
+        resources.insert(FramePacer::new(args.target_fps, Some(args.low_power_fps)));
+
         resources.insert(RenderOptions::default_2d());
         resources.insert(MeshBasicRenderOptions::default());
         resources.insert(BasicPipelineRenderOptions::default());
         resources.insert(BasicPipelineTonemapDebugData::default());
         resources.insert(DebugUiState::default());
+        resources.insert(SceneTransitionResource::default());
+        resources.insert(SceneFileWatcher::new());
+        resources.insert(SceneLoadingResource::default());
 
         let mut scene_manager = SceneManager::default();
 
@@ -652,19 +1147,58 @@ impl ApiaryApp {
             asset_resource.load_asset_path::<FontAsset, _>("fonts/mplus-1p-regular.ttf")
         };
 
+        let mut scene_registry = default_scene_registry();
+        let initial_scene_index = match &args.scene {
+            Some(name) => scene_registry.index_of(name).or_else(|| {
+                log::warn!(
+                    "Unknown scene {:?}, expected one of {:?}",
+                    name,
+                    scene_registry.names().collect::<Vec<_>>()
+                );
+                None
+            }),
+            None => None,
+        }
+        .unwrap_or(0);
+
         let mut world = World::default();
-        scene_manager.set_scene(create_scene(&mut world, &resources));
+        let scene_factory = scene_registry.factory(initial_scene_index).unwrap();
+        scene_manager.set_scene(scene_factory(&mut world, &resources));
+        scene_registry.set_current_index(Some(initial_scene_index));
+        resources.insert(scene_registry);
+
+        let mut input_recorder = args.record_input.clone().map(InputRecorder::new);
+        if let Some(input_recorder) = &mut input_recorder {
+            input_recorder.record_event(&initial_scale_factor);
+        }
+        let input_replay = match &args.replay_input {
+            Some(path) => Some(InputReplay::load(path)?),
+            None => None,
+        };
 
-        let print_time_event = crate::time::PeriodicEvent::default();
+        #[cfg(feature = "gamepad-gilrs")]
+        let gamepad_backend = crate::input::GilrsGamepadBackend::new(
+            resources.get::<ClockResource>().unwrap().clone(),
+        )
+        .map(|backend| Box::new(backend) as Box<dyn InputBackend>);
+        #[cfg(not(feature = "gamepad-gilrs"))]
+        let gamepad_backend = None;
+
+        let input_backend =
+            WinitInputBackend::new(resources.get::<ClockResource>().unwrap().clone());
 
-        {}
         Ok(ApiaryApp {
             api,
             resources,
             world,
             scene_manager,
-            print_time_event,
             font,
+            input_recorder,
+            input_replay,
+            input_backend,
+            shortcut_key_filter: ShortcutKeyFilter::default(),
+            gamepad_backend,
+            save_scene_path: args.save_scene.clone(),
         })
     }
 
@@ -673,6 +1207,13 @@ impl ApiaryApp {
         Ok(())
     }
 
+    /// Block until the frame pacer allows the next frame to start. Call before requesting a redraw.
+    pub fn wait_for_next_frame(&mut self) {
+        let clock = self.resources.get::<ClockResource>().unwrap();
+        let mut frame_pacer = self.resources.get_mut::<FramePacer>().unwrap();
+        frame_pacer.wait_for_next_frame(&*clock);
+    }
+
     pub fn process_input(
         &mut self,
         event: &winit::event::Event<()>,
@@ -682,6 +1223,9 @@ impl ApiaryApp {
             &mut self.scene_manager,
             &mut self.world,
             &self.resources,
+            &mut self.input_backend,
+            &mut self.shortcut_key_filter,
+            self.input_replay.is_some(),
             event,
             window,
         )
@@ -691,6 +1235,9 @@ impl ApiaryApp {
         scene_manager: &mut SceneManager,
         world: &mut World,
         resources: &Resources,
+        input_backend: &mut WinitInputBackend,
+        shortcut_key_filter: &mut ShortcutKeyFilter,
+        is_replaying: bool,
         event: &winit::event::Event<()>,
         _window: &winit::window::Window,
     ) -> bool {
@@ -710,56 +1257,64 @@ impl ApiaryApp {
         #[cfg(not(feature = "egui"))]
         let ignore_event = false;
 
-        if !ignore_event {
-            //log::trace!("{:?}", event);
-            let mut was_handled = false;
-            match event {
-                //
-                // Halt if the user requests to close the window
-                //
-                Event::WindowEvent {
-                    event: WindowEvent::CloseRequested,
-                    ..
-                } => return false,
-
-                //
-                // Close if the escape key is hit
-                //
-                Event::WindowEvent {
-                    event:
-                        WindowEvent::KeyboardInput {
-                            input:
-                                KeyboardInput {
-                                    state: ElementState::Pressed,
-                                    virtual_keycode: Some(virtual_keycode),
-                                    ..
-                                },
-                            ..
-                        },
-                    ..
-                } => {
-                    //log::trace!("Key Down {:?} {:?}", keycode, modifiers);
-                    if *virtual_keycode == VirtualKeyCode::Escape {
-                        return false;
-                    }
+        //
+        // Halt if the user requests to close the window
+        //
+        if let Event::WindowEvent {
+            event: WindowEvent::CloseRequested,
+            ..
+        } = event
+        {
+            return false;
+        }
 
-                    if *virtual_keycode == VirtualKeyCode::M {
-                        let metrics = resources.get::<AssetManager>().unwrap().metrics();
-                        println!("{:#?}", metrics);
-                        was_handled = true;
-                    }
-                }
-                _ => {}
+        // Give the cursor back to the OS while the window is in the background
+        if let Event::WindowEvent {
+            event: WindowEvent::Focused(focused),
+            ..
+        } = event
+        {
+            let mut cursor_resource = resources.get_mut::<CursorResource>().unwrap();
+            cursor_resource.set_window_focused(*focused);
+
+            let mut frame_pacer = resources.get_mut::<FramePacer>().unwrap();
+            frame_pacer.set_window_focused(*focused);
+        }
+
+        // Drop to the low-power frame rate while minimized, the window has no area then
+        if let Event::WindowEvent {
+            event: WindowEvent::Resized(size),
+            ..
+        } = event
+        {
+            let mut frame_pacer = resources.get_mut::<FramePacer>().unwrap();
+            frame_pacer.set_window_minimized(size.width == 0 || size.height == 0);
+        }
+
+        // Key presses that trigger a shortcut are handled by the app and aren't passed to the
+        // scene, and neither are their releases. Action bindings separately ignore keys pressed
+        // with Ctrl, Alt or Logo held, so chords like Ctrl+S don't also move the camera.
+        let is_shortcut = match input::winit_event_to_input_event(event) {
+            Some(input_event) => {
+                let input_resource = resources.get::<InputResource>().unwrap();
+                shortcut_key_filter.is_shortcut_event(
+                    &input_event,
+                    input_backend.modifiers(),
+                    input_resource.shortcuts(),
+                )
             }
+            None => false,
+        };
 
-            if !was_handled {
+        // Live input is ignored while replaying so the recorded stream is reproduced exactly
+        if !is_replaying {
+            if !ignore_event && !is_shortcut {
                 scene_manager.process_input(world, resources, event);
-
-                {
-                    let mut input_resource = resources.get_mut::<InputResource>().unwrap();
-                    input::handle_winit_event(event, &mut *input_resource);
-                }
             }
+
+            // Applied to InputResource at the start of the next update. Which layers see each
+            // event is decided by the input context stack, egui's context sits on top of it.
+            input_backend.handle_winit_event(event);
         }
 
         true
@@ -768,6 +1323,20 @@ impl ApiaryApp {
 
 impl Drop for ApiaryApp {
     fn drop(&mut self) {
+        if let Some(input_recorder) = &self.input_recorder {
+            match input_recorder.save() {
+                Ok(()) => log::info!("Saved input recording to {:?}", input_recorder.path()),
+                Err(e) => log::error!(
+                    "Failed to save input recording to {:?}: {:?}",
+                    input_recorder.path(),
+                    e
+                ),
+            }
+        }
+
+        self.scene_manager
+            .cleanup_all_scenes(&mut self.world, &self.resources);
+
         rendering_destroy(&mut self.resources).unwrap()
     }
 }
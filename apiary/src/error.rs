@@ -6,6 +6,8 @@ pub enum ApiaryError {
     StringError(String),
     IoError(Arc<std::io::Error>),
     RafxError(RafxError),
+    RonError(ron::Error),
+    BincodeError(Arc<bincode::ErrorKind>),
 }
 
 impl From<RafxError> for ApiaryError {
@@ -14,4 +16,22 @@ impl From<RafxError> for ApiaryError {
     }
 }
 
+impl From<std::io::Error> for ApiaryError {
+    fn from(e: std::io::Error) -> Self {
+        ApiaryError::IoError(Arc::new(e))
+    }
+}
+
+impl From<ron::Error> for ApiaryError {
+    fn from(e: ron::Error) -> Self {
+        ApiaryError::RonError(e)
+    }
+}
+
+impl From<bincode::Error> for ApiaryError {
+    fn from(e: bincode::Error) -> Self {
+        ApiaryError::BincodeError(Arc::from(e))
+    }
+}
+
 pub type ApiaryResult<T> = Result<T, ApiaryError>;
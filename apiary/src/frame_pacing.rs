@@ -0,0 +1,210 @@
+use std::time::{Duration, Instant};
+
+use crate::time::ClockResource;
+
+/// Limits how often frames are started. Frames are capped to `target_fps` while the window is in
+/// use, and to `low_power_fps` while it is unfocused or minimized. Waiting sleeps for most of the
+/// remaining time and spins for the last `spin_threshold` so frames start on time despite coarse
+/// OS sleep granularity. Spinning is skipped in low-power mode. Time is read from the
+/// `ClockResource` so pacing can be driven by a `ManualClock`.
+pub struct FramePacer {
+    target_fps: Option<f32>,
+    low_power_fps: Option<f32>,
+    spin_threshold: Duration,
+    window_focused: bool,
+    window_minimized: bool,
+    next_frame_deadline: Option<Instant>,
+}
+
+impl FramePacer {
+    pub const DEFAULT_SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+
+    /// `None` leaves the frame rate uncapped in that mode
+    pub fn new(
+        target_fps: Option<f32>,
+        low_power_fps: Option<f32>,
+    ) -> Self {
+        FramePacer {
+            target_fps,
+            low_power_fps,
+            spin_threshold: Self::DEFAULT_SPIN_THRESHOLD,
+            window_focused: true,
+            window_minimized: false,
+            next_frame_deadline: None,
+        }
+    }
+
+    pub fn target_fps(&self) -> Option<f32> {
+        self.target_fps
+    }
+
+    pub fn set_target_fps(
+        &mut self,
+        target_fps: Option<f32>,
+    ) {
+        self.target_fps = target_fps;
+    }
+
+    pub fn low_power_fps(&self) -> Option<f32> {
+        self.low_power_fps
+    }
+
+    pub fn set_low_power_fps(
+        &mut self,
+        low_power_fps: Option<f32>,
+    ) {
+        self.low_power_fps = low_power_fps;
+    }
+
+    pub fn spin_threshold(&self) -> Duration {
+        self.spin_threshold
+    }
+
+    pub fn set_spin_threshold(
+        &mut self,
+        spin_threshold: Duration,
+    ) {
+        self.spin_threshold = spin_threshold;
+    }
+
+    pub fn set_window_focused(
+        &mut self,
+        window_focused: bool,
+    ) {
+        self.window_focused = window_focused;
+    }
+
+    pub fn set_window_minimized(
+        &mut self,
+        window_minimized: bool,
+    ) {
+        self.window_minimized = window_minimized;
+    }
+
+    /// True while the window is unfocused or minimized
+    pub fn is_low_power(&self) -> bool {
+        !self.window_focused || self.window_minimized
+    }
+
+    /// The frame rate cap currently in effect, None if uncapped
+    pub fn effective_target_fps(&self) -> Option<f32> {
+        let fps = if self.is_low_power() {
+            self.low_power_fps
+        } else {
+            self.target_fps
+        };
+
+        fps.filter(|fps| *fps > 0.0)
+    }
+
+    /// Block until the next frame should start. Call once per frame before starting it.
+    pub fn wait_for_next_frame(
+        &mut self,
+        clock: &ClockResource,
+    ) {
+        let target_fps = match self.effective_target_fps() {
+            Some(target_fps) => target_fps,
+            None => {
+                self.next_frame_deadline = None;
+                return;
+            }
+        };
+
+        let frame_duration = Duration::from_secs_f32(1.0 / target_fps);
+        let now = clock.now();
+
+        // Deadlines advance by exactly one frame so the average rate doesn't drift, but if we
+        // have fallen more than a frame behind, start over rather than rushing to catch up
+        let deadline = match self.next_frame_deadline {
+            Some(deadline) if deadline + frame_duration > now => deadline,
+            _ => now,
+        };
+
+        // Spinning costs power, so just sleep in low-power mode
+        let spin_threshold = if self.is_low_power() {
+            Duration::default()
+        } else {
+            self.spin_threshold
+        };
+
+        clock.wait_until(deadline, spin_threshold);
+        self.next_frame_deadline = Some(deadline + frame_duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::ManualClock;
+    use std::sync::Arc;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn waits_until_the_next_frame_deadline() {
+        let manual_clock = Arc::new(ManualClock::new());
+        let clock = ClockResource::new(manual_clock.clone());
+        let start = clock.now();
+        let mut frame_pacer = FramePacer::new(Some(4.0), None);
+
+        // The first frame starts straight away
+        frame_pacer.wait_for_next_frame(&clock);
+        assert_eq!(clock.now(), start);
+
+        manual_clock.advance(ms(100));
+        frame_pacer.wait_for_next_frame(&clock);
+        assert_eq!(clock.now(), start + ms(250));
+
+        // Deadlines advance by a frame even if the frame started a little late
+        manual_clock.advance(ms(300));
+        frame_pacer.wait_for_next_frame(&clock);
+        assert_eq!(clock.now(), start + ms(550));
+        manual_clock.advance(ms(100));
+        frame_pacer.wait_for_next_frame(&clock);
+        assert_eq!(clock.now(), start + ms(750));
+    }
+
+    #[test]
+    fn falling_behind_restarts_the_schedule() {
+        let manual_clock = Arc::new(ManualClock::new());
+        let clock = ClockResource::new(manual_clock.clone());
+        let start = clock.now();
+        let mut frame_pacer = FramePacer::new(Some(4.0), None);
+
+        frame_pacer.wait_for_next_frame(&clock);
+        manual_clock.advance(ms(600));
+        frame_pacer.wait_for_next_frame(&clock);
+        assert_eq!(clock.now(), start + ms(600));
+
+        frame_pacer.wait_for_next_frame(&clock);
+        assert_eq!(clock.now(), start + ms(850));
+    }
+
+    #[test]
+    fn low_power_and_uncapped_rates() {
+        let manual_clock = Arc::new(ManualClock::new());
+        let clock = ClockResource::new(manual_clock.clone());
+        let start = clock.now();
+        let mut frame_pacer = FramePacer::new(None, Some(2.0));
+
+        frame_pacer.wait_for_next_frame(&clock);
+        frame_pacer.wait_for_next_frame(&clock);
+        assert_eq!(clock.now(), start);
+        assert_eq!(frame_pacer.effective_target_fps(), None);
+
+        frame_pacer.set_window_focused(false);
+        assert!(frame_pacer.is_low_power());
+        assert_eq!(frame_pacer.effective_target_fps(), Some(2.0));
+        frame_pacer.wait_for_next_frame(&clock);
+        frame_pacer.wait_for_next_frame(&clock);
+        assert_eq!(clock.now(), start + ms(500));
+
+        frame_pacer.set_window_focused(true);
+        frame_pacer.set_window_minimized(true);
+        assert!(frame_pacer.is_low_power());
+        frame_pacer.set_window_minimized(false);
+        assert!(!frame_pacer.is_low_power());
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::{InputBindings, InputState};
+
+/// Shapes the magnitude of an axis after the dead zone is removed. The sign of the value is
+/// preserved.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ResponseCurve {
+    Linear,
+    /// Raise the magnitude to the given power. Values above 1.0 give finer control near the
+    /// center of a stick, values below 1.0 make small movements more responsive.
+    Power(f32),
+    /// Ease in and out, expects magnitudes in the 0.0..=1.0 range
+    SmoothStep,
+}
+
+impl ResponseCurve {
+    pub fn apply(
+        &self,
+        value: f32,
+    ) -> f32 {
+        let magnitude = value.abs();
+        let shaped = match *self {
+            ResponseCurve::Linear => magnitude,
+            ResponseCurve::Power(exponent) => magnitude.powf(exponent),
+            ResponseCurve::SmoothStep => {
+                let t = magnitude.clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            }
+        };
+
+        shaped.copysign(value)
+    }
+}
+
+/// How the summed bindings of a virtual axis are turned into its final value. Processing happens
+/// in field order: dead zone, response curve, sensitivity, inversion, then smoothing.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisSettings {
+    /// Raw values with a magnitude at or below this are treated as 0.0. The remaining range is
+    /// rescaled so that a raw value of 1.0 still produces 1.0.
+    pub dead_zone: f32,
+    pub response_curve: ResponseCurve,
+    /// Multiplier applied after the response curve
+    pub sensitivity: f32,
+    pub invert: bool,
+    /// Time constant of exponential smoothing in seconds, 0.0 disables smoothing. Roughly how long
+    /// the axis takes to cover two thirds of the way to a new value.
+    pub smoothing: f32,
+    /// If set, the final value is clamped to -max..=max
+    pub max_magnitude: Option<f32>,
+}
+
+impl Default for AxisSettings {
+    fn default() -> Self {
+        AxisSettings {
+            dead_zone: 0.0,
+            response_curve: ResponseCurve::Linear,
+            sensitivity: 1.0,
+            invert: false,
+            smoothing: 0.0,
+            max_magnitude: None,
+        }
+    }
+}
+
+impl AxisSettings {
+    /// Apply everything except smoothing, which requires state from previous frames
+    pub fn apply(
+        &self,
+        raw: f32,
+    ) -> f32 {
+        let magnitude = raw.abs();
+        let value = if magnitude <= self.dead_zone {
+            0.0
+        } else if self.dead_zone > 0.0 && self.dead_zone < 1.0 {
+            ((magnitude - self.dead_zone) / (1.0 - self.dead_zone)).copysign(raw)
+        } else {
+            raw
+        };
+
+        let mut value = self.response_curve.apply(value) * self.sensitivity;
+        if self.invert {
+            value = -value;
+        }
+
+        if let Some(max_magnitude) = self.max_magnitude {
+            value = value.clamp(-max_magnitude, max_magnitude);
+        }
+
+        value
+    }
+
+    /// Move `previous` towards `target` according to the smoothing time constant
+    pub fn smooth(
+        &self,
+        previous: f32,
+        target: f32,
+        dt: f32,
+    ) -> f32 {
+        if self.smoothing <= 0.0 {
+            target
+        } else {
+            previous + (target - previous) * (1.0 - (-dt / self.smoothing).exp())
+        }
+    }
+}
+
+/// Smoothed values of the virtual axes defined in `InputBindings`. Call `update` once per frame
+/// after input has been processed.
+#[derive(Default)]
+pub struct VirtualAxes {
+    values: HashMap<String, f32>,
+}
+
+impl VirtualAxes {
+    pub fn update(
+        &mut self,
+        bindings: &InputBindings,
+        input_state: &InputState,
+        dt: f32,
+    ) {
+        self.values.retain(|axis, _| bindings.has_axis(axis));
+
+        for axis in bindings.axis_names() {
+            let target = bindings.axis_value(axis, input_state);
+            let settings = bindings.axis_settings(axis);
+            let value = self.values.entry(axis.to_string()).or_insert(target);
+            *value = settings.smooth(*value, target, dt);
+        }
+    }
+
+    /// Smoothed value of the named axis as of the last update, None if the axis wasn't bound
+    pub fn value(
+        &self,
+        axis: &str,
+    ) -> Option<f32> {
+        self.values.get(axis).copied()
+    }
+
+    /// Forget smoothing history, for example after teleporting the camera
+    pub fn reset(&mut self) {
+        self.values.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(
+        actual: f32,
+        expected: f32,
+    ) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn dead_zone_zeroes_and_rescales() {
+        let settings = AxisSettings {
+            dead_zone: 0.2,
+            ..Default::default()
+        };
+
+        assert_eq!(settings.apply(0.1), 0.0);
+        assert_eq!(settings.apply(-0.2), 0.0);
+        assert_near(settings.apply(0.6), 0.5);
+        assert_near(settings.apply(-0.6), -0.5);
+        assert_near(settings.apply(1.0), 1.0);
+    }
+
+    #[test]
+    fn response_curve_preserves_sign() {
+        let settings = AxisSettings {
+            response_curve: ResponseCurve::Power(2.0),
+            ..Default::default()
+        };
+        assert_near(settings.apply(0.5), 0.25);
+        assert_near(settings.apply(-0.5), -0.25);
+
+        let settings = AxisSettings {
+            response_curve: ResponseCurve::SmoothStep,
+            ..Default::default()
+        };
+        assert_near(settings.apply(0.5), 0.5);
+        assert_near(settings.apply(-0.25), -0.15625);
+        assert_near(settings.apply(2.0), 1.0);
+    }
+
+    #[test]
+    fn sensitivity_invert_and_max_magnitude() {
+        let settings = AxisSettings {
+            sensitivity: 3.0,
+            invert: true,
+            max_magnitude: Some(2.0),
+            ..Default::default()
+        };
+
+        assert_near(settings.apply(0.5), -1.5);
+        assert_near(settings.apply(-0.5), 1.5);
+        assert_near(settings.apply(1.0), -2.0);
+    }
+
+    #[test]
+    fn smoothing_approaches_target() {
+        let settings = AxisSettings::default();
+        assert_eq!(settings.smooth(0.0, 1.0, 0.1), 1.0);
+
+        let settings = AxisSettings {
+            smoothing: 0.5,
+            ..Default::default()
+        };
+
+        // After one time constant the value covers 1 - 1/e of the way
+        assert_near(settings.smooth(0.0, 1.0, 0.5), 1.0 - (-1.0f32).exp());
+
+        // Smaller steps add up to the same result
+        let mut value = 0.0;
+        for _ in 0..10 {
+            value = settings.smooth(value, 1.0, 0.05);
+        }
+        assert_near(value, 1.0 - (-1.0f32).exp());
+
+        let mut value = 1.0;
+        for _ in 0..100 {
+            value = settings.smooth(value, -1.0, 0.1);
+        }
+        assert_near(value, -1.0);
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::input::{
+    ButtonState, InputEvent, KeyboardKey, MouseButton, PhysicalKey, TimestampedInputEvent,
+};
+
+/// A source of input events. `WinitInputBackend` converts events from a winit window, the gamepad
+/// backends report controllers, and `ScriptedInputBackend` plays back a sequence built in code so
+/// input handling can be exercised without a window. Feed each backend into
+/// `InputResource::poll_backend` once per frame.
+pub trait InputBackend {
+    /// Append all events that occurred since the previous poll, in the order they occurred
+    fn poll_events(
+        &mut self,
+        events: &mut Vec<TimestampedInputEvent>,
+    );
+}
+
+/// A backend that plays back a script of events, one scripted frame per poll. Build the script
+/// with the helper methods, calling `end_frame` between frames. Timestamps are taken from a
+/// simulated clock that starts at `start` and only moves when `wait` is called, so
+/// timing-sensitive input such as double clicks can be scripted precisely.
+pub struct ScriptedInputBackend {
+    start: Instant,
+    elapsed: Duration,
+    frames: VecDeque<Vec<TimestampedInputEvent>>,
+    current_frame: Vec<TimestampedInputEvent>,
+}
+
+impl ScriptedInputBackend {
+    /// `start` is the timestamp of the first scripted events, normally `ClockResource::now`
+    pub fn new(start: Instant) -> Self {
+        ScriptedInputBackend {
+            start,
+            elapsed: Duration::default(),
+            frames: VecDeque::default(),
+            current_frame: Vec::default(),
+        }
+    }
+
+    /// Add an event to the frame being scripted
+    pub fn push_event(
+        &mut self,
+        event: InputEvent,
+    ) -> &mut Self {
+        self.current_frame.push(TimestampedInputEvent {
+            timestamp: self.start + self.elapsed,
+            event,
+        });
+        self
+    }
+
+    /// Advance the simulated clock used to timestamp events that are pushed after this
+    pub fn wait(
+        &mut self,
+        duration: Duration,
+    ) -> &mut Self {
+        self.elapsed += duration;
+        self
+    }
+
+    /// Finish the frame being scripted. Each call produces one poll's worth of events, so calling
+    /// this twice in a row scripts a frame with no input.
+    pub fn end_frame(&mut self) -> &mut Self {
+        self.frames
+            .push_back(std::mem::take(&mut self.current_frame));
+        self
+    }
+
+    pub fn press_key(
+        &mut self,
+        key: KeyboardKey,
+    ) -> &mut Self {
+        self.push_event(InputEvent::Keyboard {
+            key,
+            state: ButtonState::Pressed,
+        })
+    }
+
+    pub fn release_key(
+        &mut self,
+        key: KeyboardKey,
+    ) -> &mut Self {
+        self.push_event(InputEvent::Keyboard {
+            key,
+            state: ButtonState::Released,
+        })
+    }
+
+    pub fn press_physical_key(
+        &mut self,
+        key: PhysicalKey,
+    ) -> &mut Self {
+        self.push_event(InputEvent::PhysicalKey {
+            key,
+            state: ButtonState::Pressed,
+        })
+    }
+
+    pub fn release_physical_key(
+        &mut self,
+        key: PhysicalKey,
+    ) -> &mut Self {
+        self.push_event(InputEvent::PhysicalKey {
+            key,
+            state: ButtonState::Released,
+        })
+    }
+
+    /// Press and release a key within the current frame
+    pub fn tap_key(
+        &mut self,
+        key: KeyboardKey,
+    ) -> &mut Self {
+        self.press_key(key).release_key(key)
+    }
+
+    /// Move the cursor to a position in physical pixels
+    pub fn move_mouse_to(
+        &mut self,
+        position: glam::Vec2,
+    ) -> &mut Self {
+        self.push_event(InputEvent::MousePosition(position))
+    }
+
+    /// Raw mouse motion, as used for mouse-look
+    pub fn move_mouse_by(
+        &mut self,
+        delta: glam::Vec2,
+    ) -> &mut Self {
+        self.push_event(InputEvent::MouseMotion(delta))
+    }
+
+    pub fn press_mouse(
+        &mut self,
+        button: MouseButton,
+    ) -> &mut Self {
+        self.push_event(InputEvent::MouseButton {
+            button,
+            state: ButtonState::Pressed,
+        })
+    }
+
+    pub fn release_mouse(
+        &mut self,
+        button: MouseButton,
+    ) -> &mut Self {
+        self.push_event(InputEvent::MouseButton {
+            button,
+            state: ButtonState::Released,
+        })
+    }
+
+    /// Press and release a mouse button within the current frame
+    pub fn click(
+        &mut self,
+        button: MouseButton,
+    ) -> &mut Self {
+        self.press_mouse(button).release_mouse(button)
+    }
+
+    /// Type each character of the string
+    pub fn type_text(
+        &mut self,
+        text: &str,
+    ) -> &mut Self {
+        for c in text.chars() {
+            self.push_event(InputEvent::Character(c));
+        }
+        self
+    }
+
+    /// Returns true once every scripted frame has been polled
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+impl InputBackend for ScriptedInputBackend {
+    fn poll_events(
+        &mut self,
+        events: &mut Vec<TimestampedInputEvent>,
+    ) {
+        if let Some(frame) = self.frames.pop_front() {
+            events.extend(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{AxisBinding, AxisSource, ButtonBinding, InputResource, TextInputEvent};
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn input_resource() -> InputResource {
+        let mut input_resource = InputResource::new();
+        let bindings = input_resource.bindings_mut();
+        bindings.bind_action("jump", ButtonBinding::Key(KeyboardKey::Space));
+        bindings.bind_action("fire", ButtonBinding::Mouse(MouseButton::LEFT));
+        bindings.bind_axis(
+            "move_right",
+            AxisBinding::new(AxisSource::Buttons {
+                negative: ButtonBinding::Physical(PhysicalKey::KeyA),
+                positive: ButtonBinding::Physical(PhysicalKey::KeyD),
+            }),
+        );
+        bindings.bind_axis("look_x", AxisBinding::new(AxisSource::MouseMotionX));
+        input_resource
+    }
+
+    // Apply the next scripted frame and update derived state the way the app does each frame
+    fn poll(
+        input_resource: &mut InputResource,
+        backend: &mut ScriptedInputBackend,
+    ) {
+        input_resource.end_frame();
+        input_resource.poll_backend(backend);
+        input_resource.update_axes(DT);
+    }
+
+    #[test]
+    fn keys_drive_actions_and_axes() {
+        let mut backend = ScriptedInputBackend::new(Instant::now());
+        backend
+            .press_key(KeyboardKey::Space)
+            .press_physical_key(PhysicalKey::KeyD)
+            .end_frame()
+            .end_frame()
+            .release_key(KeyboardKey::Space)
+            .release_physical_key(PhysicalKey::KeyD)
+            .press_physical_key(PhysicalKey::KeyA)
+            .end_frame()
+            .tap_key(KeyboardKey::Space)
+            .end_frame();
+
+        let mut input_resource = input_resource();
+
+        poll(&mut input_resource, &mut backend);
+        assert!(input_resource.is_key_just_down(KeyboardKey::Space));
+        assert!(input_resource.action_pressed("jump"));
+        assert!(input_resource.action_down("jump"));
+        assert_eq!(input_resource.axis_value("move_right"), 1.0);
+
+        // Held without new events
+        poll(&mut input_resource, &mut backend);
+        assert!(!input_resource.is_key_just_down(KeyboardKey::Space));
+        assert!(input_resource.is_key_down(KeyboardKey::Space));
+        assert!(!input_resource.action_pressed("jump"));
+        assert!(input_resource.action_down("jump"));
+        assert_eq!(input_resource.axis_value("move_right"), 1.0);
+
+        poll(&mut input_resource, &mut backend);
+        assert!(input_resource.is_key_just_up(KeyboardKey::Space));
+        assert!(input_resource.action_released("jump"));
+        assert!(!input_resource.action_down("jump"));
+        assert_eq!(input_resource.axis_value("move_right"), -1.0);
+
+        // A tap within one frame still presses the action
+        poll(&mut input_resource, &mut backend);
+        assert!(input_resource.is_key_just_down(KeyboardKey::Space));
+        assert!(input_resource.action_pressed("jump"));
+        assert!(!input_resource.action_down("jump"));
+
+        assert!(backend.is_finished());
+        poll(&mut input_resource, &mut backend);
+        assert!(!input_resource.action_pressed("jump"));
+    }
+
+    #[test]
+    fn clicks_use_scripted_timestamps() {
+        let mut backend = ScriptedInputBackend::new(Instant::now());
+        backend
+            .move_mouse_to(glam::Vec2::new(100.0, 50.0))
+            .click(MouseButton::LEFT)
+            .end_frame()
+            .wait(Duration::from_millis(100))
+            .click(MouseButton::LEFT)
+            .end_frame()
+            .wait(Duration::from_secs(2))
+            .click(MouseButton::LEFT)
+            .end_frame();
+
+        let mut input_resource = input_resource();
+
+        poll(&mut input_resource, &mut backend);
+        assert!(input_resource.is_mouse_just_down(MouseButton::LEFT));
+        assert!(input_resource.action_pressed("fire"));
+        assert_eq!(input_resource.mouse_click_count(MouseButton::LEFT), 1);
+        assert_eq!(
+            input_resource.mouse_button_just_clicked_position(MouseButton::LEFT),
+            Some(glam::Vec2::new(100.0, 50.0))
+        );
+
+        poll(&mut input_resource, &mut backend);
+        assert_eq!(input_resource.mouse_click_count(MouseButton::LEFT), 2);
+
+        // Too long after the previous click to count towards it
+        poll(&mut input_resource, &mut backend);
+        assert_eq!(input_resource.mouse_click_count(MouseButton::LEFT), 1);
+    }
+
+    #[test]
+    fn mouse_motion_drives_axes() {
+        let mut backend = ScriptedInputBackend::new(Instant::now());
+        backend
+            .move_mouse_by(glam::Vec2::new(3.0, 0.0))
+            .move_mouse_by(glam::Vec2::new(2.0, 1.0))
+            .end_frame()
+            .end_frame();
+
+        let mut input_resource = input_resource();
+
+        poll(&mut input_resource, &mut backend);
+        assert_eq!(input_resource.mouse_motion(), glam::Vec2::new(5.0, 1.0));
+        assert_eq!(input_resource.axis_value("look_x"), 5.0);
+
+        poll(&mut input_resource, &mut backend);
+        assert_eq!(input_resource.axis_value("look_x"), 0.0);
+    }
+
+    #[test]
+    fn typed_text_and_editing_keys() {
+        let mut backend = ScriptedInputBackend::new(Instant::now());
+        backend
+            .type_text("hi!")
+            .tap_key(KeyboardKey::Back)
+            .end_frame();
+
+        let mut input_resource = input_resource();
+        let events = input_resource.poll_backend(&mut backend);
+        assert_eq!(events.len(), 5);
+
+        assert_eq!(input_resource.text_input(), "hi!");
+        assert_eq!(
+            input_resource.text_input_events(),
+            &[
+                TextInputEvent::Char('h'),
+                TextInputEvent::Char('i'),
+                TextInputEvent::Char('!'),
+                TextInputEvent::Backspace,
+            ]
+        );
+    }
+}
@@ -0,0 +1,608 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiaryResult;
+use crate::input::{
+    AxisSettings, GamepadAxis, GamepadButton, InputState, KeyboardKey, MouseButton, PhysicalKey,
+};
+
+/// Direction the mouse wheel can be scrolled in
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WheelDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Something that can be pressed and released. Actions are bound to one or more of these. Keys
+/// pressed while Ctrl, Alt or Logo is held are left to shortcuts, so they don't trigger bindings
+/// until they are released and pressed again.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ButtonBinding {
+    /// A key identified by the character it produces in the current layout
+    Key(KeyboardKey),
+    /// A key identified by its position on the keyboard, use this for movement controls
+    Physical(PhysicalKey),
+    Mouse(MouseButton),
+    /// Considered pressed (and just pressed) during any frame the wheel scrolls in the direction
+    MouseWheel(WheelDirection),
+    /// A button on any connected gamepad
+    Gamepad(GamepadButton),
+}
+
+impl ButtonBinding {
+    fn is_down(
+        &self,
+        input_state: &InputState,
+    ) -> bool {
+        match *self {
+            ButtonBinding::Key(key) => {
+                input_state.is_key_down(key) && !input_state.is_key_chorded(key)
+            }
+            ButtonBinding::Physical(key) => {
+                input_state.is_physical_key_down(key) && !input_state.is_physical_key_chorded(key)
+            }
+            ButtonBinding::Mouse(button) => input_state.is_mouse_down(button),
+            ButtonBinding::MouseWheel(direction) => Self::wheel_moved(direction, input_state),
+            ButtonBinding::Gamepad(button) => input_state.gamepads().is_any_button_down(button),
+        }
+    }
+
+    fn is_just_down(
+        &self,
+        input_state: &InputState,
+    ) -> bool {
+        match *self {
+            ButtonBinding::Key(key) => {
+                input_state.is_key_just_down(key) && !input_state.is_key_chorded(key)
+            }
+            ButtonBinding::Physical(key) => {
+                input_state.is_physical_key_just_down(key)
+                    && !input_state.is_physical_key_chorded(key)
+            }
+            ButtonBinding::Mouse(button) => input_state.is_mouse_just_down(button),
+            ButtonBinding::MouseWheel(direction) => Self::wheel_moved(direction, input_state),
+            ButtonBinding::Gamepad(button) => {
+                input_state.gamepads().is_any_button_just_down(button)
+            }
+        }
+    }
+
+    fn is_just_up(
+        &self,
+        input_state: &InputState,
+    ) -> bool {
+        match *self {
+            ButtonBinding::Key(key) => {
+                input_state.is_key_just_up(key) && !input_state.is_key_chorded(key)
+            }
+            ButtonBinding::Physical(key) => {
+                input_state.is_physical_key_just_up(key)
+                    && !input_state.is_physical_key_chorded(key)
+            }
+            ButtonBinding::Mouse(button) => input_state.is_mouse_just_up(button),
+            ButtonBinding::MouseWheel(_) => false,
+            ButtonBinding::Gamepad(button) => input_state.gamepads().is_any_button_just_up(button),
+        }
+    }
+
+    fn value(
+        &self,
+        input_state: &InputState,
+    ) -> f32 {
+        if self.is_down(input_state) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn wheel_moved(
+        direction: WheelDirection,
+        input_state: &InputState,
+    ) -> bool {
+        let delta = input_state.mouse_wheel_delta();
+        match direction {
+            WheelDirection::Up => delta.y > 0.0,
+            WheelDirection::Down => delta.y < 0.0,
+            WheelDirection::Left => delta.x < 0.0,
+            WheelDirection::Right => delta.x > 0.0,
+        }
+    }
+}
+
+/// Where the value of an axis binding comes from
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AxisSource {
+    /// -1.0 while `negative` is held, +1.0 while `positive` is held, 0.0 if both or neither
+    Buttons {
+        negative: ButtonBinding,
+        positive: ButtonBinding,
+    },
+    /// Raw mouse motion this frame along X
+    MouseMotionX,
+    /// Raw mouse motion this frame along Y
+    MouseMotionY,
+    /// Wheel scroll this frame along X
+    MouseWheelX,
+    /// Wheel scroll this frame along Y
+    MouseWheelY,
+    /// Analog gamepad input summed over all connected pads, with dead zones applied
+    GamepadAxis(GamepadAxis),
+}
+
+/// A single input contributing to a named axis
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    pub source: AxisSource,
+    /// Multiplier applied to the raw value. Use a negative value to invert the axis.
+    #[serde(default = "AxisBinding::default_scale")]
+    pub scale: f32,
+}
+
+impl AxisBinding {
+    pub fn new(source: AxisSource) -> Self {
+        AxisBinding {
+            source,
+            scale: Self::default_scale(),
+        }
+    }
+
+    pub fn with_scale(
+        source: AxisSource,
+        scale: f32,
+    ) -> Self {
+        AxisBinding { source, scale }
+    }
+
+    fn default_scale() -> f32 {
+        1.0
+    }
+
+    fn value(
+        &self,
+        input_state: &InputState,
+    ) -> f32 {
+        let raw = match self.source {
+            AxisSource::Buttons { negative, positive } => {
+                positive.value(input_state) - negative.value(input_state)
+            }
+            AxisSource::MouseMotionX => input_state.mouse_motion().x,
+            AxisSource::MouseMotionY => input_state.mouse_motion().y,
+            AxisSource::MouseWheelX => input_state.mouse_wheel_delta().x,
+            AxisSource::MouseWheelY => input_state.mouse_wheel_delta().y,
+            AxisSource::GamepadAxis(axis) => input_state.gamepads().any_axis(axis),
+        };
+
+        raw * self.scale
+    }
+}
+
+/// Maps named actions (buttons) and axes onto physical inputs. This is normally loaded from a RON
+/// file so controls can be changed without recompiling.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputBindings {
+    #[serde(default)]
+    actions: HashMap<String, Vec<ButtonBinding>>,
+    #[serde(default)]
+    axes: HashMap<String, Vec<AxisBinding>>,
+    /// Processing applied to the summed bindings of an axis. Axes without an entry use the
+    /// defaults, which pass the raw value through unchanged.
+    #[serde(default)]
+    axis_settings: HashMap<String, AxisSettings>,
+}
+
+impl InputBindings {
+    /// Parse bindings from a RON string
+    pub fn from_ron_str(ron: &str) -> ApiaryResult<Self> {
+        Ok(ron::de::from_str(ron)?)
+    }
+
+    /// Load bindings from a RON file on disk
+    pub fn load(path: &Path) -> ApiaryResult<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::from_ron_str(&contents)
+    }
+
+    /// Serialize the bindings to a human-readable RON string
+    pub fn to_ron_string(&self) -> ApiaryResult<String> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    /// Add a binding to the named action, creating the action if it doesn't exist
+    pub fn bind_action(
+        &mut self,
+        action: &str,
+        binding: ButtonBinding,
+    ) {
+        self.actions
+            .entry(action.to_string())
+            .or_default()
+            .push(binding);
+    }
+
+    /// Add a binding to the named axis, creating the axis if it doesn't exist
+    pub fn bind_axis(
+        &mut self,
+        axis: &str,
+        binding: AxisBinding,
+    ) {
+        self.axes.entry(axis.to_string()).or_default().push(binding);
+    }
+
+    /// Set how the named axis is processed
+    pub fn set_axis_settings(
+        &mut self,
+        axis: &str,
+        settings: AxisSettings,
+    ) {
+        self.axis_settings.insert(axis.to_string(), settings);
+    }
+
+    /// How the named axis is processed, the defaults if nothing was configured
+    pub fn axis_settings(
+        &self,
+        axis: &str,
+    ) -> AxisSettings {
+        self.axis_settings.get(axis).copied().unwrap_or_default()
+    }
+
+    /// Names of all axes that have bindings
+    pub fn axis_names(&self) -> impl Iterator<Item = &str> {
+        self.axes.keys().map(String::as_str)
+    }
+
+    /// Returns true if the named axis has bindings
+    pub fn has_axis(
+        &self,
+        axis: &str,
+    ) -> bool {
+        self.axes.contains_key(axis)
+    }
+
+    /// Remove all bindings for the named action
+    pub fn clear_action(
+        &mut self,
+        action: &str,
+    ) {
+        self.actions.remove(action);
+    }
+
+    /// Remove all bindings for the named axis
+    pub fn clear_axis(
+        &mut self,
+        axis: &str,
+    ) {
+        self.axes.remove(axis);
+    }
+
+    /// Bindings for the named action, empty if the action is unknown
+    pub fn action_bindings(
+        &self,
+        action: &str,
+    ) -> &[ButtonBinding] {
+        self.actions.get(action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Bindings for the named axis, empty if the axis is unknown
+    pub fn axis_bindings(
+        &self,
+        axis: &str,
+    ) -> &[AxisBinding] {
+        self.axes.get(axis).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Returns true if any input bound to the action is down
+    pub fn action_down(
+        &self,
+        action: &str,
+        input_state: &InputState,
+    ) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|binding| binding.is_down(input_state))
+    }
+
+    /// Returns true if any input bound to the action went down during this frame
+    pub fn action_pressed(
+        &self,
+        action: &str,
+        input_state: &InputState,
+    ) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|binding| binding.is_just_down(input_state))
+    }
+
+    /// Returns true if an input bound to the action went up during this frame and no other input
+    /// is still holding the action down
+    pub fn action_released(
+        &self,
+        action: &str,
+        input_state: &InputState,
+    ) -> bool {
+        let bindings = self.action_bindings(action);
+        bindings
+            .iter()
+            .any(|binding| binding.is_just_up(input_state))
+            && !bindings.iter().any(|binding| binding.is_down(input_state))
+    }
+
+    /// Sum of all inputs bound to the axis. Button-driven axes are in the -1.0..=1.0 range per
+    /// binding, mouse-driven axes are in raw device units.
+    pub fn raw_axis_value(
+        &self,
+        axis: &str,
+        input_state: &InputState,
+    ) -> f32 {
+        self.axis_bindings(axis)
+            .iter()
+            .map(|binding| binding.value(input_state))
+            .sum()
+    }
+
+    /// Value of the axis with its `AxisSettings` applied, except for smoothing. Use
+    /// `VirtualAxes` (or `InputResource::axis_value`) for smoothed values.
+    pub fn axis_value(
+        &self,
+        axis: &str,
+        input_state: &InputState,
+    ) -> f32 {
+        self.axis_settings(axis)
+            .apply(self.raw_axis_value(axis, input_state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{ButtonState, InputEvent, KeyModifiers, MouseScrollDelta};
+    use std::time::Instant;
+
+    fn physical_key(
+        input_state: &mut InputState,
+        key: PhysicalKey,
+        state: ButtonState,
+    ) {
+        input_state.handle_event_at(&InputEvent::PhysicalKey { key, state }, Instant::now());
+    }
+
+    fn jump_bindings() -> InputBindings {
+        let mut bindings = InputBindings::default();
+        bindings.bind_action("jump", ButtonBinding::Physical(PhysicalKey::Space));
+        bindings.bind_action("jump", ButtonBinding::Mouse(MouseButton::LEFT));
+        bindings
+    }
+
+    #[test]
+    fn action_pressed_held_and_released() {
+        let bindings = jump_bindings();
+        let mut input_state = InputState::new();
+        assert!(!bindings.action_down("jump", &input_state));
+        assert!(!bindings.action_down("unknown", &input_state));
+
+        physical_key(&mut input_state, PhysicalKey::Space, ButtonState::Pressed);
+        assert!(bindings.action_down("jump", &input_state));
+        assert!(bindings.action_pressed("jump", &input_state));
+        input_state.end_frame();
+
+        assert!(bindings.action_down("jump", &input_state));
+        assert!(!bindings.action_pressed("jump", &input_state));
+
+        physical_key(&mut input_state, PhysicalKey::Space, ButtonState::Released);
+        assert!(!bindings.action_down("jump", &input_state));
+        assert!(bindings.action_released("jump", &input_state));
+        input_state.end_frame();
+        assert!(!bindings.action_released("jump", &input_state));
+    }
+
+    #[test]
+    fn action_is_not_released_while_another_binding_holds_it() {
+        let bindings = jump_bindings();
+        let mut input_state = InputState::new();
+
+        physical_key(&mut input_state, PhysicalKey::Space, ButtonState::Pressed);
+        input_state.handle_event_at(
+            &InputEvent::MouseButton {
+                button: MouseButton::LEFT,
+                state: ButtonState::Pressed,
+            },
+            Instant::now(),
+        );
+        input_state.end_frame();
+
+        physical_key(&mut input_state, PhysicalKey::Space, ButtonState::Released);
+        assert!(bindings.action_down("jump", &input_state));
+        assert!(!bindings.action_released("jump", &input_state));
+    }
+
+    // Ctrl+S saves the scene, it shouldn't also move the camera back
+    #[test]
+    fn keys_pressed_with_command_modifiers_are_ignored() {
+        let mut bindings = InputBindings::default();
+        bindings.bind_action("back", ButtonBinding::Physical(PhysicalKey::KeyS));
+        bindings.bind_action("back", ButtonBinding::Key(KeyboardKey::S));
+        let mut input_state = InputState::new();
+        let ctrl = KeyModifiers {
+            ctrl: true,
+            ..Default::default()
+        };
+
+        input_state.handle_event_at(&InputEvent::ModifiersChanged(ctrl), Instant::now());
+        input_state.handle_event_at(
+            &InputEvent::Keyboard {
+                key: KeyboardKey::S,
+                state: ButtonState::Pressed,
+            },
+            Instant::now(),
+        );
+        physical_key(&mut input_state, PhysicalKey::KeyS, ButtonState::Pressed);
+        assert!(!bindings.action_pressed("back", &input_state));
+        assert!(!bindings.action_down("back", &input_state));
+        input_state.end_frame();
+
+        // Letting go of Ctrl first doesn't turn the held key into a press
+        input_state.handle_event_at(
+            &InputEvent::ModifiersChanged(KeyModifiers::NONE),
+            Instant::now(),
+        );
+        assert!(!bindings.action_down("back", &input_state));
+
+        input_state.handle_event_at(
+            &InputEvent::Keyboard {
+                key: KeyboardKey::S,
+                state: ButtonState::Released,
+            },
+            Instant::now(),
+        );
+        physical_key(&mut input_state, PhysicalKey::KeyS, ButtonState::Released);
+        assert!(!bindings.action_released("back", &input_state));
+        input_state.end_frame();
+
+        // Shift alone isn't a chord
+        let shift = KeyModifiers {
+            shift: true,
+            ..Default::default()
+        };
+        input_state.handle_event_at(&InputEvent::ModifiersChanged(shift), Instant::now());
+        physical_key(&mut input_state, PhysicalKey::KeyS, ButtonState::Pressed);
+        assert!(bindings.action_pressed("back", &input_state));
+    }
+
+    #[test]
+    fn mouse_wheel_binding_is_pressed_for_the_frame_it_scrolls() {
+        let mut bindings = InputBindings::default();
+        bindings.bind_action("zoom_in", ButtonBinding::MouseWheel(WheelDirection::Up));
+        let mut input_state = InputState::new();
+
+        input_state.handle_event_at(
+            &InputEvent::MouseWheel(MouseScrollDelta::new(0.0, 1.0)),
+            Instant::now(),
+        );
+        assert!(bindings.action_pressed("zoom_in", &input_state));
+        input_state.end_frame();
+        assert!(!bindings.action_down("zoom_in", &input_state));
+    }
+
+    #[test]
+    fn raw_axis_value_sums_bindings() {
+        let mut bindings = InputBindings::default();
+        bindings.bind_axis(
+            "move_right",
+            AxisBinding::new(AxisSource::Buttons {
+                negative: ButtonBinding::Physical(PhysicalKey::KeyA),
+                positive: ButtonBinding::Physical(PhysicalKey::KeyD),
+            }),
+        );
+        bindings.bind_axis(
+            "move_right",
+            AxisBinding::with_scale(AxisSource::MouseMotionX, 0.5),
+        );
+        let mut input_state = InputState::new();
+        assert_eq!(bindings.raw_axis_value("move_right", &input_state), 0.0);
+        assert_eq!(bindings.raw_axis_value("unknown", &input_state), 0.0);
+
+        physical_key(&mut input_state, PhysicalKey::KeyD, ButtonState::Pressed);
+        assert_eq!(bindings.raw_axis_value("move_right", &input_state), 1.0);
+
+        // Opposing buttons cancel out
+        physical_key(&mut input_state, PhysicalKey::KeyA, ButtonState::Pressed);
+        assert_eq!(bindings.raw_axis_value("move_right", &input_state), 0.0);
+
+        input_state.handle_event_at(
+            &InputEvent::MouseMotion(glam::Vec2::new(4.0, 0.0)),
+            Instant::now(),
+        );
+        assert_eq!(bindings.raw_axis_value("move_right", &input_state), 2.0);
+    }
+
+    #[test]
+    fn axis_value_applies_settings() {
+        let mut bindings = InputBindings::default();
+        bindings.bind_axis("look_x", AxisBinding::new(AxisSource::MouseMotionX));
+        bindings.set_axis_settings(
+            "look_x",
+            AxisSettings {
+                sensitivity: 0.1,
+                invert: true,
+                ..Default::default()
+            },
+        );
+        let mut input_state = InputState::new();
+        input_state.handle_event_at(
+            &InputEvent::MouseMotion(glam::Vec2::new(10.0, 0.0)),
+            Instant::now(),
+        );
+
+        assert_eq!(bindings.raw_axis_value("look_x", &input_state), 10.0);
+        assert!((bindings.axis_value("look_x", &input_state) + 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let mut bindings = jump_bindings();
+        bindings.bind_axis(
+            "look_y",
+            AxisBinding::with_scale(AxisSource::GamepadAxis(GamepadAxis::RightStickY), -20.0),
+        );
+        bindings.set_axis_settings(
+            "look_y",
+            AxisSettings {
+                dead_zone: 0.2,
+                ..Default::default()
+            },
+        );
+
+        let ron = bindings.to_ron_string().unwrap();
+        let loaded = InputBindings::from_ron_str(&ron).unwrap();
+        assert_eq!(
+            loaded.action_bindings("jump"),
+            bindings.action_bindings("jump")
+        );
+        assert_eq!(
+            loaded.axis_bindings("look_y"),
+            bindings.axis_bindings("look_y")
+        );
+        assert_eq!(
+            loaded.axis_settings("look_y"),
+            bindings.axis_settings("look_y")
+        );
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let bindings =
+            InputBindings::from_ron_str("(axes: {\"zoom\": [(source: MouseWheelY)]})").unwrap();
+        assert_eq!(
+            bindings.axis_bindings("zoom"),
+            &[AxisBinding::new(AxisSource::MouseWheelY)]
+        );
+        assert!(bindings.action_bindings("jump").is_empty());
+        assert_eq!(bindings.axis_settings("zoom"), AxisSettings::default());
+
+        assert!(InputBindings::from_ron_str("(actions: {\"jump\": [Key(NotAKey)]})").is_err());
+    }
+
+    // The app starts without any controls if this fails to parse
+    #[test]
+    fn shipped_bindings_parse() {
+        let bindings = InputBindings::from_ron_str(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../config/input_bindings.ron"
+        )))
+        .unwrap();
+
+        for action in &["toggle_lock_view", "move_fast"] {
+            assert!(!bindings.action_bindings(action).is_empty(), "{}", action);
+        }
+        for axis in &["move_forward", "move_right", "look_x", "look_y"] {
+            assert!(bindings.has_axis(axis), "{}", axis);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::time::Instant;
+
+use crate::input::{ButtonState, GamepadEvent, InputEvent, InputState, TouchPhase};
+
+/// Which kinds of input a context prevents from reaching the contexts below it.
+///
+/// Releases (keys, mouse buttons, gamepad buttons) and other events that only end something are
+/// never consumed, so a key pressed before a context started consuming doesn't get stuck down in
+/// the contexts underneath it.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct InputConsumption {
+    pub keyboard: bool,
+    pub text: bool,
+    pub mouse_buttons: bool,
+    pub mouse_motion: bool,
+    pub mouse_wheel: bool,
+    pub touch: bool,
+    pub gamepad: bool,
+}
+
+impl InputConsumption {
+    pub const NONE: InputConsumption = InputConsumption {
+        keyboard: false,
+        text: false,
+        mouse_buttons: false,
+        mouse_motion: false,
+        mouse_wheel: false,
+        touch: false,
+        gamepad: false,
+    };
+
+    pub const ALL: InputConsumption = InputConsumption {
+        keyboard: true,
+        text: true,
+        mouse_buttons: true,
+        mouse_motion: true,
+        mouse_wheel: true,
+        touch: true,
+        gamepad: true,
+    };
+
+    /// Keys and typed text, for example for a console or text field that has focus
+    pub const KEYBOARD: InputConsumption = InputConsumption {
+        keyboard: true,
+        text: true,
+        ..Self::NONE
+    };
+
+    /// Mouse buttons, wheel and touches, for example for a window under the cursor
+    pub const POINTER: InputConsumption = InputConsumption {
+        mouse_buttons: true,
+        mouse_wheel: true,
+        touch: true,
+        ..Self::NONE
+    };
+
+    /// Returns true if this event should not be passed to lower contexts
+    pub fn consumes(
+        &self,
+        event: &InputEvent,
+    ) -> bool {
+        match event {
+            InputEvent::Keyboard { state, .. } | InputEvent::PhysicalKey { state, .. } => {
+                self.keyboard && *state == ButtonState::Pressed
+            }
+            InputEvent::Character(_) | InputEvent::TextComposition(_) => self.text,
+            InputEvent::MouseButton { state, .. } => {
+                self.mouse_buttons && *state == ButtonState::Pressed
+            }
+            InputEvent::MouseMotion(_) => self.mouse_motion,
+            InputEvent::MouseWheel(_) => self.mouse_wheel,
+            InputEvent::Touch { phase, .. } => self.touch && *phase == TouchPhase::Started,
+            InputEvent::Gamepad(GamepadEvent::Button { state, .. }) => {
+                self.gamepad && *state == ButtonState::Pressed
+            }
+            InputEvent::Gamepad(GamepadEvent::Axis { .. }) => self.gamepad,
+            InputEvent::Gamepad(GamepadEvent::Connected(_))
+            | InputEvent::Gamepad(GamepadEvent::Disconnected(_))
+            | InputEvent::MousePosition(_)
+            | InputEvent::ModifiersChanged(_)
+            | InputEvent::ScaleFactorChanged(_) => false,
+        }
+    }
+}
+
+/// A layer of input handling such as a debug console, egui, or a pause menu. Each context keeps
+/// its own view of input containing only the events that reached it.
+pub struct InputContext {
+    name: String,
+    consumption: InputConsumption,
+    enabled: bool,
+    input_state: InputState,
+}
+
+impl InputContext {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn consumption(&self) -> InputConsumption {
+        self.consumption
+    }
+
+    /// Disabled contexts neither see nor consume input
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Input as seen by this context
+    pub fn input_state(&self) -> &InputState {
+        &self.input_state
+    }
+}
+
+/// Contexts layered above the base gameplay input. Events are offered to the topmost enabled
+/// context first and continue downwards until a context consumes them. Whatever is left reaches
+/// the base `InputState` owned by `InputResource`.
+#[derive(Default)]
+pub struct InputContextStack {
+    // Bottom of the stack is at index 0
+    contexts: Vec<InputContext>,
+}
+
+impl InputContextStack {
+    /// Push a context on top of the stack
+    pub fn push(
+        &mut self,
+        name: &str,
+        consumption: InputConsumption,
+    ) {
+        self.contexts.push(InputContext {
+            name: name.to_string(),
+            consumption,
+            enabled: true,
+            input_state: InputState::new(),
+        });
+    }
+
+    /// Remove the topmost context
+    pub fn pop(&mut self) -> Option<InputContext> {
+        self.contexts.pop()
+    }
+
+    /// Remove the named context wherever it is in the stack
+    pub fn remove(
+        &mut self,
+        name: &str,
+    ) -> Option<InputContext> {
+        let index = self
+            .contexts
+            .iter()
+            .position(|context| context.name == name)?;
+        Some(self.contexts.remove(index))
+    }
+
+    pub fn context(
+        &self,
+        name: &str,
+    ) -> Option<&InputContext> {
+        self.contexts.iter().find(|context| context.name == name)
+    }
+
+    /// Contexts from the bottom of the stack to the top
+    pub fn contexts(&self) -> &[InputContext] {
+        &self.contexts
+    }
+
+    pub fn set_enabled(
+        &mut self,
+        name: &str,
+        enabled: bool,
+    ) {
+        if let Some(context) = self.context_mut(name) {
+            context.enabled = enabled;
+        }
+    }
+
+    pub fn set_consumption(
+        &mut self,
+        name: &str,
+        consumption: InputConsumption,
+    ) {
+        if let Some(context) = self.context_mut(name) {
+            context.consumption = consumption;
+        }
+    }
+
+    /// Offer an event to each enabled context from the top down. Returns the event that should
+    /// reach the base input, or None if it was consumed. Consumed gamepad axes continue down as
+    /// zero so sticks don't stay deflected in the contexts below.
+    pub fn dispatch(
+        &mut self,
+        event: &InputEvent,
+        timestamp: Instant,
+    ) -> Option<InputEvent> {
+        let mut event = event.clone();
+        for context in self.contexts.iter_mut().rev() {
+            if !context.enabled {
+                continue;
+            }
+
+            context.input_state.handle_event_at(&event, timestamp);
+
+            if context.consumption.consumes(&event) {
+                match &mut event {
+                    InputEvent::Gamepad(GamepadEvent::Axis { value, .. }) => *value = 0.0,
+                    _ => return None,
+                }
+            }
+        }
+
+        Some(event)
+    }
+
+    /// Returns true if an enabled context is taking typed text, for example a focused text field.
+    /// Keys such as the arrows are being used for editing then.
+    pub fn is_text_input_active(&self) -> bool {
+        self.contexts
+            .iter()
+            .any(|context| context.enabled && context.consumption.text)
+    }
+
+    pub fn end_frame(&mut self) {
+        for context in &mut self.contexts {
+            context.input_state.end_frame();
+        }
+    }
+
+    fn context_mut(
+        &mut self,
+        name: &str,
+    ) -> Option<&mut InputContext> {
+        self.contexts
+            .iter_mut()
+            .find(|context| context.name == name)
+    }
+}
@@ -0,0 +1,89 @@
+/// How the OS cursor behaves while the window has focus
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CursorMode {
+    /// Visible and free to leave the window
+    Free,
+    /// Visible but kept within the window
+    Confined,
+    /// Hidden and grabbed, for mouse-look. Use `InputState::mouse_motion` rather than the cursor
+    /// position in this mode.
+    Locked,
+}
+
+/// The cursor mode requested by scenes, applied to the window by `ApiaryApp` once per frame. The
+/// cursor is released automatically while the window is unfocused or the UI wants the pointer,
+/// and the requested mode is restored once that is no longer the case.
+pub struct CursorResource {
+    mode: CursorMode,
+    window_focused: bool,
+    pointer_wanted_by_ui: bool,
+    applied_mode: Option<CursorMode>,
+}
+
+impl CursorResource {
+    pub fn new() -> Self {
+        CursorResource {
+            mode: CursorMode::Free,
+            window_focused: true,
+            pointer_wanted_by_ui: false,
+            applied_mode: None,
+        }
+    }
+
+    /// The mode requested by the scene, which may not currently be in effect
+    pub fn mode(&self) -> CursorMode {
+        self.mode
+    }
+
+    pub fn set_mode(
+        &mut self,
+        mode: CursorMode,
+    ) {
+        self.mode = mode;
+    }
+
+    pub fn is_window_focused(&self) -> bool {
+        self.window_focused
+    }
+
+    pub fn set_window_focused(
+        &mut self,
+        window_focused: bool,
+    ) {
+        self.window_focused = window_focused;
+    }
+
+    pub fn set_pointer_wanted_by_ui(
+        &mut self,
+        pointer_wanted_by_ui: bool,
+    ) {
+        self.pointer_wanted_by_ui = pointer_wanted_by_ui;
+    }
+
+    /// The mode that should be applied to the window right now
+    pub fn effective_mode(&self) -> CursorMode {
+        if !self.window_focused || self.pointer_wanted_by_ui {
+            CursorMode::Free
+        } else {
+            self.mode
+        }
+    }
+
+    /// Returns the effective mode if it differs from what was last applied to the window, and
+    /// records it as applied
+    pub fn take_pending_change(&mut self) -> Option<CursorMode> {
+        let effective_mode = self.effective_mode();
+        if self.applied_mode == Some(effective_mode) {
+            None
+        } else {
+            self.applied_mode = Some(effective_mode);
+            Some(effective_mode)
+        }
+    }
+}
+
+impl Default for CursorResource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
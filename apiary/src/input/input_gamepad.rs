@@ -0,0 +1,787 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::{ButtonState, InputBackend, InputEvent, TimestampedInputEvent};
+use crate::time::ClockResource;
+
+/// Identifies a connected gamepad. Ids are assigned by the backend and are stable while the pad
+/// stays connected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GamepadId(pub usize);
+
+/// Gamepad buttons, named by position on a standard layout
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    /// A on Xbox, Cross on PlayStation
+    South,
+    /// B on Xbox, Circle on PlayStation
+    East,
+    /// Y on Xbox, Triangle on PlayStation
+    North,
+    /// X on Xbox, Square on PlayStation
+    West,
+    LeftBumper,
+    RightBumper,
+    /// Digital state of the left trigger, see `GamepadAxis::LeftTrigger` for the analog value
+    LeftTrigger,
+    /// Digital state of the right trigger, see `GamepadAxis::RightTrigger` for the analog value
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Analog gamepad inputs. Sticks are in -1.0..=1.0 with +Y up, triggers are in 0.0..=1.0.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GamepadEvent {
+    Connected(GamepadId),
+    Disconnected(GamepadId),
+    Button {
+        id: GamepadId,
+        button: GamepadButton,
+        state: ButtonState,
+    },
+    Axis {
+        id: GamepadId,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+/// An `InputBackend` that only produces the gamepad events pushed into it, so gamepad input can
+/// be driven without hardware. Events are timestamped from the clock when they are pushed.
+pub struct SyntheticGamepadBackend {
+    clock: ClockResource,
+    events: VecDeque<TimestampedInputEvent>,
+}
+
+impl SyntheticGamepadBackend {
+    pub fn new(clock: ClockResource) -> Self {
+        SyntheticGamepadBackend {
+            clock,
+            events: VecDeque::default(),
+        }
+    }
+
+    pub fn push_event(
+        &mut self,
+        event: GamepadEvent,
+    ) {
+        self.events.push_back(TimestampedInputEvent {
+            timestamp: self.clock.now(),
+            event: InputEvent::Gamepad(event),
+        });
+    }
+}
+
+impl InputBackend for SyntheticGamepadBackend {
+    fn poll_events(
+        &mut self,
+        events: &mut Vec<TimestampedInputEvent>,
+    ) {
+        events.extend(self.events.drain(..));
+    }
+}
+
+/// Radial dead zones applied to analog values. Values inside the dead zone read as 0.0 and the
+/// remaining range is rescaled so output still reaches 1.0.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GamepadDeadZones {
+    pub left_stick: f32,
+    pub right_stick: f32,
+    pub trigger: f32,
+}
+
+impl Default for GamepadDeadZones {
+    fn default() -> Self {
+        GamepadDeadZones {
+            left_stick: 0.15,
+            right_stick: 0.15,
+            trigger: 0.05,
+        }
+    }
+}
+
+/// State of a single connected gamepad
+#[derive(Clone)]
+pub struct GamepadState {
+    id: GamepadId,
+    button_is_down: [bool; GamepadState::BUTTON_COUNT],
+    button_just_down: [bool; GamepadState::BUTTON_COUNT],
+    button_just_up: [bool; GamepadState::BUTTON_COUNT],
+    axes: [f32; GamepadState::AXIS_COUNT],
+}
+
+impl GamepadState {
+    const BUTTON_COUNT: usize = GamepadButton::DPadRight as usize + 1;
+    const AXIS_COUNT: usize = GamepadAxis::RightTrigger as usize + 1;
+
+    fn new(id: GamepadId) -> Self {
+        GamepadState {
+            id,
+            button_is_down: [false; Self::BUTTON_COUNT],
+            button_just_down: [false; Self::BUTTON_COUNT],
+            button_just_up: [false; Self::BUTTON_COUNT],
+            axes: [0.0; Self::AXIS_COUNT],
+        }
+    }
+
+    pub fn id(&self) -> GamepadId {
+        self.id
+    }
+
+    /// Returns true if the given button is down
+    pub fn is_button_down(
+        &self,
+        button: GamepadButton,
+    ) -> bool {
+        self.button_is_down[button as usize]
+    }
+
+    /// Returns true if the button went down during this frame
+    pub fn is_button_just_down(
+        &self,
+        button: GamepadButton,
+    ) -> bool {
+        self.button_just_down[button as usize]
+    }
+
+    /// Returns true if the button went up during this frame
+    pub fn is_button_just_up(
+        &self,
+        button: GamepadButton,
+    ) -> bool {
+        self.button_just_up[button as usize]
+    }
+
+    /// Raw axis value with no dead zone applied
+    pub fn raw_axis(
+        &self,
+        axis: GamepadAxis,
+    ) -> f32 {
+        self.axes[axis as usize]
+    }
+
+    fn handle_button_event(
+        &mut self,
+        button: GamepadButton,
+        state: ButtonState,
+    ) {
+        let index = button as usize;
+        if state == ButtonState::Pressed {
+            if !self.button_is_down[index] {
+                self.button_just_down[index] = true;
+            }
+            self.button_is_down[index] = true;
+        } else {
+            if self.button_is_down[index] {
+                self.button_just_up[index] = true;
+            }
+            self.button_is_down[index] = false;
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.button_just_down = [false; Self::BUTTON_COUNT];
+        self.button_just_up = [false; Self::BUTTON_COUNT];
+    }
+}
+
+/// State of all connected gamepads, with the same is_down/just_down/just_up API as the keyboard
+#[derive(Clone, Default)]
+pub struct GamepadsState {
+    gamepads: Vec<GamepadState>,
+    dead_zones: GamepadDeadZones,
+    just_connected: Vec<GamepadId>,
+    just_disconnected: Vec<GamepadId>,
+}
+
+impl GamepadsState {
+    pub fn dead_zones(&self) -> &GamepadDeadZones {
+        &self.dead_zones
+    }
+
+    pub fn dead_zones_mut(&mut self) -> &mut GamepadDeadZones {
+        &mut self.dead_zones
+    }
+
+    /// All connected gamepads, in the order they connected
+    pub fn gamepads(&self) -> &[GamepadState] {
+        &self.gamepads
+    }
+
+    pub fn gamepad(
+        &self,
+        id: GamepadId,
+    ) -> Option<&GamepadState> {
+        self.gamepads.iter().find(|gamepad| gamepad.id == id)
+    }
+
+    /// The earliest connected gamepad that is still connected
+    pub fn first_gamepad(&self) -> Option<&GamepadState> {
+        self.gamepads.first()
+    }
+
+    /// Gamepads connected during this frame
+    pub fn just_connected(&self) -> &[GamepadId] {
+        &self.just_connected
+    }
+
+    /// Gamepads disconnected during this frame
+    pub fn just_disconnected(&self) -> &[GamepadId] {
+        &self.just_disconnected
+    }
+
+    /// Returns true if the button is down on the given pad
+    pub fn is_button_down(
+        &self,
+        id: GamepadId,
+        button: GamepadButton,
+    ) -> bool {
+        self.gamepad(id)
+            .map(|gamepad| gamepad.is_button_down(button))
+            .unwrap_or(false)
+    }
+
+    /// Returns true if the button went down on the given pad during this frame
+    pub fn is_button_just_down(
+        &self,
+        id: GamepadId,
+        button: GamepadButton,
+    ) -> bool {
+        self.gamepad(id)
+            .map(|gamepad| gamepad.is_button_just_down(button))
+            .unwrap_or(false)
+    }
+
+    /// Returns true if the button went up on the given pad during this frame
+    pub fn is_button_just_up(
+        &self,
+        id: GamepadId,
+        button: GamepadButton,
+    ) -> bool {
+        self.gamepad(id)
+            .map(|gamepad| gamepad.is_button_just_up(button))
+            .unwrap_or(false)
+    }
+
+    /// Returns true if the button is down on any connected pad
+    pub fn is_any_button_down(
+        &self,
+        button: GamepadButton,
+    ) -> bool {
+        self.gamepads
+            .iter()
+            .any(|gamepad| gamepad.is_button_down(button))
+    }
+
+    /// Returns true if the button went down on any connected pad during this frame
+    pub fn is_any_button_just_down(
+        &self,
+        button: GamepadButton,
+    ) -> bool {
+        self.gamepads
+            .iter()
+            .any(|gamepad| gamepad.is_button_just_down(button))
+    }
+
+    /// Returns true if the button went up on any connected pad during this frame
+    pub fn is_any_button_just_up(
+        &self,
+        button: GamepadButton,
+    ) -> bool {
+        self.gamepads
+            .iter()
+            .any(|gamepad| gamepad.is_button_just_up(button))
+    }
+
+    /// Left stick position with the dead zone applied
+    pub fn left_stick(
+        &self,
+        id: GamepadId,
+    ) -> glam::Vec2 {
+        self.stick(
+            id,
+            GamepadAxis::LeftStickX,
+            GamepadAxis::LeftStickY,
+            self.dead_zones.left_stick,
+        )
+    }
+
+    /// Right stick position with the dead zone applied
+    pub fn right_stick(
+        &self,
+        id: GamepadId,
+    ) -> glam::Vec2 {
+        self.stick(
+            id,
+            GamepadAxis::RightStickX,
+            GamepadAxis::RightStickY,
+            self.dead_zones.right_stick,
+        )
+    }
+
+    /// Axis value with the dead zone applied. Stick axes use the radial dead zone of their stick.
+    pub fn axis(
+        &self,
+        id: GamepadId,
+        axis: GamepadAxis,
+    ) -> f32 {
+        match axis {
+            GamepadAxis::LeftStickX => self.left_stick(id).x,
+            GamepadAxis::LeftStickY => self.left_stick(id).y,
+            GamepadAxis::RightStickX => self.right_stick(id).x,
+            GamepadAxis::RightStickY => self.right_stick(id).y,
+            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => {
+                let raw = self
+                    .gamepad(id)
+                    .map(|gamepad| gamepad.raw_axis(axis))
+                    .unwrap_or(0.0);
+                Self::apply_dead_zone(raw, self.dead_zones.trigger)
+            }
+        }
+    }
+
+    /// Sum of the axis across all connected pads, clamped to the axis range
+    pub fn any_axis(
+        &self,
+        axis: GamepadAxis,
+    ) -> f32 {
+        let value: f32 = self
+            .gamepads
+            .iter()
+            .map(|gamepad| self.axis(gamepad.id, axis))
+            .sum();
+        value.clamp(-1.0, 1.0)
+    }
+
+    pub fn handle_event(
+        &mut self,
+        event: GamepadEvent,
+    ) {
+        match event {
+            GamepadEvent::Connected(id) => {
+                if self.gamepad(id).is_none() {
+                    self.gamepads.push(GamepadState::new(id));
+                    self.just_connected.push(id);
+                }
+            }
+            GamepadEvent::Disconnected(id) => {
+                let count = self.gamepads.len();
+                self.gamepads.retain(|gamepad| gamepad.id != id);
+                if self.gamepads.len() != count {
+                    self.just_disconnected.push(id);
+                }
+            }
+            GamepadEvent::Button { id, button, state } => {
+                if let Some(gamepad) = self.gamepad_mut(id) {
+                    gamepad.handle_button_event(button, state);
+                }
+            }
+            GamepadEvent::Axis { id, axis, value } => {
+                if let Some(gamepad) = self.gamepad_mut(id) {
+                    gamepad.axes[axis as usize] = value;
+                }
+            }
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        self.just_connected.clear();
+        self.just_disconnected.clear();
+        for gamepad in &mut self.gamepads {
+            gamepad.end_frame();
+        }
+    }
+
+    fn gamepad_mut(
+        &mut self,
+        id: GamepadId,
+    ) -> Option<&mut GamepadState> {
+        self.gamepads.iter_mut().find(|gamepad| gamepad.id == id)
+    }
+
+    fn stick(
+        &self,
+        id: GamepadId,
+        x_axis: GamepadAxis,
+        y_axis: GamepadAxis,
+        dead_zone: f32,
+    ) -> glam::Vec2 {
+        let raw = match self.gamepad(id) {
+            Some(gamepad) => glam::Vec2::new(gamepad.raw_axis(x_axis), gamepad.raw_axis(y_axis)),
+            None => return glam::Vec2::ZERO,
+        };
+
+        let length = raw.length();
+        if length <= dead_zone || length <= 0.0 {
+            return glam::Vec2::ZERO;
+        }
+
+        let scaled_length = Self::apply_dead_zone(length.min(1.0), dead_zone);
+        raw * (scaled_length / length)
+    }
+
+    fn apply_dead_zone(
+        value: f32,
+        dead_zone: f32,
+    ) -> f32 {
+        if value.abs() <= dead_zone || dead_zone >= 1.0 {
+            0.0
+        } else {
+            value.signum() * (value.abs() - dead_zone) / (1.0 - dead_zone)
+        }
+    }
+}
+
+/// Gamepad backend using gilrs. Events are timestamped from the clock when they are polled.
+#[cfg(feature = "gamepad-gilrs")]
+pub struct GilrsGamepadBackend {
+    gilrs: gilrs::Gilrs,
+    clock: ClockResource,
+    announced_initial_pads: bool,
+}
+
+#[cfg(feature = "gamepad-gilrs")]
+impl GilrsGamepadBackend {
+    pub fn new(clock: ClockResource) -> Option<Self> {
+        match gilrs::Gilrs::new() {
+            Ok(gilrs) => Some(GilrsGamepadBackend {
+                gilrs,
+                clock,
+                announced_initial_pads: false,
+            }),
+            Err(e) => {
+                log::warn!("Gamepad support unavailable: {:?}", e);
+                None
+            }
+        }
+    }
+
+    fn convert_button(button: gilrs::Button) -> Option<GamepadButton> {
+        use gilrs::Button;
+        Some(match button {
+            Button::South => GamepadButton::South,
+            Button::East => GamepadButton::East,
+            Button::North => GamepadButton::North,
+            Button::West => GamepadButton::West,
+            Button::LeftTrigger => GamepadButton::LeftBumper,
+            Button::RightTrigger => GamepadButton::RightBumper,
+            Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+            Button::RightTrigger2 => GamepadButton::RightTrigger,
+            Button::Select => GamepadButton::Select,
+            Button::Start => GamepadButton::Start,
+            Button::Mode => GamepadButton::Mode,
+            Button::LeftThumb => GamepadButton::LeftStick,
+            Button::RightThumb => GamepadButton::RightStick,
+            Button::DPadUp => GamepadButton::DPadUp,
+            Button::DPadDown => GamepadButton::DPadDown,
+            Button::DPadLeft => GamepadButton::DPadLeft,
+            Button::DPadRight => GamepadButton::DPadRight,
+            _ => return None,
+        })
+    }
+
+    fn convert_axis(axis: gilrs::Axis) -> Option<GamepadAxis> {
+        use gilrs::Axis;
+        Some(match axis {
+            Axis::LeftStickX => GamepadAxis::LeftStickX,
+            Axis::LeftStickY => GamepadAxis::LeftStickY,
+            Axis::RightStickX => GamepadAxis::RightStickX,
+            Axis::RightStickY => GamepadAxis::RightStickY,
+            Axis::LeftZ => GamepadAxis::LeftTrigger,
+            Axis::RightZ => GamepadAxis::RightTrigger,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(feature = "gamepad-gilrs")]
+impl InputBackend for GilrsGamepadBackend {
+    fn poll_events(
+        &mut self,
+        events: &mut Vec<TimestampedInputEvent>,
+    ) {
+        use gilrs::EventType;
+
+        let timestamp = self.clock.now();
+        let mut push = |event| {
+            events.push(TimestampedInputEvent {
+                timestamp,
+                event: InputEvent::Gamepad(event),
+            })
+        };
+
+        // Pads that were plugged in before startup don't produce a Connected event
+        if !self.announced_initial_pads {
+            self.announced_initial_pads = true;
+            for (id, _gamepad) in self.gilrs.gamepads() {
+                push(GamepadEvent::Connected(GamepadId(id.into())));
+            }
+        }
+
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            let id = GamepadId(id.into());
+            match event {
+                EventType::Connected => push(GamepadEvent::Connected(id)),
+                EventType::Disconnected => push(GamepadEvent::Disconnected(id)),
+                EventType::ButtonPressed(button, _) => {
+                    if let Some(button) = Self::convert_button(button) {
+                        push(GamepadEvent::Button {
+                            id,
+                            button,
+                            state: ButtonState::Pressed,
+                        });
+                    }
+                }
+                EventType::ButtonReleased(button, _) => {
+                    if let Some(button) = Self::convert_button(button) {
+                        push(GamepadEvent::Button {
+                            id,
+                            button,
+                            state: ButtonState::Released,
+                        });
+                    }
+                }
+                // Analog triggers are reported as buttons with a value by gilrs
+                EventType::ButtonChanged(gilrs::Button::LeftTrigger2, value, _) => {
+                    push(GamepadEvent::Axis {
+                        id,
+                        axis: GamepadAxis::LeftTrigger,
+                        value,
+                    });
+                }
+                EventType::ButtonChanged(gilrs::Button::RightTrigger2, value, _) => {
+                    push(GamepadEvent::Axis {
+                        id,
+                        axis: GamepadAxis::RightTrigger,
+                        value,
+                    });
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    if let Some(axis) = Self::convert_axis(axis) {
+                        push(GamepadEvent::Axis { id, axis, value });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputResource;
+    use crate::time::{Clock, ManualClock};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const PAD: GamepadId = GamepadId(3);
+
+    fn new_backend() -> SyntheticGamepadBackend {
+        SyntheticGamepadBackend::new(ClockResource::new(Arc::new(ManualClock::new())))
+    }
+
+    fn connected_backend(input_resource: &mut InputResource) -> SyntheticGamepadBackend {
+        let mut backend = new_backend();
+        backend.push_event(GamepadEvent::Connected(PAD));
+        input_resource.poll_backend(&mut backend);
+        input_resource.end_frame();
+        backend
+    }
+
+    #[test]
+    fn connect_and_disconnect() {
+        let mut input_resource = InputResource::new();
+        let mut backend = new_backend();
+
+        backend.push_event(GamepadEvent::Connected(PAD));
+        // Repeated connects of the same pad are ignored
+        backend.push_event(GamepadEvent::Connected(PAD));
+        input_resource.poll_backend(&mut backend);
+        assert_eq!(input_resource.gamepads().just_connected(), &[PAD]);
+        assert_eq!(input_resource.gamepads().gamepads().len(), 1);
+        assert_eq!(input_resource.gamepads().first_gamepad().unwrap().id(), PAD);
+
+        input_resource.end_frame();
+        assert!(input_resource.gamepads().just_connected().is_empty());
+
+        // Polling again doesn't repeat events that were already taken
+        input_resource.poll_backend(&mut backend);
+        assert!(input_resource.gamepads().just_connected().is_empty());
+
+        backend.push_event(GamepadEvent::Disconnected(PAD));
+        input_resource.poll_backend(&mut backend);
+        assert_eq!(input_resource.gamepads().just_disconnected(), &[PAD]);
+        assert!(input_resource.gamepads().gamepad(PAD).is_none());
+        assert!(input_resource.gamepads().first_gamepad().is_none());
+    }
+
+    #[test]
+    fn events_are_timestamped_from_the_clock() {
+        let clock = Arc::new(ManualClock::new());
+        let mut backend = SyntheticGamepadBackend::new(ClockResource::new(clock.clone()));
+        backend.push_event(GamepadEvent::Connected(PAD));
+        clock.advance(Duration::from_millis(10));
+        backend.push_event(GamepadEvent::Disconnected(PAD));
+
+        let mut input_resource = InputResource::new();
+        let events = input_resource.poll_backend(&mut backend);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].timestamp + Duration::from_millis(10), clock.now());
+        assert_eq!(events[1].timestamp, clock.now());
+        assert_eq!(input_resource.frame_events(), &events[..]);
+    }
+
+    #[test]
+    fn events_for_unknown_pads_are_ignored() {
+        let mut input_resource = InputResource::new();
+        let mut backend = new_backend();
+
+        backend.push_event(GamepadEvent::Button {
+            id: PAD,
+            button: GamepadButton::South,
+            state: ButtonState::Pressed,
+        });
+        input_resource.poll_backend(&mut backend);
+        assert!(!input_resource
+            .gamepads()
+            .is_any_button_down(GamepadButton::South));
+    }
+
+    #[test]
+    fn button_press_and_release() {
+        let mut input_resource = InputResource::new();
+        let mut backend = connected_backend(&mut input_resource);
+
+        backend.push_event(GamepadEvent::Button {
+            id: PAD,
+            button: GamepadButton::South,
+            state: ButtonState::Pressed,
+        });
+        input_resource.poll_backend(&mut backend);
+        let gamepads = input_resource.gamepads();
+        assert!(gamepads.is_button_down(PAD, GamepadButton::South));
+        assert!(gamepads.is_button_just_down(PAD, GamepadButton::South));
+        assert!(gamepads.is_any_button_just_down(GamepadButton::South));
+        assert!(!gamepads.is_button_down(PAD, GamepadButton::East));
+
+        input_resource.end_frame();
+        let gamepads = input_resource.gamepads();
+        assert!(gamepads.is_button_down(PAD, GamepadButton::South));
+        assert!(!gamepads.is_button_just_down(PAD, GamepadButton::South));
+
+        backend.push_event(GamepadEvent::Button {
+            id: PAD,
+            button: GamepadButton::South,
+            state: ButtonState::Released,
+        });
+        input_resource.poll_backend(&mut backend);
+        let gamepads = input_resource.gamepads();
+        assert!(!gamepads.is_button_down(PAD, GamepadButton::South));
+        assert!(gamepads.is_button_just_up(PAD, GamepadButton::South));
+    }
+
+    #[test]
+    fn stick_dead_zone_is_radial_and_rescaled() {
+        let mut input_resource = InputResource::new();
+        let mut backend = connected_backend(&mut input_resource);
+        let dead_zone = input_resource.gamepads().dead_zones().left_stick;
+
+        let move_stick = |backend: &mut SyntheticGamepadBackend, x: f32, y: f32| {
+            backend.push_event(GamepadEvent::Axis {
+                id: PAD,
+                axis: GamepadAxis::LeftStickX,
+                value: x,
+            });
+            backend.push_event(GamepadEvent::Axis {
+                id: PAD,
+                axis: GamepadAxis::LeftStickY,
+                value: y,
+            });
+        };
+
+        move_stick(&mut backend, dead_zone * 0.5, 0.0);
+        input_resource.poll_backend(&mut backend);
+        assert_eq!(input_resource.gamepads().left_stick(PAD), glam::Vec2::ZERO);
+        let gamepad = input_resource.gamepads().gamepad(PAD).unwrap();
+        assert_eq!(gamepad.raw_axis(GamepadAxis::LeftStickX), dead_zone * 0.5);
+
+        // Each axis is inside the dead zone but the stick as a whole is outside it
+        move_stick(&mut backend, dead_zone * 0.9, dead_zone * 0.9);
+        input_resource.poll_backend(&mut backend);
+        let stick = input_resource.gamepads().left_stick(PAD);
+        assert!(stick.x > 0.0 && stick.y > 0.0);
+
+        // Full deflection still reaches 1.0
+        move_stick(&mut backend, 0.0, 1.0);
+        input_resource.poll_backend(&mut backend);
+        assert_eq!(
+            input_resource.gamepads().left_stick(PAD),
+            glam::Vec2::new(0.0, 1.0)
+        );
+
+        // Halfway through the live range
+        let halfway = dead_zone + (1.0 - dead_zone) * 0.5;
+        move_stick(&mut backend, -halfway, 0.0);
+        input_resource.poll_backend(&mut backend);
+        let value = input_resource.gamepads().axis(PAD, GamepadAxis::LeftStickX);
+        assert!((value + 0.5).abs() < 1e-5);
+        assert_eq!(
+            input_resource.gamepads().any_axis(GamepadAxis::RightStickX),
+            0.0
+        );
+    }
+
+    #[test]
+    fn trigger_dead_zone() {
+        let mut input_resource = InputResource::new();
+        let mut backend = connected_backend(&mut input_resource);
+        let dead_zone = input_resource.gamepads().dead_zones().trigger;
+
+        backend.push_event(GamepadEvent::Axis {
+            id: PAD,
+            axis: GamepadAxis::RightTrigger,
+            value: dead_zone * 0.5,
+        });
+        input_resource.poll_backend(&mut backend);
+        assert_eq!(
+            input_resource
+                .gamepads()
+                .axis(PAD, GamepadAxis::RightTrigger),
+            0.0
+        );
+
+        backend.push_event(GamepadEvent::Axis {
+            id: PAD,
+            axis: GamepadAxis::RightTrigger,
+            value: 1.0,
+        });
+        input_resource.poll_backend(&mut backend);
+        assert_eq!(
+            input_resource
+                .gamepads()
+                .any_axis(GamepadAxis::RightTrigger),
+            1.0
+        );
+    }
+}
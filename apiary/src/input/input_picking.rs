@@ -0,0 +1,254 @@
+use rafx::renderer::RenderViewMeta;
+
+use crate::input::InputState;
+
+/// A half-line in world space, used for picking
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: glam::Vec3,
+    /// Normalized
+    pub direction: glam::Vec3,
+}
+
+impl Ray {
+    /// The point `distance` units along the ray
+    pub fn at(
+        &self,
+        distance: f32,
+    ) -> glam::Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// Distance along the ray to where it crosses the plane, None if it is parallel to the plane
+    /// or the plane is behind the origin
+    pub fn intersect_plane(
+        &self,
+        plane_point: glam::Vec3,
+        plane_normal: glam::Vec3,
+    ) -> Option<f32> {
+        let denominator = plane_normal.dot(self.direction);
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let distance = plane_normal.dot(plane_point - self.origin) / denominator;
+        if distance >= 0.0 {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+}
+
+/// Build a world-space ray through a point on the screen. `screen_position` and `viewport_size`
+/// are in physical pixels with the origin at the top left. Works with perspective and
+/// orthographic projections, including reversed and infinite depth. Returns None if the viewport
+/// is empty or the matrices aren't invertible.
+pub fn screen_to_world_ray(
+    screen_position: glam::Vec2,
+    viewport_size: glam::Vec2,
+    view: glam::Mat4,
+    proj: glam::Mat4,
+) -> Option<Ray> {
+    if viewport_size.x <= 0.0 || viewport_size.y <= 0.0 {
+        return None;
+    }
+
+    let ndc_x = 2.0 * screen_position.x / viewport_size.x - 1.0;
+    let ndc_y = 1.0 - 2.0 * screen_position.y / viewport_size.y;
+
+    let view_proj_inverse = (proj * view).inverse();
+    let unproject = |ndc_z: f32| {
+        let p = view_proj_inverse * glam::Vec4::new(ndc_x, ndc_y, ndc_z, 1.0);
+        p.truncate() / p.w
+    };
+
+    let view_inverse = view.inverse();
+    // Right handed, the camera looks down -Z in view space
+    let forward = view_inverse.transform_vector3(-glam::Vec3::Z);
+
+    // Depth values strictly between 0 and 1 are finite for every depth range, including infinite
+    // far planes
+    let a = unproject(0.25);
+    let b = unproject(0.75);
+
+    let is_perspective = proj.w_axis.w == 0.0;
+    let ray = if is_perspective {
+        let eye = view_inverse.w_axis.truncate();
+        Ray {
+            origin: eye,
+            direction: (a - eye).normalize(),
+        }
+    } else {
+        let mut direction = (b - a).normalize();
+        if direction.dot(forward) < 0.0 {
+            direction = -direction;
+        }
+
+        // Start the ray behind both points so nothing between the near plane and them is missed
+        let near = unproject(0.0);
+        let far = unproject(1.0);
+        let origin = if (near - a).dot(forward) < (far - a).dot(forward) {
+            near
+        } else {
+            far
+        };
+
+        Ray { origin, direction }
+    };
+
+    if ray.origin.is_finite() && ray.direction.is_finite() {
+        Some(ray)
+    } else {
+        None
+    }
+}
+
+/// Build a world-space ray under the cursor for the given view. `viewport_size` is in physical
+/// pixels, for example `ViewportsResource::main_window_size`.
+pub fn cursor_world_ray(
+    input_state: &InputState,
+    view_meta: &RenderViewMeta,
+    viewport_size: glam::Vec2,
+) -> Option<Ray> {
+    screen_to_world_ray(
+        input_state.mouse_position_physical(),
+        viewport_size,
+        view_meta.view,
+        view_meta.proj,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputEvent;
+    use std::time::Instant;
+
+    const FOV_Y: f32 = std::f32::consts::FRAC_PI_2;
+
+    fn viewport() -> glam::Vec2 {
+        glam::Vec2::new(800.0, 600.0)
+    }
+
+    // Z-up camera at (1, 2, 3) looking along +X, so left is +Y
+    fn eye() -> glam::Vec3 {
+        glam::Vec3::new(1.0, 2.0, 3.0)
+    }
+
+    fn view() -> glam::Mat4 {
+        glam::Mat4::look_at_rh(eye(), eye() + glam::Vec3::X, glam::Vec3::Z)
+    }
+
+    fn aspect() -> f32 {
+        viewport().x / viewport().y
+    }
+
+    fn assert_near(
+        actual: glam::Vec3,
+        expected: glam::Vec3,
+    ) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-4),
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    fn perspective_projections() -> Vec<glam::Mat4> {
+        vec![
+            glam::Mat4::perspective_rh(FOV_Y, aspect(), 0.1, 100.0),
+            glam::Mat4::perspective_infinite_reverse_rh(FOV_Y, aspect(), 0.1),
+        ]
+    }
+
+    #[test]
+    fn centre_pixel_is_the_forward_ray() {
+        for proj in perspective_projections() {
+            let ray = screen_to_world_ray(viewport() / 2.0, viewport(), view(), proj).unwrap();
+            assert_near(ray.origin, eye());
+            assert_near(ray.direction, glam::Vec3::X);
+        }
+    }
+
+    #[test]
+    fn corners_match_the_field_of_view() {
+        let half_height = (FOV_Y / 2.0).tan();
+        let half_width = half_height * aspect();
+
+        for proj in perspective_projections() {
+            let top_left = screen_to_world_ray(glam::Vec2::ZERO, viewport(), view(), proj).unwrap();
+            assert_near(
+                top_left.direction,
+                glam::Vec3::new(1.0, half_width, half_height).normalize(),
+            );
+
+            let bottom_right = screen_to_world_ray(viewport(), viewport(), view(), proj).unwrap();
+            assert_near(
+                bottom_right.direction,
+                glam::Vec3::new(1.0, -half_width, -half_height).normalize(),
+            );
+        }
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel() {
+        let proj = glam::Mat4::orthographic_rh(-4.0, 4.0, -3.0, 3.0, 0.1, 100.0);
+
+        let centre = screen_to_world_ray(viewport() / 2.0, viewport(), view(), proj).unwrap();
+        assert_near(centre.direction, glam::Vec3::X);
+        assert_near(centre.origin, eye() + glam::Vec3::X * 0.1);
+
+        let top_left = screen_to_world_ray(glam::Vec2::ZERO, viewport(), view(), proj).unwrap();
+        assert_near(top_left.direction, glam::Vec3::X);
+        assert_near(top_left.origin, eye() + glam::Vec3::new(0.1, 4.0, 3.0));
+    }
+
+    #[test]
+    fn empty_viewport_has_no_ray() {
+        let proj = glam::Mat4::perspective_rh(FOV_Y, aspect(), 0.1, 100.0);
+        assert!(screen_to_world_ray(glam::Vec2::ZERO, glam::Vec2::ZERO, view(), proj).is_none());
+    }
+
+    #[test]
+    fn cursor_position_is_in_physical_pixels() {
+        // cursor_world_ray passes the physical cursor position, so with a scale factor of 2 the
+        // cursor is centred at half the viewport's size in logical units
+        let mut input_state = InputState::new();
+        input_state.handle_event_at(&InputEvent::ScaleFactorChanged(2.0), Instant::now());
+        input_state.handle_event_at(&InputEvent::MousePosition(viewport() / 2.0), Instant::now());
+        assert_eq!(input_state.mouse_position(), viewport() / 4.0);
+
+        let proj = glam::Mat4::perspective_rh(FOV_Y, aspect(), 0.1, 100.0);
+        let ray = screen_to_world_ray(
+            input_state.mouse_position_physical(),
+            viewport(),
+            view(),
+            proj,
+        )
+        .unwrap();
+        assert_near(ray.direction, glam::Vec3::X);
+    }
+
+    #[test]
+    fn ray_hits_the_ground_plane() {
+        let proj = glam::Mat4::perspective_rh(FOV_Y, aspect(), 0.1, 100.0);
+        let bottom_centre = glam::Vec2::new(viewport().x / 2.0, viewport().y);
+        let ray = screen_to_world_ray(bottom_centre, viewport(), view(), proj).unwrap();
+
+        // 45 degrees down from a height of 3
+        let distance = ray
+            .intersect_plane(glam::Vec3::ZERO, glam::Vec3::Z)
+            .unwrap();
+        assert_near(ray.at(distance), glam::Vec3::new(4.0, 2.0, 0.0));
+
+        let up = Ray {
+            origin: eye(),
+            direction: glam::Vec3::Z,
+        };
+        assert!(up
+            .intersect_plane(glam::Vec3::ZERO, glam::Vec3::Z)
+            .is_none());
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiaryResult;
+use crate::input::{InputEvent, TimestampedInputEvent};
+use crate::time::PeriodicEvent;
+
+/// An input event along with how long after the start of its frame it was received, so replay
+/// reproduces timing such as double clicks and long presses
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub offset: Duration,
+    pub event: InputEvent,
+}
+
+/// All input consumed during a single frame, along with how long that frame took so that replay
+/// advances time by the same amount
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// `TimeState::update_count` of the frame the events were consumed in
+    pub frame: u64,
+    pub elapsed: Duration,
+    pub events: Vec<RecordedEvent>,
+}
+
+impl RecordedFrame {
+    /// The frame's events with timestamps relative to `frame_start`, which should be
+    /// `TimeState::current_instant` before the frame's update
+    pub fn timestamped_events(
+        &self,
+        frame_start: Instant,
+    ) -> impl Iterator<Item = TimestampedInputEvent> + '_ {
+        self.events.iter().map(move |event| TimestampedInputEvent {
+            timestamp: frame_start + event.offset,
+            event: event.event.clone(),
+        })
+    }
+}
+
+/// A stream of recorded input, stored on disk in a compact binary form
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputRecording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl InputRecording {
+    pub fn load(path: &Path) -> ApiaryResult<Self> {
+        let bytes = std::fs::read(path)?;
+        Ok(bincode::deserialize(&bytes)?)
+    }
+
+    pub fn save(
+        &self,
+        path: &Path,
+    ) -> ApiaryResult<()> {
+        let bytes = bincode::serialize(self)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+/// Captures every input event fed into `InputState` and writes them out when saved. The recording
+/// is rewritten every `SAVE_INTERVAL` by `save_if_due` so that a crash doesn't lose it.
+pub struct InputRecorder {
+    path: PathBuf,
+    recording: InputRecording,
+    pending_events: Vec<TimestampedInputEvent>,
+    save_event: PeriodicEvent,
+}
+
+impl InputRecorder {
+    /// How often `save_if_due` writes the recording to disk
+    pub const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+    pub fn new(path: PathBuf) -> Self {
+        InputRecorder {
+            path,
+            recording: InputRecording::default(),
+            pending_events: Vec::default(),
+            save_event: PeriodicEvent::default(),
+        }
+    }
+
+    /// Call for every event that is applied to the live `InputState`
+    pub fn record_event(
+        &mut self,
+        event: &TimestampedInputEvent,
+    ) {
+        self.pending_events.push(event.clone());
+    }
+
+    /// Call once per frame after time has been updated. Events recorded since the previous call
+    /// are attributed to this frame and stored relative to `frame_start`, which should be
+    /// `TimeState::current_instant` before the update.
+    pub fn end_frame(
+        &mut self,
+        frame: u64,
+        frame_start: Instant,
+        elapsed: Duration,
+    ) {
+        let events = self
+            .pending_events
+            .drain(..)
+            .map(|event| RecordedEvent {
+                offset: event.timestamp.saturating_duration_since(frame_start),
+                event: event.event,
+            })
+            .collect();
+
+        self.recording.frames.push(RecordedFrame {
+            frame,
+            elapsed,
+            events,
+        });
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn recording(&self) -> &InputRecording {
+        &self.recording
+    }
+
+    pub fn save(&self) -> ApiaryResult<()> {
+        self.recording.save(&self.path)
+    }
+
+    /// Save if `SAVE_INTERVAL` has passed since the previous save. `now` should come from the
+    /// `ClockResource`. Returns true if the recording was written.
+    pub fn save_if_due(
+        &mut self,
+        now: Instant,
+    ) -> ApiaryResult<bool> {
+        if !self.save_event.try_take_event(now, Self::SAVE_INTERVAL) {
+            return Ok(false);
+        }
+
+        self.save()?;
+        Ok(true)
+    }
+}
+
+/// Plays back an `InputRecording` one frame at a time
+pub struct InputReplay {
+    frames: VecDeque<RecordedFrame>,
+}
+
+impl InputReplay {
+    pub fn new(recording: InputRecording) -> Self {
+        InputReplay {
+            frames: recording.frames.into(),
+        }
+    }
+
+    pub fn load(path: &Path) -> ApiaryResult<Self> {
+        Ok(Self::new(InputRecording::load(path)?))
+    }
+
+    /// Take the next recorded frame, or None once the recording has been exhausted
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        self.frames.pop_front()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{ButtonState, InputState, KeyboardKey, MouseButton};
+
+    fn timestamped(
+        timestamp: Instant,
+        event: InputEvent,
+    ) -> TimestampedInputEvent {
+        TimestampedInputEvent { timestamp, event }
+    }
+
+    #[test]
+    fn record_save_load_replay_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "apiary_input_recording_test_{}.bin",
+            std::process::id()
+        ));
+
+        let frame_duration = Duration::from_millis(16);
+        let click = |state| InputEvent::MouseButton {
+            button: MouseButton::LEFT,
+            state,
+        };
+
+        // Two frames: a key press, then a double click within a single frame
+        let start = Instant::now();
+        let mut recorder = InputRecorder::new(path.clone());
+        recorder.record_event(&timestamped(
+            start + Duration::from_millis(5),
+            InputEvent::Keyboard {
+                key: KeyboardKey::A,
+                state: ButtonState::Pressed,
+            },
+        ));
+        recorder.end_frame(1, start, frame_duration);
+
+        let frame_2_start = start + frame_duration;
+        for (offset_ms, state) in [
+            (1, ButtonState::Pressed),
+            (3, ButtonState::Released),
+            (9, ButtonState::Pressed),
+            (11, ButtonState::Released),
+        ] {
+            recorder.record_event(&timestamped(
+                frame_2_start + Duration::from_millis(offset_ms),
+                click(state),
+            ));
+        }
+        recorder.end_frame(2, frame_2_start, frame_duration);
+        recorder.save().unwrap();
+
+        let mut replay = InputReplay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Replay starting at a different time, the offsets within each frame are preserved
+        let replay_start = start + Duration::from_secs(60);
+        let mut frame_start = replay_start;
+        let mut input_state = InputState::new();
+
+        let frame = replay.next_frame().unwrap();
+        assert_eq!(frame, recorder.recording().frames[0]);
+        let events: Vec<_> = frame.timestamped_events(frame_start).collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].timestamp, replay_start + Duration::from_millis(5));
+        for event in &events {
+            input_state.handle_event_at(&event.event, event.timestamp);
+        }
+        assert!(input_state.is_key_just_down(KeyboardKey::A));
+        input_state.end_frame();
+        frame_start += frame.elapsed;
+
+        let frame = replay.next_frame().unwrap();
+        assert_eq!(frame.frame, 2);
+        let events: Vec<_> = frame.timestamped_events(frame_start).collect();
+        assert_eq!(
+            events[2].timestamp,
+            replay_start + frame_duration + Duration::from_millis(9)
+        );
+        for event in &events {
+            input_state.handle_event_at(&event.event, event.timestamp);
+        }
+        assert!(input_state.is_key_down(KeyboardKey::A));
+        assert_eq!(input_state.mouse_click_count(MouseButton::LEFT), 2);
+
+        assert!(replay.is_finished());
+        assert!(replay.next_frame().is_none());
+    }
+}
@@ -1,15 +1,30 @@
 use std::ops::{Deref, DerefMut};
+use std::time::Instant;
 
-use crate::input::InputState;
+use crate::input::{
+    Gesture, GestureRecognizer, InputBackend, InputBindings, InputContextStack, InputEvent,
+    InputState, ShortcutRegistry, TimestampedInputEvent, VirtualAxes,
+};
 
 pub struct InputResource {
+    // Base input, receives everything not consumed by a context in the stack
     input_state: InputState,
+    contexts: InputContextStack,
+    bindings: InputBindings,
+    axes: VirtualAxes,
+    shortcuts: ShortcutRegistry,
+    gesture_recognizer: GestureRecognizer,
 }
 
 impl InputResource {
     pub fn new() -> Self {
         InputResource {
             input_state: InputState::new(),
+            contexts: InputContextStack::default(),
+            bindings: InputBindings::default(),
+            axes: VirtualAxes::default(),
+            shortcuts: ShortcutRegistry::default(),
+            gesture_recognizer: GestureRecognizer::default(),
         }
     }
 
@@ -19,6 +34,156 @@ impl InputResource {
     pub fn input_state_mut(&mut self) -> &mut InputState {
         &mut self.input_state
     }
+
+    /// Input contexts layered above the base input
+    pub fn contexts(&self) -> &InputContextStack {
+        &self.contexts
+    }
+    pub fn contexts_mut(&mut self) -> &mut InputContextStack {
+        &mut self.contexts
+    }
+
+    /// Input as seen by the named context, None if no such context exists
+    pub fn context_state(
+        &self,
+        name: &str,
+    ) -> Option<&InputState> {
+        self.contexts
+            .context(name)
+            .map(|context| context.input_state())
+    }
+
+    /// Route an event that was received at the given time through the context stack, applying
+    /// whatever isn't consumed to the base input. `timestamp` should come from the `ClockResource`.
+    pub fn handle_event_at(
+        &mut self,
+        event: &InputEvent,
+        timestamp: Instant,
+    ) {
+        if let Some(event) = self.contexts.dispatch(event, timestamp) {
+            self.input_state.handle_event_at(&event, timestamp);
+        }
+    }
+
+    /// Apply every event the backend has received since it was last polled. Returns the events
+    /// that were applied, in order.
+    pub fn poll_backend(
+        &mut self,
+        backend: &mut dyn InputBackend,
+    ) -> Vec<TimestampedInputEvent> {
+        let mut events = Vec::default();
+        backend.poll_events(&mut events);
+        for event in &events {
+            self.handle_event_at(&event.event, event.timestamp);
+        }
+
+        events
+    }
+
+    /// Call at the end of every frame. This clears events that were "just" completed in the base
+    /// input and every context.
+    pub fn end_frame(&mut self) {
+        self.input_state.end_frame();
+        self.contexts.end_frame();
+    }
+
+    pub fn bindings(&self) -> &InputBindings {
+        &self.bindings
+    }
+    pub fn bindings_mut(&mut self) -> &mut InputBindings {
+        &mut self.bindings
+    }
+    pub fn set_bindings(
+        &mut self,
+        bindings: InputBindings,
+    ) {
+        self.bindings = bindings;
+        self.axes.reset();
+    }
+
+    pub fn shortcuts(&self) -> &ShortcutRegistry {
+        &self.shortcuts
+    }
+    pub fn shortcuts_mut(&mut self) -> &mut ShortcutRegistry {
+        &mut self.shortcuts
+    }
+
+    /// Returns true if a chord registered under the name was pressed during this frame
+    pub fn shortcut_just_pressed(
+        &self,
+        name: &str,
+    ) -> bool {
+        self.shortcuts.just_pressed(name, &self.input_state)
+    }
+
+    pub fn gesture_recognizer(&self) -> &GestureRecognizer {
+        &self.gesture_recognizer
+    }
+    pub fn gesture_recognizer_mut(&mut self) -> &mut GestureRecognizer {
+        &mut self.gesture_recognizer
+    }
+
+    /// Recognise gestures from this frame's touch input. Call once per frame before scenes update.
+    pub fn update_gestures(
+        &mut self,
+        dt: f32,
+    ) {
+        self.gesture_recognizer.update(&self.input_state, dt);
+    }
+
+    /// Gestures recognised during this frame
+    pub fn gestures(&self) -> &[Gesture] {
+        self.gesture_recognizer.gestures()
+    }
+
+    /// Returns true if any input bound to the action is down
+    pub fn action_down(
+        &self,
+        action: &str,
+    ) -> bool {
+        self.bindings.action_down(action, &self.input_state)
+    }
+
+    /// Returns true if any input bound to the action went down during this frame
+    pub fn action_pressed(
+        &self,
+        action: &str,
+    ) -> bool {
+        self.bindings.action_pressed(action, &self.input_state)
+    }
+
+    /// Returns true if the action stopped being held during this frame
+    pub fn action_released(
+        &self,
+        action: &str,
+    ) -> bool {
+        self.bindings.action_released(action, &self.input_state)
+    }
+
+    /// Smooth virtual axes towards this frame's input. Call once per frame before scenes update.
+    pub fn update_axes(
+        &mut self,
+        dt: f32,
+    ) {
+        self.axes.update(&self.bindings, &self.input_state, dt);
+    }
+
+    pub fn axes(&self) -> &VirtualAxes {
+        &self.axes
+    }
+    pub fn axes_mut(&mut self) -> &mut VirtualAxes {
+        &mut self.axes
+    }
+
+    /// Current value of the named axis with its settings (including smoothing) applied
+    pub fn axis_value(
+        &self,
+        axis: &str,
+    ) -> f32 {
+        self.axes
+            .value(axis)
+            .unwrap_or_else(|| self.bindings.axis_value(axis, &self.input_state))
+    }
 }
 
 impl Deref for InputResource {
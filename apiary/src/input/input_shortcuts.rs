@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+
+use crate::input::{ButtonState, InputEvent, InputState, KeyModifiers, KeyboardKey};
+
+/// A key pressed while an exact set of modifiers is held, for example Ctrl+S or Shift+Alt+F1
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct KeyChord {
+    pub key: KeyboardKey,
+    #[serde(default)]
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// A chord with no modifiers held
+    pub fn new(key: KeyboardKey) -> Self {
+        KeyChord {
+            key,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    pub fn with_shift(mut self) -> Self {
+        self.modifiers.shift = true;
+        self
+    }
+
+    pub fn with_ctrl(mut self) -> Self {
+        self.modifiers.ctrl = true;
+        self
+    }
+
+    pub fn with_alt(mut self) -> Self {
+        self.modifiers.alt = true;
+        self
+    }
+
+    pub fn with_logo(mut self) -> Self {
+        self.modifiers.logo = true;
+        self
+    }
+
+    /// Returns true if the key went down during this frame while exactly these modifiers were
+    /// held. Ctrl+S will not trigger on Ctrl+Shift+S.
+    pub fn is_just_pressed(
+        &self,
+        input_state: &InputState,
+    ) -> bool {
+        input_state.is_key_just_down(self.key) && input_state.modifiers() == self.modifiers
+    }
+}
+
+/// A named shortcut registered with the `ShortcutRegistry`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Shortcut {
+    pub name: String,
+    pub chord: KeyChord,
+}
+
+/// Named key chords, used for debug hotkeys and other shortcuts that should be declared once
+/// rather than matched by hand wherever input is processed
+#[derive(Clone, Debug, Default)]
+pub struct ShortcutRegistry {
+    shortcuts: Vec<Shortcut>,
+}
+
+impl ShortcutRegistry {
+    /// Register a chord under the given name. A name may be registered with several chords.
+    pub fn register(
+        &mut self,
+        name: &str,
+        chord: KeyChord,
+    ) {
+        self.shortcuts.push(Shortcut {
+            name: name.to_string(),
+            chord,
+        });
+    }
+
+    /// Remove every chord registered under the given name
+    pub fn unregister(
+        &mut self,
+        name: &str,
+    ) {
+        self.shortcuts.retain(|shortcut| shortcut.name != name);
+    }
+
+    pub fn shortcuts(&self) -> &[Shortcut] {
+        &self.shortcuts
+    }
+
+    /// Returns true if any chord registered under the name was pressed during this frame
+    pub fn just_pressed(
+        &self,
+        name: &str,
+        input_state: &InputState,
+    ) -> bool {
+        self.shortcuts
+            .iter()
+            .any(|shortcut| shortcut.name == name && shortcut.chord.is_just_pressed(input_state))
+    }
+
+    /// Returns true if the chord is registered under any name, for example to keep a key press
+    /// that triggers a shortcut from also being handled elsewhere
+    pub fn is_registered(
+        &self,
+        chord: KeyChord,
+    ) -> bool {
+        self.shortcuts
+            .iter()
+            .any(|shortcut| shortcut.chord == chord)
+    }
+
+    /// Names of all shortcuts that were pressed during this frame
+    pub fn triggered<'a>(
+        &'a self,
+        input_state: &'a InputState,
+    ) -> impl Iterator<Item = &'a str> {
+        self.shortcuts
+            .iter()
+            .filter(move |shortcut| shortcut.chord.is_just_pressed(input_state))
+            .map(|shortcut| shortcut.name.as_str())
+    }
+}
+
+/// Picks out key events that belong to shortcuts so they can be kept from scenes. A key press is
+/// a shortcut if its chord is registered, and the key stays claimed by the shortcut until it is
+/// released, so the release and any key repeats are picked out too even if the modifiers have
+/// changed by then.
+#[derive(Clone, Debug, Default)]
+pub struct ShortcutKeyFilter {
+    held_keys: Vec<KeyboardKey>,
+}
+
+impl ShortcutKeyFilter {
+    /// Returns true if the event is the press or release of a key that triggered a shortcut.
+    /// `modifiers` are the modifiers held when the event was received.
+    pub fn is_shortcut_event(
+        &mut self,
+        event: &InputEvent,
+        modifiers: KeyModifiers,
+        shortcuts: &ShortcutRegistry,
+    ) -> bool {
+        match *event {
+            InputEvent::Keyboard {
+                key,
+                state: ButtonState::Pressed,
+            } => {
+                if self.held_keys.contains(&key) {
+                    true
+                } else if shortcuts.is_registered(KeyChord { key, modifiers }) {
+                    self.held_keys.push(key);
+                    true
+                } else {
+                    false
+                }
+            }
+            InputEvent::Keyboard {
+                key,
+                state: ButtonState::Released,
+            } => match self.held_keys.iter().position(|held_key| *held_key == key) {
+                Some(index) => {
+                    self.held_keys.swap_remove(index);
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn key_event(
+        key: KeyboardKey,
+        state: ButtonState,
+    ) -> InputEvent {
+        InputEvent::Keyboard { key, state }
+    }
+
+    fn ctrl() -> KeyModifiers {
+        KeyModifiers {
+            ctrl: true,
+            ..Default::default()
+        }
+    }
+
+    fn shortcuts() -> ShortcutRegistry {
+        let mut shortcuts = ShortcutRegistry::default();
+        shortcuts.register("save", KeyChord::new(KeyboardKey::S).with_ctrl());
+        shortcuts
+    }
+
+    #[test]
+    fn shortcut_press_and_release_are_filtered() {
+        let shortcuts = shortcuts();
+        let mut filter = ShortcutKeyFilter::default();
+        let press = key_event(KeyboardKey::S, ButtonState::Pressed);
+        let release = key_event(KeyboardKey::S, ButtonState::Released);
+
+        assert!(!filter.is_shortcut_event(&press, KeyModifiers::NONE, &shortcuts));
+        assert!(!filter.is_shortcut_event(&release, KeyModifiers::NONE, &shortcuts));
+
+        assert!(filter.is_shortcut_event(&press, ctrl(), &shortcuts));
+        // Ctrl is let go first, the repeat and release still belong to the shortcut
+        assert!(filter.is_shortcut_event(&press, KeyModifiers::NONE, &shortcuts));
+        assert!(filter.is_shortcut_event(&release, KeyModifiers::NONE, &shortcuts));
+
+        assert!(!filter.is_shortcut_event(&press, KeyModifiers::NONE, &shortcuts));
+        assert!(!filter.is_shortcut_event(&release, KeyModifiers::NONE, &shortcuts));
+    }
+
+    #[test]
+    fn other_events_are_not_filtered() {
+        let shortcuts = shortcuts();
+        let mut filter = ShortcutKeyFilter::default();
+
+        assert!(filter.is_shortcut_event(
+            &key_event(KeyboardKey::S, ButtonState::Pressed),
+            ctrl(),
+            &shortcuts
+        ));
+        assert!(!filter.is_shortcut_event(
+            &key_event(KeyboardKey::W, ButtonState::Released),
+            ctrl(),
+            &shortcuts
+        ));
+        assert!(!filter.is_shortcut_event(&InputEvent::Character('s'), ctrl(), &shortcuts));
+    }
+
+    #[test]
+    fn chords_need_exact_modifiers() {
+        let mut input_state = InputState::new();
+        let chord = KeyChord::new(KeyboardKey::S).with_ctrl();
+        let mut ctrl_shift = ctrl();
+        ctrl_shift.shift = true;
+
+        input_state.handle_event_at(&InputEvent::ModifiersChanged(ctrl_shift), Instant::now());
+        input_state.handle_event_at(
+            &key_event(KeyboardKey::S, ButtonState::Pressed),
+            Instant::now(),
+        );
+        assert!(!chord.is_just_pressed(&input_state));
+
+        input_state.handle_event_at(&InputEvent::ModifiersChanged(ctrl()), Instant::now());
+        assert!(chord.is_just_pressed(&input_state));
+    }
+}
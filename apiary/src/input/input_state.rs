@@ -1,7 +1,15 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::{
+    GamepadEvent, GamepadsState, TextComposition, TextInputEvent, TouchPhase, TouchPoint,
+};
+
 // End-users should provide their own layer to translate from these general values to something
 // appropriate to their platform or windowing system
 // These match winit
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum KeyboardKey {
     /// The '1' key over the letters.
     Key1,
@@ -201,7 +209,112 @@ pub enum KeyboardKey {
     Cut,
 }
 
-#[derive(Copy, Clone)]
+/// A key identified by its physical position on the keyboard rather than the character it
+/// produces. Variants are named after the key in that position on a US QWERTY layout, so `KeyW`
+/// is the key above `KeyS` even on AZERTY or Dvorak keyboards.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PhysicalKey {
+    Escape,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    Digit0,
+    Minus,
+    Equal,
+    Backspace,
+    Tab,
+    KeyQ,
+    KeyW,
+    KeyE,
+    KeyR,
+    KeyT,
+    KeyY,
+    KeyU,
+    KeyI,
+    KeyO,
+    KeyP,
+    BracketLeft,
+    BracketRight,
+    Enter,
+    ControlLeft,
+    KeyA,
+    KeyS,
+    KeyD,
+    KeyF,
+    KeyG,
+    KeyH,
+    KeyJ,
+    KeyK,
+    KeyL,
+    Semicolon,
+    Quote,
+    Backquote,
+    ShiftLeft,
+    Backslash,
+    KeyZ,
+    KeyX,
+    KeyC,
+    KeyV,
+    KeyB,
+    KeyN,
+    KeyM,
+    Comma,
+    Period,
+    Slash,
+    ShiftRight,
+    AltLeft,
+    Space,
+    CapsLock,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    ControlRight,
+    AltRight,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    NumpadEnter,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct MouseButton(pub u16);
 
 impl MouseButton {
@@ -210,7 +323,7 @@ impl MouseButton {
     pub const MIDDLE: MouseButton = MouseButton(2);
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct MouseScrollDelta {
     pub x: f32,
     pub y: f32,
@@ -225,12 +338,89 @@ impl MouseScrollDelta {
     }
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ButtonState {
     Released,
     Pressed,
 }
 
+/// Which modifier keys are held. Left and right variants of a modifier are not distinguished.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct KeyModifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The Windows key on PC, Command on Mac
+    pub logo: bool,
+}
+
+impl KeyModifiers {
+    pub const NONE: KeyModifiers = KeyModifiers {
+        shift: false,
+        ctrl: false,
+        alt: false,
+        logo: false,
+    };
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    /// Returns true if Ctrl, Alt or Logo is held. Keys pressed with one of these held are part
+    /// of a chord rather than ordinary input, Shift alone doesn't count.
+    pub fn has_command_modifier(&self) -> bool {
+        self.ctrl || self.alt || self.logo
+    }
+}
+
+/// A single windowing-system-independent input event. Everything that changes `InputState` can be
+/// expressed as one of these, which allows input to be recorded and replayed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Keyboard {
+        key: KeyboardKey,
+        state: ButtonState,
+    },
+    MouseButton {
+        button: MouseButton,
+        state: ButtonState,
+    },
+    /// Raw device motion, reported even when the cursor is locked or outside the window
+    MouseMotion(glam::Vec2),
+    /// Cursor position within the window, in physical pixels
+    MousePosition(glam::Vec2),
+    MouseWheel(MouseScrollDelta),
+    ModifiersChanged(KeyModifiers),
+    /// A character was typed. Control characters are ignored.
+    Character(char),
+    /// IME composition text changed, None when composition ends or is cancelled. Not produced by
+    /// `WinitInputBackend`, see `TextComposition`.
+    TextComposition(Option<TextComposition>),
+    /// `position` is in physical pixels
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        position: glam::Vec2,
+    },
+    Gamepad(GamepadEvent),
+    /// A key identified by physical position. Sent alongside `Keyboard` when the platform
+    /// scancode is known.
+    PhysicalKey {
+        key: PhysicalKey,
+        state: ButtonState,
+    },
+    /// The ratio of physical pixels to logical units changed, for example because the window
+    /// moved to a monitor with a different DPI
+    ScaleFactorChanged(f32),
+}
+
+/// An input event along with when it was received
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimestampedInputEvent {
+    pub timestamp: Instant,
+    pub event: InputEvent,
+}
+
 /// Encapsulates the state of a mouse drag
 #[derive(Copy, Clone, Debug)]
 pub struct MouseDragState {
@@ -240,15 +430,97 @@ pub struct MouseDragState {
     pub accumulated_frame_delta: glam::Vec2,
 }
 
+impl MouseDragState {
+    /// Start a drag if the pointer has moved more than `min_drag_distance` from where it went
+    /// down, otherwise returns None
+    pub fn try_begin(
+        begin_position: glam::Vec2,
+        position: glam::Vec2,
+        min_drag_distance: f32,
+    ) -> Option<MouseDragState> {
+        let min_drag_distance_met =
+            glam::Vec2::length(begin_position - position) > min_drag_distance;
+        if min_drag_distance_met {
+            let delta = position - begin_position;
+
+            // We dragged a non-trivial amount, start the drag
+            Some(MouseDragState {
+                begin_position,
+                end_position: position,
+                previous_frame_delta: delta,
+                accumulated_frame_delta: delta,
+            })
+        } else {
+            // Pointer moved too small an amount to be considered a drag
+            None
+        }
+    }
+
+    /// The drag state after the pointer moves to the given position
+    pub fn moved_to(
+        &self,
+        position: glam::Vec2,
+    ) -> MouseDragState {
+        let delta = position - (self.begin_position + self.accumulated_frame_delta);
+
+        MouseDragState {
+            begin_position: self.begin_position,
+            end_position: position,
+            previous_frame_delta: delta,
+            accumulated_frame_delta: self.accumulated_frame_delta + delta,
+        }
+    }
+}
+
+/// Thresholds used to tell clicks, multi-clicks, long presses and drags apart
+#[derive(Copy, Clone, Debug)]
+pub struct InputThresholds {
+    /// Distance in logical units that the mouse or a touch has to move to be considered a
+    /// drag rather than a click. Also the furthest apart the clicks of a multi-click may be.
+    pub drag_distance: f32,
+    /// Longest time between presses for them to count as a double/triple click
+    pub multi_click_interval: Duration,
+    /// How long a button has to be held without dragging to be considered a long press
+    pub long_press_duration: Duration,
+}
+
+impl Default for InputThresholds {
+    fn default() -> Self {
+        InputThresholds {
+            drag_distance: 2.0,
+            multi_click_interval: Duration::from_millis(500),
+            long_press_duration: Duration::from_millis(500),
+        }
+    }
+}
+
 /// State of input devices. This is maintained by processing events from winit
+///
+/// Positions are in logical units (physical pixels divided by the window's scale factor) unless
+/// the method name says otherwise, so thresholds behave the same on high-DPI displays.
 pub struct InputState {
+    thresholds: InputThresholds,
+    scale_factor: f32,
+
+    // Whether keys were down when the frame started. The "just" queries for keys replay this
+    // frame's events from here rather than being tracked separately.
     key_is_down: [bool; Self::KEYBOARD_BUTTON_COUNT],
-    key_just_down: [bool; Self::KEYBOARD_BUTTON_COUNT],
-    key_just_up: [bool; Self::KEYBOARD_BUTTON_COUNT],
+    key_was_down: [bool; Self::KEYBOARD_BUTTON_COUNT],
+
+    physical_key_is_down: [bool; Self::PHYSICAL_KEY_COUNT],
+    physical_key_was_down: [bool; Self::PHYSICAL_KEY_COUNT],
+
+    // Whether the most recent press of the key was made with a command modifier held
+    key_is_chorded: [bool; Self::KEYBOARD_BUTTON_COUNT],
+    physical_key_is_chorded: [bool; Self::PHYSICAL_KEY_COUNT],
 
     mouse_position: glam::Vec2,
+    mouse_position_physical: glam::Vec2,
     mouse_motion: glam::Vec2,
     mouse_wheel_delta: MouseScrollDelta,
+    modifiers: KeyModifiers,
+    text_input_events: Vec<TextInputEvent>,
+    text_composition: Option<TextComposition>,
     mouse_button_is_down: [bool; Self::MOUSE_BUTTON_COUNT as usize],
     mouse_button_just_down: [Option<glam::Vec2>; Self::MOUSE_BUTTON_COUNT as usize],
     mouse_button_just_up: [Option<glam::Vec2>; Self::MOUSE_BUTTON_COUNT as usize],
@@ -258,8 +530,21 @@ pub struct InputState {
     mouse_button_went_down_position: [Option<glam::Vec2>; Self::MOUSE_BUTTON_COUNT as usize],
     mouse_button_went_up_position: [Option<glam::Vec2>; Self::MOUSE_BUTTON_COUNT as usize],
 
+    mouse_button_went_down_time: [Option<Instant>; Self::MOUSE_BUTTON_COUNT as usize],
+    // Clicks in the current multi-click sequence, including the press in progress. 0 after a drag.
+    mouse_button_click_count: [u32; Self::MOUSE_BUTTON_COUNT as usize],
+
     mouse_drag_in_progress: [Option<MouseDragState>; Self::MOUSE_BUTTON_COUNT as usize],
     mouse_drag_just_finished: [Option<MouseDragState>; Self::MOUSE_BUTTON_COUNT as usize],
+
+    touches: Vec<TouchPoint>,
+    touches_just_started: Vec<u64>,
+    touches_just_ended: Vec<TouchPoint>,
+
+    gamepads: GamepadsState,
+
+    // Every event received during this frame, in the order received
+    frame_events: Vec<TimestampedInputEvent>,
 }
 
 impl InputState {
@@ -267,35 +552,62 @@ impl InputState {
     /// ignored
     pub const KEYBOARD_BUTTON_COUNT: usize = 255;
 
+    /// Number of physical keys we will track
+    pub const PHYSICAL_KEY_COUNT: usize = PhysicalKey::NumpadEnter as usize + 1;
+
     /// Number of mouse buttons we will track. Any button with a higher index will be ignored.
     pub const MOUSE_BUTTON_COUNT: u16 = 7;
-
-    /// Distance in LogicalPosition units that the mouse has to be dragged to be considered a drag
-    /// rather than a click
-    const MIN_DRAG_DISTANCE: f32 = 2.0;
 }
 
 impl InputState {
     /// Create a new input state to track the given window
     pub fn new() -> InputState {
         InputState {
+            thresholds: InputThresholds::default(),
+            scale_factor: 1.0,
             key_is_down: [false; Self::KEYBOARD_BUTTON_COUNT],
-            key_just_down: [false; Self::KEYBOARD_BUTTON_COUNT],
-            key_just_up: [false; Self::KEYBOARD_BUTTON_COUNT],
+            key_was_down: [false; Self::KEYBOARD_BUTTON_COUNT],
+            physical_key_is_down: [false; Self::PHYSICAL_KEY_COUNT],
+            physical_key_was_down: [false; Self::PHYSICAL_KEY_COUNT],
+            key_is_chorded: [false; Self::KEYBOARD_BUTTON_COUNT],
+            physical_key_is_chorded: [false; Self::PHYSICAL_KEY_COUNT],
             mouse_position: glam::Vec2::ZERO,
+            mouse_position_physical: glam::Vec2::ZERO,
             mouse_motion: glam::Vec2::ZERO,
             mouse_wheel_delta: MouseScrollDelta { x: 0.0, y: 0.0 },
+            modifiers: KeyModifiers::NONE,
+            text_input_events: Vec::default(),
+            text_composition: None,
             mouse_button_is_down: [false; Self::MOUSE_BUTTON_COUNT as usize],
             mouse_button_just_down: [None; Self::MOUSE_BUTTON_COUNT as usize],
             mouse_button_just_up: [None; Self::MOUSE_BUTTON_COUNT as usize],
             mouse_button_just_clicked: [None; Self::MOUSE_BUTTON_COUNT as usize],
             mouse_button_went_down_position: [None; Self::MOUSE_BUTTON_COUNT as usize],
             mouse_button_went_up_position: [None; Self::MOUSE_BUTTON_COUNT as usize],
+            mouse_button_went_down_time: [None; Self::MOUSE_BUTTON_COUNT as usize],
+            mouse_button_click_count: [0; Self::MOUSE_BUTTON_COUNT as usize],
             mouse_drag_in_progress: [None; Self::MOUSE_BUTTON_COUNT as usize],
             mouse_drag_just_finished: [None; Self::MOUSE_BUTTON_COUNT as usize],
+            touches: Vec::default(),
+            touches_just_started: Vec::default(),
+            touches_just_ended: Vec::default(),
+            gamepads: GamepadsState::default(),
+            frame_events: Vec::default(),
         }
     }
 
+    /// Thresholds used to detect clicks, multi-clicks, long presses and drags
+    pub fn thresholds(&self) -> &InputThresholds {
+        &self.thresholds
+    }
+
+    pub fn set_thresholds(
+        &mut self,
+        thresholds: InputThresholds,
+    ) {
+        self.thresholds = thresholds;
+    }
+
     /// Returns true if the given key is down
     pub fn is_key_down(
         &self,
@@ -308,35 +620,144 @@ impl InputState {
         }
     }
 
-    /// Returns true if the key went down during this frame
+    /// Returns true if the key went down during this frame, even if it was released again
     pub fn is_key_just_down(
         &self,
         key: KeyboardKey,
     ) -> bool {
         if let Some(index) = Self::keyboard_button_to_index(key) {
-            self.key_just_down[index]
+            let (went_down, _) =
+                Self::button_transitions(self.key_was_down[index], self.key_event_states(key));
+            went_down
         } else {
             false
         }
     }
 
-    /// Returns true if the key went up during this frame
+    /// Returns true if the key went up during this frame, even if it was pressed again
     pub fn is_key_just_up(
         &self,
         key: KeyboardKey,
     ) -> bool {
         if let Some(index) = Self::keyboard_button_to_index(key) {
-            self.key_just_up[index]
+            let (_, went_up) =
+                Self::button_transitions(self.key_was_down[index], self.key_event_states(key));
+            went_up
         } else {
             false
         }
     }
 
-    /// Get the current mouse position
+    /// Returns true if the key in the given physical position is down, regardless of layout
+    pub fn is_physical_key_down(
+        &self,
+        key: PhysicalKey,
+    ) -> bool {
+        self.physical_key_is_down[key as usize]
+    }
+
+    /// Returns true if the key in the given physical position went down during this frame
+    pub fn is_physical_key_just_down(
+        &self,
+        key: PhysicalKey,
+    ) -> bool {
+        let (went_down, _) = Self::button_transitions(
+            self.physical_key_was_down[key as usize],
+            self.physical_key_event_states(key),
+        );
+        went_down
+    }
+
+    /// Returns true if the key in the given physical position went up during this frame
+    pub fn is_physical_key_just_up(
+        &self,
+        key: PhysicalKey,
+    ) -> bool {
+        let (_, went_up) = Self::button_transitions(
+            self.physical_key_was_down[key as usize],
+            self.physical_key_event_states(key),
+        );
+        went_up
+    }
+
+    /// Returns true if the key was last pressed while Ctrl, Alt or Logo was held, such as the S
+    /// of Ctrl+S. This stays true until the key is pressed again so its release can be ignored
+    /// too. Action and axis bindings ignore chorded keys.
+    pub fn is_key_chorded(
+        &self,
+        key: KeyboardKey,
+    ) -> bool {
+        Self::keyboard_button_to_index(key).map_or(false, |index| self.key_is_chorded[index])
+    }
+
+    /// Returns true if the key in the given physical position was last pressed while Ctrl, Alt or
+    /// Logo was held, see `is_key_chorded`
+    pub fn is_physical_key_chorded(
+        &self,
+        key: PhysicalKey,
+    ) -> bool {
+        self.physical_key_is_chorded[key as usize]
+    }
+
+    /// Get the modifier keys that are currently held
+    pub fn modifiers(&self) -> KeyModifiers {
+        self.modifiers
+    }
+
+    /// Text editing operations received during this frame, in order
+    pub fn text_input_events(&self) -> &[TextInputEvent] {
+        &self.text_input_events
+    }
+
+    /// Printable characters typed during this frame
+    pub fn text_input(&self) -> String {
+        self.text_input_events
+            .iter()
+            .filter_map(|event| match event {
+                TextInputEvent::Char(c) => Some(*c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// IME composition text that is being edited but hasn't been committed yet. Always None with
+    /// the winit backend, see `TextComposition`.
+    pub fn text_composition(&self) -> Option<&TextComposition> {
+        self.text_composition.as_ref()
+    }
+
+    /// Ratio of physical pixels to logical units for the window
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    /// Convert a position in physical pixels to logical units
+    pub fn physical_to_logical(
+        &self,
+        position: glam::Vec2,
+    ) -> glam::Vec2 {
+        position / self.scale_factor
+    }
+
+    /// Convert a position in logical units to physical pixels
+    pub fn logical_to_physical(
+        &self,
+        position: glam::Vec2,
+    ) -> glam::Vec2 {
+        position * self.scale_factor
+    }
+
+    /// Get the current mouse position in logical units
     pub fn mouse_position(&self) -> glam::Vec2 {
         self.mouse_position
     }
 
+    /// Get the current mouse position in physical pixels, for example to compare against the
+    /// swapchain size
+    pub fn mouse_position_physical(&self) -> glam::Vec2 {
+        self.mouse_position_physical
+    }
+
     pub fn mouse_motion(&self) -> glam::Vec2 {
         self.mouse_motion
     }
@@ -433,6 +854,43 @@ impl InputState {
         }
     }
 
+    /// Returns how many clicks in a row the click this frame completes, for example 2 for a double
+    /// click. Returns 0 if the button wasn't just clicked.
+    pub fn mouse_click_count(
+        &self,
+        mouse_button: MouseButton,
+    ) -> u32 {
+        if let Some(index) = Self::mouse_button_to_index(mouse_button) {
+            if self.mouse_button_just_clicked[index].is_some() {
+                return self.mouse_button_click_count[index];
+            }
+        }
+
+        0
+    }
+
+    /// Returns true if the button has been held for at least the long press duration without
+    /// being dragged. `now` should come from the `ClockResource`, the clock input events are
+    /// timestamped with, so that the result is the same when input is replayed.
+    pub fn is_mouse_long_pressed(
+        &self,
+        mouse_button: MouseButton,
+        now: Instant,
+    ) -> bool {
+        if let Some(index) = Self::mouse_button_to_index(mouse_button) {
+            if !self.mouse_button_is_down[index] || self.mouse_drag_in_progress[index].is_some() {
+                return false;
+            }
+
+            if let Some(went_down_time) = self.mouse_button_went_down_time[index] {
+                return now.saturating_duration_since(went_down_time)
+                    >= self.thresholds.long_press_duration;
+            }
+        }
+
+        false
+    }
+
     /// Returns the position the button went down at previously. This could have been some time ago.
     pub fn mouse_button_went_down_position(
         &self,
@@ -506,21 +964,142 @@ impl InputState {
         }
     }
 
+    /// Touches that are currently in contact, in the order they started
+    pub fn touches(&self) -> &[TouchPoint] {
+        &self.touches
+    }
+
+    /// Returns the active touch with the given id, otherwise None
+    pub fn touch(
+        &self,
+        id: u64,
+    ) -> Option<&TouchPoint> {
+        self.touches.iter().find(|touch| touch.id == id)
+    }
+
+    /// Returns true if the touch with the given id started during this frame
+    pub fn is_touch_just_started(
+        &self,
+        id: u64,
+    ) -> bool {
+        self.touches_just_started.contains(&id)
+    }
+
+    /// Touches that started during this frame and are still active
+    pub fn touches_just_started(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.touches
+            .iter()
+            .filter(move |touch| self.touches_just_started.contains(&touch.id))
+    }
+
+    /// Touches that ended or were cancelled during this frame
+    pub fn touches_just_ended(&self) -> &[TouchPoint] {
+        &self.touches_just_ended
+    }
+
+    /// Every event received during this frame, in the order it was received. Unlike the "just"
+    /// queries this preserves ordering between events and repeated presses within a frame. The
+    /// "just" queries for keys are derived from it.
+    pub fn frame_events(&self) -> &[TimestampedInputEvent] {
+        &self.frame_events
+    }
+
+    /// Returns how many times the key went down during this frame, including key repeats
+    pub fn key_press_count(
+        &self,
+        key: KeyboardKey,
+    ) -> usize {
+        self.frame_events
+            .iter()
+            .filter(|e| {
+                e.event
+                    == InputEvent::Keyboard {
+                        key,
+                        state: ButtonState::Pressed,
+                    }
+            })
+            .count()
+    }
+
+    /// Returns how many times the button went down during this frame
+    pub fn mouse_press_count(
+        &self,
+        mouse_button: MouseButton,
+    ) -> usize {
+        self.frame_events
+            .iter()
+            .filter(|e| {
+                e.event
+                    == InputEvent::MouseButton {
+                        button: mouse_button,
+                        state: ButtonState::Pressed,
+                    }
+            })
+            .count()
+    }
+
+    /// State of all connected gamepads
+    pub fn gamepads(&self) -> &GamepadsState {
+        &self.gamepads
+    }
+
+    /// Mutable gamepad state, for example to adjust dead zones
+    pub fn gamepads_mut(&mut self) -> &mut GamepadsState {
+        &mut self.gamepads
+    }
+
     //
     // Handlers for significant events
     //
 
+    /// Apply a single input event that was received at the given time. Timestamps should come
+    /// from the `ClockResource` so that input can be recorded, replayed and tested.
+    pub fn handle_event_at(
+        &mut self,
+        event: &InputEvent,
+        timestamp: Instant,
+    ) {
+        self.frame_events.push(TimestampedInputEvent {
+            timestamp,
+            event: event.clone(),
+        });
+
+        match event {
+            InputEvent::Keyboard { key, state } => self.apply_keyboard_event(*key, *state),
+            InputEvent::MouseButton { button, state } => {
+                self.handle_mouse_button_event(*button, *state, timestamp)
+            }
+            InputEvent::MouseMotion(delta) => self.handle_mouse_motion_event(*delta),
+            InputEvent::MousePosition(position) => {
+                self.handle_mouse_update_position(self.physical_to_logical(*position))
+            }
+            InputEvent::MouseWheel(delta) => self.handle_mouse_wheel_event(*delta),
+            InputEvent::ModifiersChanged(modifiers) => self.handle_modifiers_changed(*modifiers),
+            InputEvent::Character(c) => self.handle_received_character(*c),
+            InputEvent::TextComposition(composition) => {
+                self.handle_text_composition(composition.clone())
+            }
+            InputEvent::Touch {
+                id,
+                phase,
+                position,
+            } => self.handle_touch_event(*id, *phase, self.physical_to_logical(*position)),
+            InputEvent::Gamepad(gamepad_event) => self.gamepads.handle_event(*gamepad_event),
+            InputEvent::PhysicalKey { key, state } => self.handle_physical_key_event(*key, *state),
+            InputEvent::ScaleFactorChanged(scale_factor) => {
+                self.handle_scale_factor_changed(*scale_factor)
+            }
+        }
+    }
+
     /// Call at the end of every frame. This clears events that were "just" completed.
     pub fn end_frame(&mut self) {
         self.mouse_wheel_delta = MouseScrollDelta { x: 0.0, y: 0.0 };
+        self.text_input_events.clear();
+        self.frame_events.clear();
 
-        for value in self.key_just_down.iter_mut() {
-            *value = false;
-        }
-
-        for value in self.key_just_up.iter_mut() {
-            *value = false;
-        }
+        self.key_was_down = self.key_is_down;
+        self.physical_key_was_down = self.physical_key_is_down;
 
         for value in self.mouse_button_just_down.iter_mut() {
             *value = None;
@@ -546,35 +1125,132 @@ impl InputState {
                 //v.world_space_previous_frame_delta = glam::Vec2::zero()
             }
         }
+
+        self.touches_just_started.clear();
+        self.touches_just_ended.clear();
+
+        self.gamepads.end_frame();
+
+        for touch in self.touches.iter_mut() {
+            if let Some(drag) = &mut touch.drag {
+                drag.previous_frame_delta = glam::Vec2::ZERO;
+            }
+        }
     }
 
-    /// Call when a key event occurs
+    /// Call when a key event occurs. The event is timestamped now and goes through
+    /// `handle_event_at`, so the "just" queries see it. Prefer `handle_event_at` with a time from
+    /// the `ClockResource` when input needs to be replayed.
     pub fn handle_keyboard_event(
         &mut self,
         keyboard_button: KeyboardKey,
         button_state: ButtonState,
+    ) {
+        self.handle_event_at(
+            &InputEvent::Keyboard {
+                key: keyboard_button,
+                state: button_state,
+            },
+            Instant::now(),
+        );
+    }
+
+    // Key events are only applied through `handle_event_at` so that they are in `frame_events`,
+    // which the "just" queries are derived from
+    fn apply_keyboard_event(
+        &mut self,
+        keyboard_button: KeyboardKey,
+        button_state: ButtonState,
     ) {
         if let Some(kc) = Self::keyboard_button_to_index(keyboard_button) {
             // Assign true if key is down, or false if key is up
             if button_state == ButtonState::Pressed {
+                // Repeats of a held key keep the chord state of the first press
                 if !self.key_is_down[kc] {
-                    self.key_just_down[kc] = true;
+                    self.key_is_chorded[kc] = self.modifiers.has_command_modifier();
                 }
-                self.key_is_down[kc] = true
-            } else {
-                if self.key_is_down[kc] {
-                    self.key_just_up[kc] = true;
+                self.key_is_down[kc] = true;
+
+                // Key repeats arrive as additional presses, so editing keys repeat as expected
+                if let Some(text_input_event) = TextInputEvent::from_key(keyboard_button) {
+                    self.text_input_events.push(text_input_event);
                 }
+            } else {
                 self.key_is_down[kc] = false
             }
         }
     }
 
-    /// Call when a mouse button event occurs
+    fn handle_physical_key_event(
+        &mut self,
+        key: PhysicalKey,
+        button_state: ButtonState,
+    ) {
+        let is_pressed = button_state == ButtonState::Pressed;
+        if is_pressed && !self.physical_key_is_down[key as usize] {
+            self.physical_key_is_chorded[key as usize] = self.modifiers.has_command_modifier();
+        }
+        self.physical_key_is_down[key as usize] = is_pressed;
+    }
+
+    // States of this frame's events for the key, in order
+    fn key_event_states(
+        &self,
+        key: KeyboardKey,
+    ) -> impl Iterator<Item = ButtonState> + '_ {
+        self.frame_events
+            .iter()
+            .filter_map(move |e| match &e.event {
+                InputEvent::Keyboard {
+                    key: event_key,
+                    state,
+                } if *event_key == key => Some(*state),
+                _ => None,
+            })
+    }
+
+    fn physical_key_event_states(
+        &self,
+        key: PhysicalKey,
+    ) -> impl Iterator<Item = ButtonState> + '_ {
+        self.frame_events
+            .iter()
+            .filter_map(move |e| match &e.event {
+                InputEvent::PhysicalKey {
+                    key: event_key,
+                    state,
+                } if *event_key == key => Some(*state),
+                _ => None,
+            })
+    }
+
+    // Replays a button's events from whether it was down when the frame started, returning
+    // whether it went down and whether it went up. Repeated presses while held are not a change.
+    fn button_transitions(
+        was_down: bool,
+        states: impl Iterator<Item = ButtonState>,
+    ) -> (bool, bool) {
+        let mut is_down = was_down;
+        let mut went_down = false;
+        let mut went_up = false;
+        for state in states {
+            match state {
+                ButtonState::Pressed => went_down |= !is_down,
+                ButtonState::Released => went_up |= is_down,
+            }
+            is_down = state == ButtonState::Pressed;
+        }
+
+        (went_down, went_up)
+    }
+
+    /// Call when a mouse button event occurred at the given time. The timestamps of presses are
+    /// used to detect multi-clicks and long presses.
     pub fn handle_mouse_button_event(
         &mut self,
         button: MouseButton,
         button_event: ButtonState,
+        timestamp: Instant,
     ) {
         if let Some(button_index) = Self::mouse_button_to_index(button) {
             assert!(button_index < InputState::MOUSE_BUTTON_COUNT as usize);
@@ -582,10 +1258,35 @@ impl InputState {
             // Update is down/up, just down/up
             match button_event {
                 ButtonState::Pressed => {
+                    // Continue a multi-click if the previous press was a click that was recent
+                    // and close enough to this one
+                    let continues_multi_click = self.mouse_button_click_count[button_index] > 0
+                        && self.mouse_button_went_down_time[button_index].map_or(
+                            false,
+                            |went_down_time| {
+                                timestamp.saturating_duration_since(went_down_time)
+                                    <= self.thresholds.multi_click_interval
+                            },
+                        )
+                        && self.mouse_button_went_down_position[button_index].map_or(
+                            false,
+                            |went_down_position| {
+                                glam::Vec2::length(went_down_position - self.mouse_position)
+                                    <= self.thresholds.drag_distance
+                            },
+                        );
+
+                    self.mouse_button_click_count[button_index] = if continues_multi_click {
+                        self.mouse_button_click_count[button_index] + 1
+                    } else {
+                        1
+                    };
+
                     self.mouse_button_just_down[button_index] = Some(self.mouse_position);
                     self.mouse_button_is_down[button_index] = true;
 
                     self.mouse_button_went_down_position[button_index] = Some(self.mouse_position);
+                    self.mouse_button_went_down_time[button_index] = Some(timestamp);
                 }
                 ButtonState::Released => {
                     self.mouse_button_just_up[button_index] = Some(self.mouse_position);
@@ -595,17 +1296,11 @@ impl InputState {
 
                     match self.mouse_drag_in_progress[button_index] {
                         Some(in_progress) => {
-                            let delta = self.mouse_position
-                                - (in_progress.begin_position
-                                    + in_progress.accumulated_frame_delta);
-
-                            self.mouse_drag_just_finished[button_index] = Some(MouseDragState {
-                                begin_position: in_progress.begin_position,
-                                end_position: self.mouse_position,
-                                previous_frame_delta: delta,
-                                accumulated_frame_delta: in_progress.accumulated_frame_delta
-                                    + delta,
-                            });
+                            self.mouse_drag_just_finished[button_index] =
+                                Some(in_progress.moved_to(self.mouse_position));
+
+                            // A drag ends any multi-click sequence
+                            self.mouse_button_click_count[button_index] = 0;
                         }
                         None => {
                             self.mouse_button_just_clicked[button_index] = Some(self.mouse_position)
@@ -626,13 +1321,14 @@ impl InputState {
         self.mouse_motion += delta
     }
 
-    /// Call when a cursor moves within the window
+    /// Call when a cursor moves within the window. `position` is in logical units.
     pub fn handle_mouse_update_position(
         &mut self,
         position: glam::Vec2,
     ) {
         // Update mouse position
         self.mouse_position = position;
+        self.mouse_position_physical = self.logical_to_physical(position);
 
         // Update drag in progress state
         for i in 0..Self::MOUSE_BUTTON_COUNT {
@@ -641,25 +1337,11 @@ impl InputState {
                 self.mouse_drag_in_progress[i] = match self.mouse_drag_in_progress[i] {
                     None => {
                         match self.mouse_button_went_down_position[i] {
-                            Some(went_down_position) => {
-                                let min_drag_distance_met =
-                                    glam::Vec2::length(went_down_position - self.mouse_position)
-                                        > Self::MIN_DRAG_DISTANCE;
-                                if min_drag_distance_met {
-                                    let delta = self.mouse_position - went_down_position;
-
-                                    // We dragged a non-trivial amount, start the drag
-                                    Some(MouseDragState {
-                                        begin_position: went_down_position,
-                                        end_position: self.mouse_position,
-                                        previous_frame_delta: delta,
-                                        accumulated_frame_delta: delta,
-                                    })
-                                } else {
-                                    // Mouse moved too small an amount to be considered a drag
-                                    None
-                                }
-                            }
+                            Some(went_down_position) => MouseDragState::try_begin(
+                                went_down_position,
+                                self.mouse_position,
+                                self.thresholds.drag_distance,
+                            ),
 
                             // We don't know where the mosue went down, so we can't start a drag
                             None => None,
@@ -667,23 +1349,71 @@ impl InputState {
                     }
                     Some(old_drag_state) => {
                         // We were already dragging, so just update the end position
-
-                        let delta = self.mouse_position
-                            - (old_drag_state.begin_position
-                                + old_drag_state.accumulated_frame_delta);
-
-                        Some(MouseDragState {
-                            begin_position: old_drag_state.begin_position,
-                            end_position: self.mouse_position,
-                            previous_frame_delta: delta,
-                            accumulated_frame_delta: old_drag_state.accumulated_frame_delta + delta,
-                        })
+                        Some(old_drag_state.moved_to(self.mouse_position))
                     }
                 };
             }
         }
     }
 
+    /// Call when the window's scale factor changes. The cursor stays at the same physical
+    /// position, so its logical position is updated.
+    pub fn handle_scale_factor_changed(
+        &mut self,
+        scale_factor: f32,
+    ) {
+        if scale_factor > 0.0 {
+            self.scale_factor = scale_factor;
+            self.mouse_position = self.physical_to_logical(self.mouse_position_physical);
+        }
+    }
+
+    /// Call when a touch starts, moves or ends. `position` is in logical units.
+    pub fn handle_touch_event(
+        &mut self,
+        id: u64,
+        phase: TouchPhase,
+        position: glam::Vec2,
+    ) {
+        match phase {
+            TouchPhase::Started => {
+                // Drop any stale touch with the same id, the platform is allowed to reuse ids
+                self.touches.retain(|touch| touch.id != id);
+                self.touches.push(TouchPoint {
+                    id,
+                    begin_position: position,
+                    position,
+                    phase,
+                    drag: None,
+                });
+                self.touches_just_started.push(id);
+            }
+            TouchPhase::Moved => {
+                if let Some(touch) = self.touches.iter_mut().find(|touch| touch.id == id) {
+                    touch.position = position;
+                    touch.phase = phase;
+                    touch.drag = match touch.drag {
+                        None => MouseDragState::try_begin(
+                            touch.begin_position,
+                            position,
+                            self.thresholds.drag_distance,
+                        ),
+                        Some(drag) => Some(drag.moved_to(position)),
+                    };
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if let Some(index) = self.touches.iter().position(|touch| touch.id == id) {
+                    let mut touch = self.touches.remove(index);
+                    touch.position = position;
+                    touch.phase = phase;
+                    touch.drag = touch.drag.map(|drag| drag.moved_to(position));
+                    self.touches_just_ended.push(touch);
+                }
+            }
+        }
+    }
+
     pub fn handle_mouse_wheel_event(
         &mut self,
         delta: MouseScrollDelta,
@@ -692,6 +1422,34 @@ impl InputState {
         self.mouse_wheel_delta.y += delta.y;
     }
 
+    /// Call when a character is typed
+    pub fn handle_received_character(
+        &mut self,
+        c: char,
+    ) {
+        // Backspace, enter, etc. are handled as key presses so they behave the same on every
+        // platform
+        if !c.is_control() {
+            self.text_input_events.push(TextInputEvent::Char(c));
+        }
+    }
+
+    /// Call when IME composition text changes
+    pub fn handle_text_composition(
+        &mut self,
+        composition: Option<TextComposition>,
+    ) {
+        self.text_composition = composition;
+    }
+
+    /// Call when the set of held modifier keys changes
+    pub fn handle_modifiers_changed(
+        &mut self,
+        modifiers: KeyModifiers,
+    ) {
+        self.modifiers = modifiers;
+    }
+
     /// Convert the winit mouse button enum into a numerical index
     pub fn mouse_button_to_index(button: MouseButton) -> Option<usize> {
         if button.0 >= Self::MOUSE_BUTTON_COUNT {
@@ -719,3 +1477,223 @@ impl InputState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mouse_button(
+        input_state: &mut InputState,
+        state: ButtonState,
+        timestamp: Instant,
+    ) {
+        input_state.handle_event_at(
+            &InputEvent::MouseButton {
+                button: MouseButton::LEFT,
+                state,
+            },
+            timestamp,
+        );
+    }
+
+    fn move_mouse(
+        input_state: &mut InputState,
+        position: glam::Vec2,
+        timestamp: Instant,
+    ) {
+        input_state.handle_event_at(&InputEvent::MousePosition(position), timestamp);
+    }
+
+    // Press and release in one frame, returning the click count reported for that frame
+    fn click(
+        input_state: &mut InputState,
+        timestamp: Instant,
+    ) -> u32 {
+        mouse_button(input_state, ButtonState::Pressed, timestamp);
+        mouse_button(
+            input_state,
+            ButtonState::Released,
+            timestamp + Duration::from_millis(20),
+        );
+        let click_count = input_state.mouse_click_count(MouseButton::LEFT);
+        input_state.end_frame();
+        click_count
+    }
+
+    fn key(
+        input_state: &mut InputState,
+        key: KeyboardKey,
+        state: ButtonState,
+    ) {
+        input_state.handle_event_at(&InputEvent::Keyboard { key, state }, Instant::now());
+    }
+
+    #[test]
+    fn key_pressed_and_released_within_a_frame_is_just_down() {
+        let mut input_state = InputState::new();
+        key(&mut input_state, KeyboardKey::Space, ButtonState::Pressed);
+        key(&mut input_state, KeyboardKey::Space, ButtonState::Released);
+
+        assert!(input_state.is_key_just_down(KeyboardKey::Space));
+        assert!(input_state.is_key_just_up(KeyboardKey::Space));
+        assert!(!input_state.is_key_down(KeyboardKey::Space));
+        assert_eq!(input_state.key_press_count(KeyboardKey::Space), 1);
+
+        input_state.end_frame();
+        assert!(!input_state.is_key_just_down(KeyboardKey::Space));
+        assert!(!input_state.is_key_just_up(KeyboardKey::Space));
+    }
+
+    #[test]
+    fn key_repeat_is_not_just_down() {
+        let mut input_state = InputState::new();
+        key(&mut input_state, KeyboardKey::Space, ButtonState::Pressed);
+        assert!(input_state.is_key_just_down(KeyboardKey::Space));
+        input_state.end_frame();
+
+        key(&mut input_state, KeyboardKey::Space, ButtonState::Pressed);
+        key(&mut input_state, KeyboardKey::Space, ButtonState::Pressed);
+        assert!(input_state.is_key_down(KeyboardKey::Space));
+        assert!(!input_state.is_key_just_down(KeyboardKey::Space));
+        assert_eq!(input_state.key_press_count(KeyboardKey::Space), 2);
+
+        // Released and pressed again within a frame is both
+        key(&mut input_state, KeyboardKey::Space, ButtonState::Released);
+        key(&mut input_state, KeyboardKey::Space, ButtonState::Pressed);
+        assert!(input_state.is_key_just_up(KeyboardKey::Space));
+        assert!(input_state.is_key_just_down(KeyboardKey::Space));
+        assert!(!input_state.is_key_just_down(KeyboardKey::A));
+    }
+
+    #[test]
+    fn handle_keyboard_event_is_recorded_for_the_frame() {
+        let mut input_state = InputState::new();
+        input_state.handle_keyboard_event(KeyboardKey::Space, ButtonState::Pressed);
+        assert!(input_state.is_key_down(KeyboardKey::Space));
+        assert!(input_state.is_key_just_down(KeyboardKey::Space));
+        assert_eq!(input_state.frame_events().len(), 1);
+
+        input_state.end_frame();
+        input_state.handle_keyboard_event(KeyboardKey::Space, ButtonState::Released);
+        assert!(input_state.is_key_just_up(KeyboardKey::Space));
+        assert!(!input_state.is_key_down(KeyboardKey::Space));
+    }
+
+    #[test]
+    fn physical_key_tapped_within_a_frame_is_just_down() {
+        let mut input_state = InputState::new();
+        for state in [ButtonState::Pressed, ButtonState::Released] {
+            input_state.handle_event_at(
+                &InputEvent::PhysicalKey {
+                    key: PhysicalKey::KeyW,
+                    state,
+                },
+                Instant::now(),
+            );
+        }
+
+        assert!(input_state.is_physical_key_just_down(PhysicalKey::KeyW));
+        assert!(input_state.is_physical_key_just_up(PhysicalKey::KeyW));
+        assert!(!input_state.is_physical_key_down(PhysicalKey::KeyW));
+    }
+
+    #[test]
+    fn double_and_triple_click() {
+        let mut input_state = InputState::new();
+        let start = Instant::now();
+
+        assert_eq!(click(&mut input_state, start), 1);
+        assert_eq!(
+            click(&mut input_state, start + Duration::from_millis(200)),
+            2
+        );
+        assert_eq!(
+            click(&mut input_state, start + Duration::from_millis(400)),
+            3
+        );
+        assert_eq!(input_state.mouse_click_count(MouseButton::LEFT), 0);
+    }
+
+    #[test]
+    fn slow_clicks_are_single_clicks() {
+        let mut input_state = InputState::new();
+        let start = Instant::now();
+        let interval = input_state.thresholds().multi_click_interval;
+
+        assert_eq!(click(&mut input_state, start), 1);
+        assert_eq!(
+            click(
+                &mut input_state,
+                start + interval + Duration::from_millis(1)
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn clicks_far_apart_are_single_clicks() {
+        let mut input_state = InputState::new();
+        let start = Instant::now();
+        let drag_distance = input_state.thresholds().drag_distance;
+
+        assert_eq!(click(&mut input_state, start), 1);
+        move_mouse(
+            &mut input_state,
+            glam::Vec2::new(drag_distance * 2.0, 0.0),
+            start + Duration::from_millis(100),
+        );
+        assert_eq!(
+            click(&mut input_state, start + Duration::from_millis(200)),
+            1
+        );
+    }
+
+    #[test]
+    fn drag_resets_click_count() {
+        let mut input_state = InputState::new();
+        let start = Instant::now();
+        let drag_end = glam::Vec2::new(input_state.thresholds().drag_distance * 2.0, 0.0);
+
+        assert_eq!(click(&mut input_state, start), 1);
+
+        let t = start + Duration::from_millis(100);
+        mouse_button(&mut input_state, ButtonState::Pressed, t);
+        move_mouse(&mut input_state, drag_end, t);
+        assert!(input_state.is_mouse_drag_in_progress(MouseButton::LEFT));
+        mouse_button(&mut input_state, ButtonState::Released, t);
+        assert!(input_state.is_mouse_drag_just_finished(MouseButton::LEFT));
+        assert!(!input_state.is_mouse_button_just_clicked(MouseButton::LEFT));
+        assert_eq!(input_state.mouse_click_count(MouseButton::LEFT), 0);
+        input_state.end_frame();
+
+        // Pressing again where the drag ended starts a new sequence rather than continuing it
+        assert_eq!(
+            click(&mut input_state, start + Duration::from_millis(200)),
+            1
+        );
+    }
+
+    #[test]
+    fn long_press_threshold() {
+        let mut input_state = InputState::new();
+        let start = Instant::now();
+        let long_press_duration = input_state.thresholds().long_press_duration;
+
+        assert!(!input_state.is_mouse_long_pressed(MouseButton::LEFT, start));
+
+        mouse_button(&mut input_state, ButtonState::Pressed, start);
+        input_state.end_frame();
+        let just_before = start + long_press_duration - Duration::from_millis(1);
+        assert!(!input_state.is_mouse_long_pressed(MouseButton::LEFT, just_before));
+        assert!(input_state.is_mouse_long_pressed(MouseButton::LEFT, start + long_press_duration));
+
+        // Dragging turns the press into a drag rather than a long press
+        let later = start + long_press_duration * 2;
+        let drag_end = glam::Vec2::new(input_state.thresholds().drag_distance * 2.0, 0.0);
+        move_mouse(&mut input_state, drag_end, later);
+        assert!(!input_state.is_mouse_long_pressed(MouseButton::LEFT, later));
+
+        mouse_button(&mut input_state, ButtonState::Released, later);
+        assert!(!input_state.is_mouse_long_pressed(MouseButton::LEFT, later));
+    }
+}
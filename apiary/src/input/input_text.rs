@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+
+use crate::input::{InputState, KeyboardKey};
+
+/// A single text editing operation received during a frame, in the order it was received
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TextInputEvent {
+    /// A printable character was typed
+    Char(char),
+    Backspace,
+    Delete,
+    CursorLeft,
+    CursorRight,
+    CursorHome,
+    CursorEnd,
+    /// Enter was pressed
+    Submit,
+}
+
+impl TextInputEvent {
+    /// Editing operations that are driven by keys rather than by typed characters. These are
+    /// produced for every press, including key repeats.
+    pub fn from_key(key: KeyboardKey) -> Option<Self> {
+        match key {
+            KeyboardKey::Back => Some(TextInputEvent::Backspace),
+            KeyboardKey::Delete => Some(TextInputEvent::Delete),
+            KeyboardKey::Left => Some(TextInputEvent::CursorLeft),
+            KeyboardKey::Right => Some(TextInputEvent::CursorRight),
+            KeyboardKey::Home => Some(TextInputEvent::CursorHome),
+            KeyboardKey::End => Some(TextInputEvent::CursorEnd),
+            KeyboardKey::Return | KeyboardKey::NumpadEnter => Some(TextInputEvent::Submit),
+            _ => None,
+        }
+    }
+}
+
+/// In-progress IME composition (pre-edit) text that has not been committed yet.
+///
+/// winit 0.24 has no IME support, so `WinitInputBackend` never produces composition events and
+/// IME input only arrives as committed characters. Composition is only seen when another backend,
+/// or a test, feeds `InputEvent::TextComposition` in directly.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextComposition {
+    pub text: String,
+    /// Cursor position within `text`, in chars
+    pub cursor: usize,
+}
+
+/// An editable line of text driven by the text input stream of `InputState`. Use this to build
+/// name-entry fields or a console prompt outside of egui.
+#[derive(Clone, Debug, Default)]
+pub struct TextInputBuffer {
+    text: String,
+    /// Cursor position in chars, 0..=char count
+    cursor: usize,
+    max_chars: Option<usize>,
+}
+
+impl TextInputBuffer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_text(text: &str) -> Self {
+        TextInputBuffer {
+            text: text.to_string(),
+            cursor: text.chars().count(),
+            max_chars: None,
+        }
+    }
+
+    /// Limit the number of chars the buffer will accept
+    pub fn with_max_chars(
+        mut self,
+        max_chars: usize,
+    ) -> Self {
+        self.max_chars = Some(max_chars);
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    /// Remove and return the contents of the buffer
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    pub fn insert_char(
+        &mut self,
+        c: char,
+    ) {
+        if let Some(max_chars) = self.max_chars {
+            if self.char_count() >= max_chars {
+                return;
+            }
+        }
+
+        let byte_index = self.byte_index(self.cursor);
+        self.text.insert(byte_index, c);
+        self.cursor += 1;
+    }
+
+    pub fn insert_str(
+        &mut self,
+        s: &str,
+    ) {
+        for c in s.chars() {
+            self.insert_char(c);
+        }
+    }
+
+    /// Remove the char before the cursor
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let byte_index = self.byte_index(self.cursor);
+            self.text.remove(byte_index);
+        }
+    }
+
+    /// Remove the char after the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.char_count() {
+            let byte_index = self.byte_index(self.cursor);
+            self.text.remove(byte_index);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.char_count());
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.char_count();
+    }
+
+    /// Apply a single editing operation. Returns true if it was a submit.
+    pub fn apply_event(
+        &mut self,
+        event: TextInputEvent,
+    ) -> bool {
+        match event {
+            TextInputEvent::Char(c) => self.insert_char(c),
+            TextInputEvent::Backspace => self.backspace(),
+            TextInputEvent::Delete => self.delete(),
+            TextInputEvent::CursorLeft => self.move_left(),
+            TextInputEvent::CursorRight => self.move_right(),
+            TextInputEvent::CursorHome => self.move_home(),
+            TextInputEvent::CursorEnd => self.move_end(),
+            TextInputEvent::Submit => return true,
+        }
+
+        false
+    }
+
+    /// Apply all text input received this frame. Returns true if enter was pressed.
+    pub fn update(
+        &mut self,
+        input_state: &InputState,
+    ) -> bool {
+        let mut submitted = false;
+        for event in input_state.text_input_events() {
+            submitted |= self.apply_event(*event);
+        }
+
+        submitted
+    }
+
+    /// The text as it should be displayed, with any in-progress IME composition spliced in at the
+    /// cursor
+    pub fn display_text(
+        &self,
+        input_state: &InputState,
+    ) -> String {
+        let mut display_text = self.text.clone();
+        if let Some(composition) = input_state.text_composition() {
+            display_text.insert_str(self.byte_index(self.cursor), &composition.text);
+        }
+
+        display_text
+    }
+
+    fn char_count(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    fn byte_index(
+        &self,
+        char_index: usize,
+    ) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_index)
+            .map(|(byte_index, _)| byte_index)
+            .unwrap_or_else(|| self.text.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputEvent;
+    use std::time::Instant;
+
+    #[test]
+    fn insert_at_cursor() {
+        let mut buffer = TextInputBuffer::new();
+        buffer.insert_str("hllo");
+        assert_eq!(buffer.cursor(), 4);
+
+        buffer.move_home();
+        buffer.move_right();
+        buffer.insert_char('e');
+        assert_eq!(buffer.text(), "hello");
+        assert_eq!(buffer.cursor(), 2);
+    }
+
+    #[test]
+    fn insert_respects_max_chars() {
+        let mut buffer = TextInputBuffer::new().with_max_chars(3);
+        buffer.insert_str("abcd");
+        assert_eq!(buffer.text(), "abc");
+    }
+
+    #[test]
+    fn backspace_at_start_does_nothing() {
+        let mut buffer = TextInputBuffer::with_text("abc");
+        buffer.move_home();
+        buffer.backspace();
+        assert_eq!(buffer.text(), "abc");
+        assert_eq!(buffer.cursor(), 0);
+    }
+
+    #[test]
+    fn delete_at_end_does_nothing() {
+        let mut buffer = TextInputBuffer::with_text("abc");
+        buffer.delete();
+        assert_eq!(buffer.text(), "abc");
+        assert_eq!(buffer.cursor(), 3);
+
+        buffer.move_left();
+        buffer.delete();
+        assert_eq!(buffer.text(), "ab");
+    }
+
+    #[test]
+    fn cursor_moves_over_multi_byte_chars() {
+        // 2, 3 and 4 byte chars
+        let mut buffer = TextInputBuffer::with_text("é日😀");
+        assert_eq!(buffer.cursor(), 3);
+
+        buffer.move_right();
+        assert_eq!(buffer.cursor(), 3);
+
+        buffer.move_left();
+        buffer.backspace();
+        assert_eq!(buffer.text(), "é😀");
+        assert_eq!(buffer.cursor(), 1);
+
+        buffer.insert_char('ñ');
+        assert_eq!(buffer.text(), "éñ😀");
+
+        buffer.move_home();
+        buffer.move_left();
+        assert_eq!(buffer.cursor(), 0);
+        buffer.delete();
+        assert_eq!(buffer.text(), "ñ😀");
+
+        buffer.move_end();
+        buffer.backspace();
+        assert_eq!(buffer.text(), "ñ");
+    }
+
+    #[test]
+    fn update_applies_frame_events() {
+        let mut input_state = InputState::new();
+        let now = Instant::now();
+        for c in "ab".chars() {
+            input_state.handle_event_at(&InputEvent::Character(c), now);
+        }
+        for key in [KeyboardKey::Back, KeyboardKey::Return] {
+            input_state.handle_event_at(
+                &InputEvent::Keyboard {
+                    key,
+                    state: crate::input::ButtonState::Pressed,
+                },
+                now,
+            );
+        }
+
+        let mut buffer = TextInputBuffer::new();
+        assert!(buffer.update(&input_state));
+        assert_eq!(buffer.text(), "a");
+    }
+
+    #[test]
+    fn display_text_includes_composition() {
+        let mut input_state = InputState::new();
+        input_state.handle_event_at(
+            &InputEvent::TextComposition(Some(TextComposition {
+                text: "に".to_string(),
+                cursor: 1,
+            })),
+            Instant::now(),
+        );
+
+        let mut buffer = TextInputBuffer::with_text("ab");
+        buffer.move_left();
+        assert_eq!(buffer.display_text(&input_state), "aにb");
+        assert_eq!(buffer.text(), "ab");
+    }
+}
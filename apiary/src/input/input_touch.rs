@@ -0,0 +1,392 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::{InputState, MouseDragState};
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+    Cancelled,
+}
+
+/// A finger in contact with the screen. Touch ids are unique while the touch is active but may be
+/// reused by the platform afterwards.
+#[derive(Copy, Clone, Debug)]
+pub struct TouchPoint {
+    pub id: u64,
+    pub begin_position: glam::Vec2,
+    pub position: glam::Vec2,
+    /// Ended or Cancelled for touches returned from `InputState::touches_just_ended`
+    pub phase: TouchPhase,
+    /// Set once the touch has moved far enough to be considered a drag rather than a tap
+    pub drag: Option<MouseDragState>,
+}
+
+/// A gesture recognised from the active touches during a frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Gesture {
+    /// A single touch that ended quickly without being dragged
+    Tap { position: glam::Vec2 },
+    /// A single touch held in place for at least `InputThresholds::long_press_duration`. Reported
+    /// once per touch.
+    LongPress { position: glam::Vec2 },
+    /// A single touch being dragged. `delta` is the movement during this frame.
+    Pan {
+        position: glam::Vec2,
+        delta: glam::Vec2,
+    },
+    /// Two touches moving apart or together. `scale` is relative to the previous frame, > 1.0
+    /// when zooming in.
+    PinchZoom { center: glam::Vec2, scale: f32 },
+    /// Two touches rotating around each other. `angle` is in radians relative to the previous
+    /// frame, positive is counter-clockwise in window coordinates.
+    Rotate { center: glam::Vec2, angle: f32 },
+}
+
+/// Tap and long press use the `InputThresholds` of the `InputState` gestures are recognised from,
+/// the same thresholds used for mouse clicks
+#[derive(Copy, Clone, Debug)]
+pub struct GestureConfig {
+    /// Minimum per-frame change in pinch distance ratio to report a zoom
+    pub min_pinch_scale_delta: f32,
+    /// Minimum per-frame rotation in radians to report a rotate
+    pub min_rotate_angle: f32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        GestureConfig {
+            min_pinch_scale_delta: 0.001,
+            min_rotate_angle: 0.001,
+        }
+    }
+}
+
+/// Turns the raw touch points tracked by `InputState` into higher level gestures. Call `update`
+/// once per frame after input has been processed.
+#[derive(Default)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    touch_durations: HashMap<u64, f32>,
+    previous_positions: HashMap<u64, glam::Vec2>,
+    long_pressed: HashSet<u64>,
+    multi_touch: HashSet<u64>,
+    previous_pair: Option<(u64, u64, glam::Vec2)>,
+    gestures: Vec<Gesture>,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        GestureRecognizer {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn config(&self) -> &GestureConfig {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut GestureConfig {
+        &mut self.config
+    }
+
+    /// Gestures recognised during the most recent update
+    pub fn gestures(&self) -> &[Gesture] {
+        &self.gestures
+    }
+
+    pub fn update(
+        &mut self,
+        input_state: &InputState,
+        dt: f32,
+    ) {
+        self.gestures.clear();
+
+        let long_press_duration = input_state.thresholds().long_press_duration.as_secs_f32();
+        let touches = input_state.touches();
+
+        // The platform may reuse the id of a touch that ended, so forget anything remembered about
+        // an earlier touch with the id of one that just started
+        let started_ids: Vec<u64> = touches
+            .iter()
+            .chain(input_state.touches_just_ended())
+            .map(|touch| touch.id)
+            .filter(|id| input_state.is_touch_just_started(*id))
+            .collect();
+        for id in started_ids {
+            self.forget_touch(id);
+        }
+
+        for touch in touches {
+            *self.touch_durations.entry(touch.id).or_insert(0.0) += dt;
+        }
+
+        if touches.len() == 1 {
+            self.update_single_touch(&touches[0], long_press_duration);
+        }
+
+        if touches.len() >= 2 {
+            self.update_touch_pair(&touches[0], &touches[1]);
+        } else {
+            self.previous_pair = None;
+        }
+
+        for touch in input_state.touches_just_ended() {
+            let duration = self.touch_durations.remove(&touch.id).unwrap_or(0.0);
+            let long_pressed = self.long_pressed.remove(&touch.id);
+            let multi_touch = self.multi_touch.remove(&touch.id);
+            self.previous_positions.remove(&touch.id);
+
+            if touch.phase == TouchPhase::Ended
+                && touch.drag.is_none()
+                && !long_pressed
+                && !multi_touch
+                && duration < long_press_duration
+            {
+                self.gestures.push(Gesture::Tap {
+                    position: touch.position,
+                });
+            }
+        }
+
+        for touch in touches {
+            self.previous_positions.insert(touch.id, touch.position);
+        }
+    }
+
+    fn forget_touch(
+        &mut self,
+        id: u64,
+    ) {
+        self.touch_durations.remove(&id);
+        self.previous_positions.remove(&id);
+        self.long_pressed.remove(&id);
+        self.multi_touch.remove(&id);
+
+        if let Some((a, b, _)) = self.previous_pair {
+            if a == id || b == id {
+                self.previous_pair = None;
+            }
+        }
+    }
+
+    fn update_single_touch(
+        &mut self,
+        touch: &TouchPoint,
+        long_press_duration: f32,
+    ) {
+        if self.multi_touch.contains(&touch.id) {
+            return;
+        }
+
+        if touch.drag.is_some() {
+            let previous_position = self
+                .previous_positions
+                .get(&touch.id)
+                .copied()
+                .unwrap_or(touch.begin_position);
+            let delta = touch.position - previous_position;
+            if delta != glam::Vec2::ZERO {
+                self.gestures.push(Gesture::Pan {
+                    position: touch.position,
+                    delta,
+                });
+            }
+        } else if !self.long_pressed.contains(&touch.id) {
+            let duration = self.touch_durations.get(&touch.id).copied().unwrap_or(0.0);
+            if duration >= long_press_duration {
+                self.long_pressed.insert(touch.id);
+                self.gestures.push(Gesture::LongPress {
+                    position: touch.position,
+                });
+            }
+        }
+    }
+
+    fn update_touch_pair(
+        &mut self,
+        a: &TouchPoint,
+        b: &TouchPoint,
+    ) {
+        self.multi_touch.insert(a.id);
+        self.multi_touch.insert(b.id);
+
+        let vector = b.position - a.position;
+        let center = (a.position + b.position) * 0.5;
+
+        if let Some((previous_a, previous_b, previous_vector)) = self.previous_pair {
+            if previous_a == a.id && previous_b == b.id {
+                let previous_length = previous_vector.length();
+                if previous_length > 0.0 {
+                    let scale = vector.length() / previous_length;
+                    if (scale - 1.0).abs() >= self.config.min_pinch_scale_delta {
+                        self.gestures.push(Gesture::PinchZoom { center, scale });
+                    }
+                }
+
+                let cross = previous_vector.x * vector.y - previous_vector.y * vector.x;
+                let angle = cross.atan2(previous_vector.dot(vector));
+                if angle.abs() >= self.config.min_rotate_angle {
+                    self.gestures.push(Gesture::Rotate { center, angle });
+                }
+            }
+        }
+
+        self.previous_pair = Some((a.id, b.id, vector));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputEvent;
+    use std::time::Instant;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn touch(
+        input_state: &mut InputState,
+        id: u64,
+        phase: TouchPhase,
+        x: f32,
+        y: f32,
+    ) {
+        input_state.handle_event_at(
+            &InputEvent::Touch {
+                id,
+                phase,
+                position: glam::Vec2::new(x, y),
+            },
+            Instant::now(),
+        );
+    }
+
+    fn end_frame(
+        recognizer: &mut GestureRecognizer,
+        input_state: &mut InputState,
+    ) -> Vec<Gesture> {
+        recognizer.update(input_state, DT);
+        input_state.end_frame();
+        recognizer.gestures().to_vec()
+    }
+
+    #[test]
+    fn quick_touch_is_a_tap() {
+        let mut input_state = InputState::new();
+        let mut recognizer = GestureRecognizer::default();
+
+        touch(&mut input_state, 1, TouchPhase::Started, 10.0, 10.0);
+        assert!(end_frame(&mut recognizer, &mut input_state).is_empty());
+
+        touch(&mut input_state, 1, TouchPhase::Ended, 10.0, 10.0);
+        assert_eq!(
+            end_frame(&mut recognizer, &mut input_state),
+            vec![Gesture::Tap {
+                position: glam::Vec2::new(10.0, 10.0)
+            }]
+        );
+    }
+
+    #[test]
+    fn held_touch_is_a_long_press_once() {
+        let mut input_state = InputState::new();
+        let mut recognizer = GestureRecognizer::default();
+        let long_press_frames =
+            (input_state.thresholds().long_press_duration.as_secs_f32() / DT).ceil() as usize;
+
+        touch(&mut input_state, 1, TouchPhase::Started, 10.0, 10.0);
+        let mut long_presses = 0;
+        for frame in 0..long_press_frames * 2 {
+            let gestures = end_frame(&mut recognizer, &mut input_state);
+            if gestures.contains(&Gesture::LongPress {
+                position: glam::Vec2::new(10.0, 10.0),
+            }) {
+                assert!(frame + 1 >= long_press_frames);
+                long_presses += 1;
+            }
+        }
+        assert_eq!(long_presses, 1);
+
+        // Releasing after a long press is not also a tap
+        touch(&mut input_state, 1, TouchPhase::Ended, 10.0, 10.0);
+        assert!(end_frame(&mut recognizer, &mut input_state).is_empty());
+    }
+
+    #[test]
+    fn swipe_pans_and_is_not_a_tap() {
+        let mut input_state = InputState::new();
+        let mut recognizer = GestureRecognizer::default();
+
+        touch(&mut input_state, 1, TouchPhase::Started, 0.0, 0.0);
+        end_frame(&mut recognizer, &mut input_state);
+
+        let mut total_delta = glam::Vec2::ZERO;
+        for i in 1..=5 {
+            touch(&mut input_state, 1, TouchPhase::Moved, 20.0 * i as f32, 0.0);
+            for gesture in end_frame(&mut recognizer, &mut input_state) {
+                match gesture {
+                    Gesture::Pan { delta, .. } => total_delta += delta,
+                    gesture => panic!("Unexpected gesture {:?}", gesture),
+                }
+            }
+        }
+        assert_eq!(total_delta, glam::Vec2::new(100.0, 0.0));
+
+        touch(&mut input_state, 1, TouchPhase::Ended, 100.0, 0.0);
+        assert!(end_frame(&mut recognizer, &mut input_state).is_empty());
+    }
+
+    #[test]
+    fn spreading_two_touches_zooms_in() {
+        let mut input_state = InputState::new();
+        let mut recognizer = GestureRecognizer::default();
+
+        touch(&mut input_state, 1, TouchPhase::Started, 90.0, 100.0);
+        touch(&mut input_state, 2, TouchPhase::Started, 110.0, 100.0);
+        end_frame(&mut recognizer, &mut input_state);
+
+        touch(&mut input_state, 1, TouchPhase::Moved, 80.0, 100.0);
+        touch(&mut input_state, 2, TouchPhase::Moved, 120.0, 100.0);
+        let gestures = end_frame(&mut recognizer, &mut input_state);
+        assert_eq!(
+            gestures,
+            vec![Gesture::PinchZoom {
+                center: glam::Vec2::new(100.0, 100.0),
+                scale: 2.0
+            }]
+        );
+
+        // Lifting the fingers after a pinch doesn't tap
+        touch(&mut input_state, 1, TouchPhase::Ended, 80.0, 100.0);
+        touch(&mut input_state, 2, TouchPhase::Ended, 120.0, 100.0);
+        assert!(end_frame(&mut recognizer, &mut input_state).is_empty());
+    }
+
+    #[test]
+    fn reused_touch_id_starts_fresh() {
+        let mut input_state = InputState::new();
+        let mut recognizer = GestureRecognizer::default();
+        let long_press_frames =
+            (input_state.thresholds().long_press_duration.as_secs_f32() / DT).ceil() as usize;
+
+        // The platform drops the end of a long press and reuses the id for a new touch
+        touch(&mut input_state, 1, TouchPhase::Started, 10.0, 10.0);
+        for _ in 0..long_press_frames {
+            end_frame(&mut recognizer, &mut input_state);
+        }
+
+        touch(&mut input_state, 1, TouchPhase::Started, 50.0, 50.0);
+        end_frame(&mut recognizer, &mut input_state);
+        touch(&mut input_state, 1, TouchPhase::Ended, 50.0, 50.0);
+        assert_eq!(
+            end_frame(&mut recognizer, &mut input_state),
+            vec![Gesture::Tap {
+                position: glam::Vec2::new(50.0, 50.0)
+            }]
+        );
+    }
+}
@@ -1,5 +1,14 @@
 use super::ButtonState;
+use super::CursorMode;
+use super::InputBackend;
+use super::InputEvent;
+use super::KeyModifiers;
 use super::KeyboardKey;
+use super::PhysicalKey;
+use super::TimestampedInputEvent;
+use super::TouchPhase;
+use crate::time::ClockResource;
+use std::time::Instant;
 
 use winit::event as we;
 use winit::event::{DeviceEvent, VirtualKeyCode};
@@ -174,6 +183,326 @@ impl From<we::VirtualKeyCode> for KeyboardKey {
     }
 }
 
+/// Convert a platform scancode as reported by winit into the key at that physical position.
+/// Linux and other unix platforms report evdev codes.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn scancode_to_physical_key(scancode: u32) -> Option<PhysicalKey> {
+    Some(match scancode {
+        1 => PhysicalKey::Escape,
+        2 => PhysicalKey::Digit1,
+        3 => PhysicalKey::Digit2,
+        4 => PhysicalKey::Digit3,
+        5 => PhysicalKey::Digit4,
+        6 => PhysicalKey::Digit5,
+        7 => PhysicalKey::Digit6,
+        8 => PhysicalKey::Digit7,
+        9 => PhysicalKey::Digit8,
+        10 => PhysicalKey::Digit9,
+        11 => PhysicalKey::Digit0,
+        12 => PhysicalKey::Minus,
+        13 => PhysicalKey::Equal,
+        14 => PhysicalKey::Backspace,
+        15 => PhysicalKey::Tab,
+        16 => PhysicalKey::KeyQ,
+        17 => PhysicalKey::KeyW,
+        18 => PhysicalKey::KeyE,
+        19 => PhysicalKey::KeyR,
+        20 => PhysicalKey::KeyT,
+        21 => PhysicalKey::KeyY,
+        22 => PhysicalKey::KeyU,
+        23 => PhysicalKey::KeyI,
+        24 => PhysicalKey::KeyO,
+        25 => PhysicalKey::KeyP,
+        26 => PhysicalKey::BracketLeft,
+        27 => PhysicalKey::BracketRight,
+        28 => PhysicalKey::Enter,
+        29 => PhysicalKey::ControlLeft,
+        30 => PhysicalKey::KeyA,
+        31 => PhysicalKey::KeyS,
+        32 => PhysicalKey::KeyD,
+        33 => PhysicalKey::KeyF,
+        34 => PhysicalKey::KeyG,
+        35 => PhysicalKey::KeyH,
+        36 => PhysicalKey::KeyJ,
+        37 => PhysicalKey::KeyK,
+        38 => PhysicalKey::KeyL,
+        39 => PhysicalKey::Semicolon,
+        40 => PhysicalKey::Quote,
+        41 => PhysicalKey::Backquote,
+        42 => PhysicalKey::ShiftLeft,
+        43 => PhysicalKey::Backslash,
+        44 => PhysicalKey::KeyZ,
+        45 => PhysicalKey::KeyX,
+        46 => PhysicalKey::KeyC,
+        47 => PhysicalKey::KeyV,
+        48 => PhysicalKey::KeyB,
+        49 => PhysicalKey::KeyN,
+        50 => PhysicalKey::KeyM,
+        51 => PhysicalKey::Comma,
+        52 => PhysicalKey::Period,
+        53 => PhysicalKey::Slash,
+        54 => PhysicalKey::ShiftRight,
+        55 => PhysicalKey::NumpadMultiply,
+        56 => PhysicalKey::AltLeft,
+        57 => PhysicalKey::Space,
+        58 => PhysicalKey::CapsLock,
+        59 => PhysicalKey::F1,
+        60 => PhysicalKey::F2,
+        61 => PhysicalKey::F3,
+        62 => PhysicalKey::F4,
+        63 => PhysicalKey::F5,
+        64 => PhysicalKey::F6,
+        65 => PhysicalKey::F7,
+        66 => PhysicalKey::F8,
+        67 => PhysicalKey::F9,
+        68 => PhysicalKey::F10,
+        71 => PhysicalKey::Numpad7,
+        72 => PhysicalKey::Numpad8,
+        73 => PhysicalKey::Numpad9,
+        74 => PhysicalKey::NumpadSubtract,
+        75 => PhysicalKey::Numpad4,
+        76 => PhysicalKey::Numpad5,
+        77 => PhysicalKey::Numpad6,
+        78 => PhysicalKey::NumpadAdd,
+        79 => PhysicalKey::Numpad1,
+        80 => PhysicalKey::Numpad2,
+        81 => PhysicalKey::Numpad3,
+        82 => PhysicalKey::Numpad0,
+        83 => PhysicalKey::NumpadDecimal,
+        87 => PhysicalKey::F11,
+        88 => PhysicalKey::F12,
+        96 => PhysicalKey::NumpadEnter,
+        97 => PhysicalKey::ControlRight,
+        98 => PhysicalKey::NumpadDivide,
+        100 => PhysicalKey::AltRight,
+        102 => PhysicalKey::Home,
+        103 => PhysicalKey::ArrowUp,
+        104 => PhysicalKey::PageUp,
+        105 => PhysicalKey::ArrowLeft,
+        106 => PhysicalKey::ArrowRight,
+        107 => PhysicalKey::End,
+        108 => PhysicalKey::ArrowDown,
+        109 => PhysicalKey::PageDown,
+        110 => PhysicalKey::Insert,
+        111 => PhysicalKey::Delete,
+        _ => return None,
+    })
+}
+
+/// Convert a platform scancode as reported by winit into the key at that physical position.
+/// Windows reports PC set 1 scancodes, with 0xE000 added for extended keys. The extended prefix is
+/// what tells right ctrl/alt and the navigation keys apart from left ctrl/alt and the numpad.
+#[cfg(target_os = "windows")]
+pub fn scancode_to_physical_key(scancode: u32) -> Option<PhysicalKey> {
+    Some(match scancode {
+        0x01 => PhysicalKey::Escape,
+        0x02 => PhysicalKey::Digit1,
+        0x03 => PhysicalKey::Digit2,
+        0x04 => PhysicalKey::Digit3,
+        0x05 => PhysicalKey::Digit4,
+        0x06 => PhysicalKey::Digit5,
+        0x07 => PhysicalKey::Digit6,
+        0x08 => PhysicalKey::Digit7,
+        0x09 => PhysicalKey::Digit8,
+        0x0A => PhysicalKey::Digit9,
+        0x0B => PhysicalKey::Digit0,
+        0x0C => PhysicalKey::Minus,
+        0x0D => PhysicalKey::Equal,
+        0x0E => PhysicalKey::Backspace,
+        0x0F => PhysicalKey::Tab,
+        0x10 => PhysicalKey::KeyQ,
+        0x11 => PhysicalKey::KeyW,
+        0x12 => PhysicalKey::KeyE,
+        0x13 => PhysicalKey::KeyR,
+        0x14 => PhysicalKey::KeyT,
+        0x15 => PhysicalKey::KeyY,
+        0x16 => PhysicalKey::KeyU,
+        0x17 => PhysicalKey::KeyI,
+        0x18 => PhysicalKey::KeyO,
+        0x19 => PhysicalKey::KeyP,
+        0x1A => PhysicalKey::BracketLeft,
+        0x1B => PhysicalKey::BracketRight,
+        0x1C => PhysicalKey::Enter,
+        0x1D => PhysicalKey::ControlLeft,
+        0x1E => PhysicalKey::KeyA,
+        0x1F => PhysicalKey::KeyS,
+        0x20 => PhysicalKey::KeyD,
+        0x21 => PhysicalKey::KeyF,
+        0x22 => PhysicalKey::KeyG,
+        0x23 => PhysicalKey::KeyH,
+        0x24 => PhysicalKey::KeyJ,
+        0x25 => PhysicalKey::KeyK,
+        0x26 => PhysicalKey::KeyL,
+        0x27 => PhysicalKey::Semicolon,
+        0x28 => PhysicalKey::Quote,
+        0x29 => PhysicalKey::Backquote,
+        0x2A => PhysicalKey::ShiftLeft,
+        0x2B => PhysicalKey::Backslash,
+        0x2C => PhysicalKey::KeyZ,
+        0x2D => PhysicalKey::KeyX,
+        0x2E => PhysicalKey::KeyC,
+        0x2F => PhysicalKey::KeyV,
+        0x30 => PhysicalKey::KeyB,
+        0x31 => PhysicalKey::KeyN,
+        0x32 => PhysicalKey::KeyM,
+        0x33 => PhysicalKey::Comma,
+        0x34 => PhysicalKey::Period,
+        0x35 => PhysicalKey::Slash,
+        0x36 => PhysicalKey::ShiftRight,
+        0x37 => PhysicalKey::NumpadMultiply,
+        0x38 => PhysicalKey::AltLeft,
+        0x39 => PhysicalKey::Space,
+        0x3A => PhysicalKey::CapsLock,
+        0x3B => PhysicalKey::F1,
+        0x3C => PhysicalKey::F2,
+        0x3D => PhysicalKey::F3,
+        0x3E => PhysicalKey::F4,
+        0x3F => PhysicalKey::F5,
+        0x40 => PhysicalKey::F6,
+        0x41 => PhysicalKey::F7,
+        0x42 => PhysicalKey::F8,
+        0x43 => PhysicalKey::F9,
+        0x44 => PhysicalKey::F10,
+        0x47 => PhysicalKey::Numpad7,
+        0x48 => PhysicalKey::Numpad8,
+        0x49 => PhysicalKey::Numpad9,
+        0x4A => PhysicalKey::NumpadSubtract,
+        0x4B => PhysicalKey::Numpad4,
+        0x4C => PhysicalKey::Numpad5,
+        0x4D => PhysicalKey::Numpad6,
+        0x4E => PhysicalKey::NumpadAdd,
+        0x4F => PhysicalKey::Numpad1,
+        0x50 => PhysicalKey::Numpad2,
+        0x51 => PhysicalKey::Numpad3,
+        0x52 => PhysicalKey::Numpad0,
+        0x53 => PhysicalKey::NumpadDecimal,
+        0x57 => PhysicalKey::F11,
+        0x58 => PhysicalKey::F12,
+        0xE01C => PhysicalKey::NumpadEnter,
+        0xE01D => PhysicalKey::ControlRight,
+        0xE035 => PhysicalKey::NumpadDivide,
+        0xE038 => PhysicalKey::AltRight,
+        0xE047 => PhysicalKey::Home,
+        0xE048 => PhysicalKey::ArrowUp,
+        0xE049 => PhysicalKey::PageUp,
+        0xE04B => PhysicalKey::ArrowLeft,
+        0xE04D => PhysicalKey::ArrowRight,
+        0xE04F => PhysicalKey::End,
+        0xE050 => PhysicalKey::ArrowDown,
+        0xE051 => PhysicalKey::PageDown,
+        0xE052 => PhysicalKey::Insert,
+        0xE053 => PhysicalKey::Delete,
+        _ => return None,
+    })
+}
+
+/// Convert a platform scancode as reported by winit into the key at that physical position.
+/// macOS reports Carbon virtual key codes, which identify physical keys.
+#[cfg(target_os = "macos")]
+pub fn scancode_to_physical_key(scancode: u32) -> Option<PhysicalKey> {
+    Some(match scancode {
+        0x00 => PhysicalKey::KeyA,
+        0x01 => PhysicalKey::KeyS,
+        0x02 => PhysicalKey::KeyD,
+        0x03 => PhysicalKey::KeyF,
+        0x04 => PhysicalKey::KeyH,
+        0x05 => PhysicalKey::KeyG,
+        0x06 => PhysicalKey::KeyZ,
+        0x07 => PhysicalKey::KeyX,
+        0x08 => PhysicalKey::KeyC,
+        0x09 => PhysicalKey::KeyV,
+        0x0B => PhysicalKey::KeyB,
+        0x0C => PhysicalKey::KeyQ,
+        0x0D => PhysicalKey::KeyW,
+        0x0E => PhysicalKey::KeyE,
+        0x0F => PhysicalKey::KeyR,
+        0x10 => PhysicalKey::KeyY,
+        0x11 => PhysicalKey::KeyT,
+        0x12 => PhysicalKey::Digit1,
+        0x13 => PhysicalKey::Digit2,
+        0x14 => PhysicalKey::Digit3,
+        0x15 => PhysicalKey::Digit4,
+        0x16 => PhysicalKey::Digit6,
+        0x17 => PhysicalKey::Digit5,
+        0x18 => PhysicalKey::Equal,
+        0x19 => PhysicalKey::Digit9,
+        0x1A => PhysicalKey::Digit7,
+        0x1B => PhysicalKey::Minus,
+        0x1C => PhysicalKey::Digit8,
+        0x1D => PhysicalKey::Digit0,
+        0x1E => PhysicalKey::BracketRight,
+        0x1F => PhysicalKey::KeyO,
+        0x20 => PhysicalKey::KeyU,
+        0x21 => PhysicalKey::BracketLeft,
+        0x22 => PhysicalKey::KeyI,
+        0x23 => PhysicalKey::KeyP,
+        0x24 => PhysicalKey::Enter,
+        0x25 => PhysicalKey::KeyL,
+        0x26 => PhysicalKey::KeyJ,
+        0x27 => PhysicalKey::Quote,
+        0x28 => PhysicalKey::KeyK,
+        0x29 => PhysicalKey::Semicolon,
+        0x2A => PhysicalKey::Backslash,
+        0x2B => PhysicalKey::Comma,
+        0x2C => PhysicalKey::Slash,
+        0x2D => PhysicalKey::KeyN,
+        0x2E => PhysicalKey::KeyM,
+        0x2F => PhysicalKey::Period,
+        0x30 => PhysicalKey::Tab,
+        0x31 => PhysicalKey::Space,
+        0x32 => PhysicalKey::Backquote,
+        0x33 => PhysicalKey::Backspace,
+        0x35 => PhysicalKey::Escape,
+        0x38 => PhysicalKey::ShiftLeft,
+        0x39 => PhysicalKey::CapsLock,
+        0x3A => PhysicalKey::AltLeft,
+        0x3B => PhysicalKey::ControlLeft,
+        0x3C => PhysicalKey::ShiftRight,
+        0x3D => PhysicalKey::AltRight,
+        0x3E => PhysicalKey::ControlRight,
+        0x41 => PhysicalKey::NumpadDecimal,
+        0x43 => PhysicalKey::NumpadMultiply,
+        0x45 => PhysicalKey::NumpadAdd,
+        0x4B => PhysicalKey::NumpadDivide,
+        0x4C => PhysicalKey::NumpadEnter,
+        0x4E => PhysicalKey::NumpadSubtract,
+        0x52 => PhysicalKey::Numpad0,
+        0x53 => PhysicalKey::Numpad1,
+        0x54 => PhysicalKey::Numpad2,
+        0x55 => PhysicalKey::Numpad3,
+        0x56 => PhysicalKey::Numpad4,
+        0x57 => PhysicalKey::Numpad5,
+        0x58 => PhysicalKey::Numpad6,
+        0x59 => PhysicalKey::Numpad7,
+        0x5B => PhysicalKey::Numpad8,
+        0x5C => PhysicalKey::Numpad9,
+        0x60 => PhysicalKey::F5,
+        0x61 => PhysicalKey::F6,
+        0x62 => PhysicalKey::F7,
+        0x63 => PhysicalKey::F3,
+        0x64 => PhysicalKey::F8,
+        0x65 => PhysicalKey::F9,
+        0x67 => PhysicalKey::F11,
+        0x6D => PhysicalKey::F10,
+        0x6F => PhysicalKey::F12,
+        // The Help key sits where Insert is on PC keyboards
+        0x72 => PhysicalKey::Insert,
+        0x73 => PhysicalKey::Home,
+        0x74 => PhysicalKey::PageUp,
+        0x75 => PhysicalKey::Delete,
+        0x76 => PhysicalKey::F4,
+        0x77 => PhysicalKey::End,
+        0x78 => PhysicalKey::F2,
+        0x79 => PhysicalKey::PageDown,
+        0x7A => PhysicalKey::F1,
+        0x7B => PhysicalKey::ArrowLeft,
+        0x7C => PhysicalKey::ArrowRight,
+        0x7D => PhysicalKey::ArrowDown,
+        0x7E => PhysicalKey::ArrowUp,
+        _ => return None,
+    })
+}
+
 #[derive(Copy, Clone)]
 pub struct WinitElementState {
     element_state: we::ElementState,
@@ -243,13 +572,55 @@ impl Into<super::MouseScrollDelta> for WinitMouseScrollDelta {
     }
 }
 
-/// Call when winit sends an event
-pub fn handle_winit_event<T>(event: &winit::event::Event<T>, input_state: &mut super::InputState) {
+#[derive(Copy, Clone)]
+pub struct WinitTouchPhase {
+    touch_phase: we::TouchPhase,
+}
+
+impl WinitTouchPhase {
+    pub fn new(touch_phase: we::TouchPhase) -> Self {
+        WinitTouchPhase { touch_phase }
+    }
+}
+
+impl Into<TouchPhase> for WinitTouchPhase {
+    fn into(self) -> TouchPhase {
+        match self.touch_phase {
+            we::TouchPhase::Started => TouchPhase::Started,
+            we::TouchPhase::Moved => TouchPhase::Moved,
+            we::TouchPhase::Ended => TouchPhase::Ended,
+            we::TouchPhase::Cancelled => TouchPhase::Cancelled,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct WinitModifiersState {
+    modifiers_state: we::ModifiersState,
+}
+
+impl WinitModifiersState {
+    pub fn new(modifiers_state: we::ModifiersState) -> Self {
+        WinitModifiersState { modifiers_state }
+    }
+}
+
+impl Into<KeyModifiers> for WinitModifiersState {
+    fn into(self) -> KeyModifiers {
+        KeyModifiers {
+            shift: self.modifiers_state.shift(),
+            ctrl: self.modifiers_state.ctrl(),
+            alt: self.modifiers_state.alt(),
+            logo: self.modifiers_state.logo(),
+        }
+    }
+}
+
+/// Convert a winit event into an `InputEvent`, returns None for events that don't affect input
+pub fn winit_event_to_input_event<T>(event: &winit::event::Event<T>) -> Option<InputEvent> {
     use winit::event::Event;
     use winit::event::WindowEvent;
 
-    let _is_close_requested = false;
-
     match event {
         //Process keyboard input
         Event::WindowEvent {
@@ -257,10 +628,10 @@ pub fn handle_winit_event<T>(event: &winit::event::Event<T>, input_state: &mut s
             ..
         } => {
             log::trace!("keyboard input {:?}", input);
-            if let Some(vk) = input.virtual_keycode {
-                input_state
-                    .handle_keyboard_event(vk.into(), WinitElementState::new(input.state).into());
-            }
+            input.virtual_keycode.map(|vk| InputEvent::Keyboard {
+                key: vk.into(),
+                state: WinitElementState::new(input.state).into(),
+            })
         }
 
         Event::WindowEvent {
@@ -280,10 +651,10 @@ pub fn handle_winit_event<T>(event: &winit::event::Event<T>, input_state: &mut s
                 button,
             );
 
-            input_state.handle_mouse_button_event(
-                WinitMouseButton::new(*button).into(),
-                WinitElementState::new(*state).into(),
-            );
+            Some(InputEvent::MouseButton {
+                button: WinitMouseButton::new(*button).into(),
+                state: WinitElementState::new(*state).into(),
+            })
         }
 
         Event::WindowEvent {
@@ -296,10 +667,10 @@ pub fn handle_winit_event<T>(event: &winit::event::Event<T>, input_state: &mut s
             ..
         } => {
             log::trace!("mouse move input {:?} {:?}", device_id, position);
-            input_state.handle_mouse_update_position(glam::Vec2::new(
+            Some(InputEvent::MousePosition(glam::Vec2::new(
                 position.x as f32,
                 position.y as f32,
-            ));
+            )))
         }
 
         Event::DeviceEvent {
@@ -307,7 +678,10 @@ pub fn handle_winit_event<T>(event: &winit::event::Event<T>, input_state: &mut s
             device_id,
         } => {
             log::trace!("mouse motion input {:?} {:?}", device_id, delta);
-            input_state.handle_mouse_motion_event(glam::Vec2::new(delta.0 as f32, delta.1 as f32));
+            Some(InputEvent::MouseMotion(glam::Vec2::new(
+                delta.0 as f32,
+                delta.1 as f32,
+            )))
         }
 
         Event::WindowEvent {
@@ -317,10 +691,196 @@ pub fn handle_winit_event<T>(event: &winit::event::Event<T>, input_state: &mut s
             ..
         } => {
             log::trace!("mouse wheel {:?} {:?}", device_id, delta);
-            input_state.handle_mouse_wheel_event(WinitMouseScrollDelta::new(*delta).into());
+            Some(InputEvent::MouseWheel(
+                WinitMouseScrollDelta::new(*delta).into(),
+            ))
+        }
+
+        Event::WindowEvent {
+            event: WindowEvent::ModifiersChanged(modifiers),
+            ..
+        } => {
+            log::trace!("modifiers changed {:?}", modifiers);
+            Some(InputEvent::ModifiersChanged(
+                WinitModifiersState::new(*modifiers).into(),
+            ))
+        }
+
+        // winit 0.24 does not report IME composition, only committed characters arrive here
+        Event::WindowEvent {
+            event: WindowEvent::ReceivedCharacter(c),
+            ..
+        } => {
+            log::trace!("received character {:?}", c);
+            Some(InputEvent::Character(*c))
+        }
+
+        Event::WindowEvent {
+            event: WindowEvent::ScaleFactorChanged { scale_factor, .. },
+            ..
+        } => {
+            log::trace!("scale factor changed {:?}", scale_factor);
+            Some(InputEvent::ScaleFactorChanged(*scale_factor as f32))
+        }
+
+        Event::WindowEvent {
+            event: WindowEvent::Touch(touch),
+            ..
+        } => {
+            log::trace!("touch {:?}", touch);
+            Some(InputEvent::Touch {
+                id: touch.id,
+                phase: WinitTouchPhase::new(touch.phase).into(),
+                position: glam::Vec2::new(touch.location.x as f32, touch.location.y as f32),
+            })
         }
 
         // Ignore any other events
-        _ => (),
+        _ => None,
+    }
+}
+
+/// Convert a winit keyboard event into a `PhysicalKey` event if the scancode is recognised
+pub fn winit_event_to_physical_key_event<T>(event: &winit::event::Event<T>) -> Option<InputEvent> {
+    use winit::event::Event;
+    use winit::event::WindowEvent;
+
+    match event {
+        Event::WindowEvent {
+            event: WindowEvent::KeyboardInput { input, .. },
+            ..
+        } => scancode_to_physical_key(input.scancode).map(|key| InputEvent::PhysicalKey {
+            key,
+            state: WinitElementState::new(input.state).into(),
+        }),
+        _ => None,
+    }
+}
+
+/// Convert a winit event into all of the `InputEvent`s it produces. Keyboard input produces both
+/// a virtual key and a physical key event.
+pub fn winit_event_to_input_events<T>(
+    event: &winit::event::Event<T>
+) -> impl Iterator<Item = InputEvent> {
+    winit_event_to_input_event(event)
+        .into_iter()
+        .chain(winit_event_to_physical_key_event(event))
+}
+
+/// Buffers input from winit events until the next poll. Pass every event from the event loop to
+/// `handle_winit_event`. Events are timestamped from the `ClockResource` as they arrive.
+pub struct WinitInputBackend {
+    clock: ClockResource,
+    pending_events: Vec<TimestampedInputEvent>,
+    modifiers: KeyModifiers,
+}
+
+impl WinitInputBackend {
+    pub fn new(clock: ClockResource) -> Self {
+        WinitInputBackend {
+            clock,
+            pending_events: Vec::default(),
+            modifiers: KeyModifiers::default(),
+        }
+    }
+
+    /// Modifiers held as of the most recent event, which may be ahead of `InputState::modifiers`
+    /// until the backend is next polled
+    pub fn modifiers(&self) -> KeyModifiers {
+        self.modifiers
+    }
+
+    /// Call when winit sends an event
+    pub fn handle_winit_event<T>(
+        &mut self,
+        event: &winit::event::Event<T>,
+    ) {
+        let timestamp = self.clock.now();
+        for input_event in winit_event_to_input_events(event) {
+            if let InputEvent::ModifiersChanged(modifiers) = input_event {
+                self.modifiers = modifiers;
+            }
+
+            self.pending_events.push(TimestampedInputEvent {
+                timestamp,
+                event: input_event,
+            });
+        }
+    }
+}
+
+impl InputBackend for WinitInputBackend {
+    fn poll_events(
+        &mut self,
+        events: &mut Vec<TimestampedInputEvent>,
+    ) {
+        events.append(&mut self.pending_events);
+    }
+}
+
+/// Call when winit sends an event. `timestamp` should come from the `ClockResource`.
+pub fn handle_winit_event<T>(
+    event: &winit::event::Event<T>,
+    input_state: &mut super::InputState,
+    timestamp: Instant,
+) {
+    for input_event in winit_event_to_input_events(event) {
+        input_state.handle_event_at(&input_event, timestamp);
+    }
+}
+
+/// Apply a cursor mode to the window. winit doesn't distinguish between confining and locking the
+/// cursor, so `Locked` is a grabbed, hidden cursor.
+pub fn apply_cursor_mode(
+    window: &winit::window::Window,
+    mode: CursorMode,
+) -> Result<(), winit::error::ExternalError> {
+    let (grab, visible) = match mode {
+        CursorMode::Free => (false, true),
+        CursorMode::Confined => (true, true),
+        CursorMode::Locked => (true, false),
+    };
+
+    window.set_cursor_visible(visible);
+    window.set_cursor_grab(grab)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every key is reachable from exactly one scancode on the platform being built for
+    #[test]
+    fn navigation_and_numpad_keys_have_scancodes() {
+        let keys = [
+            PhysicalKey::ControlRight,
+            PhysicalKey::AltRight,
+            PhysicalKey::ArrowUp,
+            PhysicalKey::ArrowDown,
+            PhysicalKey::ArrowLeft,
+            PhysicalKey::ArrowRight,
+            PhysicalKey::Insert,
+            PhysicalKey::Delete,
+            PhysicalKey::Home,
+            PhysicalKey::End,
+            PhysicalKey::PageUp,
+            PhysicalKey::PageDown,
+            PhysicalKey::Numpad0,
+            PhysicalKey::Numpad5,
+            PhysicalKey::Numpad9,
+            PhysicalKey::NumpadAdd,
+            PhysicalKey::NumpadSubtract,
+            PhysicalKey::NumpadMultiply,
+            PhysicalKey::NumpadDivide,
+            PhysicalKey::NumpadDecimal,
+            PhysicalKey::NumpadEnter,
+        ];
+
+        for key in keys.iter() {
+            let scancode_count = (0..=0xFFFF)
+                .filter(|scancode| scancode_to_physical_key(*scancode) == Some(*key))
+                .count();
+            assert_eq!(scancode_count, 1, "{:?}", key);
+        }
     }
 }
@@ -1,8 +1,41 @@
 mod input_state;
 pub use input_state::*;
 
+mod input_backend;
+pub use input_backend::*;
+
 mod input_resource;
 pub use input_resource::*;
 
 mod input_winit;
 pub use input_winit::*;
+
+mod input_bindings;
+pub use input_bindings::*;
+
+mod input_axes;
+pub use input_axes::*;
+
+mod input_picking;
+pub use input_picking::*;
+
+mod input_recording;
+pub use input_recording::*;
+
+mod input_shortcuts;
+pub use input_shortcuts::*;
+
+mod input_text;
+pub use input_text::*;
+
+mod input_gamepad;
+pub use input_gamepad::*;
+
+mod input_cursor;
+pub use input_cursor::*;
+
+mod input_context;
+pub use input_context::*;
+
+mod input_touch;
+pub use input_touch::*;
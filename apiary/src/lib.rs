@@ -9,8 +9,10 @@ mod tests {
 
 pub mod app;
 pub mod error;
+pub mod frame_pacing;
 mod input;
 pub mod reader_threads;
 pub mod rendering;
-mod scenes;
+pub mod scenes;
 pub mod time;
+pub mod timers;
@@ -1,21 +1,116 @@
+mod scene_file;
+pub use scene_file::*;
+
+mod scene_file_watcher;
+pub use scene_file_watcher::*;
+
+mod scene_loading;
+pub use scene_loading::*;
+
+mod scene_registry;
+pub use scene_registry::*;
+
+mod pbr_test_scene;
 mod ui_scene;
 mod util;
 
+use crate::timers::TimerEvent;
 use legion::{IntoQuery, Read, Resources, World};
+use pbr_test_scene::PbrTestScene;
+use rafx::renderer::Renderer;
 use rafx_plugins::components::{
     DirectionalLightComponent, PointLightComponent, SpotLightComponent, TransformComponent,
 };
 use rafx_plugins::features::debug3d::Debug3DResource;
+use std::path::PathBuf;
 use ui_scene::UiScene;
 
+/// Builds a scene once a transition to it is applied, so it is created against the world it will
+/// run in.
+pub type SceneFactory = Box<dyn FnOnce(&mut World, &Resources) -> Box<dyn Scene> + Send + Sync>;
+
+enum SceneTransition {
+    Switch(SceneFactory),
+    Push(SceneFactory),
+    Pop,
+}
+
+/// Scene transitions requested during the frame. Transitions are queued rather than applied
+/// immediately because the requesting scene is usually still running; `SceneManager` applies them
+/// in order at the start of the next frame.
+#[derive(Default)]
+pub struct SceneTransitionResource {
+    pending: Vec<SceneTransition>,
+}
+
+impl SceneTransitionResource {
+    /// Clean up every scene on the stack, clear the world and replace them with a new scene
+    pub fn request_switch(
+        &mut self,
+        factory: SceneFactory,
+    ) {
+        self.pending.push(SceneTransition::Switch(factory));
+    }
+
+    /// Put a scene on top of the stack, such as a pause menu. It shares the world with the scenes
+    /// below it, which are suspended until it is popped.
+    pub fn request_push(
+        &mut self,
+        factory: SceneFactory,
+    ) {
+        self.pending.push(SceneTransition::Push(factory));
+    }
+
+    /// Clean up the top scene and resume the one below it. The popped scene is responsible for
+    /// removing any entities it added to the world.
+    pub fn request_pop(&mut self) {
+        self.pending.push(SceneTransition::Pop);
+    }
+
+    pub fn has_pending_transitions(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+/// A stack of scenes. Only the top scene receives input and updates.
+#[derive(Default)]
 pub struct SceneManager {
-    current_scene: Option<Box<dyn Scene>>,
-    next_scene: Option<Box<dyn Scene>>,
+    scenes: Vec<Box<dyn Scene>>,
 }
 
 pub trait Scene {
+    /// Called zero or more times per frame before `update`, once per fixed tick. Use
+    /// `TimeState::fixed_dt` rather than the frame time here.
+    fn fixed_update(
+        &mut self,
+        _world: &mut World,
+        _resources: &mut Resources,
+    ) {
+    }
     fn update(&mut self, world: &mut World, resources: &mut Resources);
     fn cleanup(&mut self, _world: &mut World, _resources: &Resources) {}
+    /// Called when another scene is pushed on top of this one
+    fn suspend(
+        &mut self,
+        _world: &mut World,
+        _resources: &Resources,
+    ) {
+    }
+    /// Called when the scene on top of this one is popped
+    fn resume(
+        &mut self,
+        _world: &mut World,
+        _resources: &Resources,
+    ) {
+    }
+    /// Called once per frame before `update` with every timer in `TimerResource` that fired
+    fn timers_fired(
+        &mut self,
+        _world: &mut World,
+        _resources: &mut Resources,
+        _events: &[TimerEvent],
+    ) {
+    }
     fn process_input(
         &mut self,
         _world: &mut World,
@@ -23,21 +118,55 @@ pub trait Scene {
         _event: &winit::event::Event<()>,
     ) {
     }
+    /// Called with files watched through `SceneFileWatcher` that changed on disk. Every scene on
+    /// the stack is told, so check the paths are ones this scene loaded.
+    fn scene_files_changed(
+        &mut self,
+        _world: &mut World,
+        _resources: &Resources,
+        _paths: &[PathBuf],
+    ) {
+    }
 }
 
 impl SceneManager {
+    /// Replace the stack with the given scene immediately, without cleaning up. Only meant for
+    /// installing the first scene; use `SceneTransitionResource` once the app is running.
     pub fn set_scene(&mut self, scene: Box<dyn Scene>) {
-        self.current_scene = Some(scene);
+        self.scenes.clear();
+        self.scenes.push(scene);
+    }
+
+    /// Number of scenes on the stack, including suspended ones
+    pub fn scene_count(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn timers_fired(
+        &mut self,
+        world: &mut World,
+        resources: &mut Resources,
+        events: &[TimerEvent],
+    ) {
+        if let Some(current_scene) = self.scenes.last_mut() {
+            current_scene.timers_fired(world, resources, events);
+        }
+    }
+
+    pub fn fixed_update_scene(
+        &mut self,
+        world: &mut World,
+        resources: &mut Resources,
+    ) {
+        if let Some(current_scene) = self.scenes.last_mut() {
+            current_scene.fixed_update(world, resources);
+        }
     }
 
     pub fn update_scene(&mut self, world: &mut World, resources: &mut Resources) {
-        if self.current_scene.is_none() {
-            return;
+        if let Some(current_scene) = self.scenes.last_mut() {
+            current_scene.update(world, resources);
         }
-        self.current_scene
-            .as_mut()
-            .unwrap()
-            .update(world, resources);
     }
 
     pub fn process_input(
@@ -46,37 +175,89 @@ impl SceneManager {
         resources: &Resources,
         event: &winit::event::Event<()>,
     ) {
-        if let Some(current_scene) = &mut self.current_scene {
+        if let Some(current_scene) = self.scenes.last_mut() {
             current_scene.process_input(world, resources, event);
         }
     }
 
-    pub fn has_next_scene(&self) -> bool {
-        self.next_scene.is_some()
+    pub fn scene_files_changed(
+        &mut self,
+        world: &mut World,
+        resources: &Resources,
+        paths: &[PathBuf],
+    ) {
+        for scene in &mut self.scenes {
+            scene.scene_files_changed(world, resources, paths);
+        }
     }
 
-    pub fn try_cleanup_current_scene(&mut self, world: &mut World, resources: &Resources) {
-        if let Some(current_scene) = &mut self.current_scene {
-            current_scene.cleanup(world, resources);
-        }
+    /// Apply every transition queued in `SceneTransitionResource`, in the order they were
+    /// requested. Call at a point in the frame where no scene is running and the world isn't
+    /// borrowed, before anything reads the world for the frame.
+    pub fn apply_pending_transitions(
+        &mut self,
+        world: &mut World,
+        resources: &Resources,
+    ) {
+        let pending = std::mem::take(
+            &mut resources
+                .get_mut::<SceneTransitionResource>()
+                .unwrap()
+                .pending,
+        );
 
-        world.clear();
-        self.current_scene = None;
+        for transition in pending {
+            match transition {
+                SceneTransition::Switch(factory) => {
+                    self.cleanup_all_scenes(world, resources);
+                    Self::clear_world(world, resources);
+                    let scene = factory(world, resources);
+                    self.scenes.push(scene);
+                }
+                SceneTransition::Push(factory) => {
+                    if let Some(current_scene) = self.scenes.last_mut() {
+                        current_scene.suspend(world, resources);
+                    }
+                    let scene = factory(world, resources);
+                    self.scenes.push(scene);
+                }
+                SceneTransition::Pop => match self.scenes.pop() {
+                    Some(mut scene) => {
+                        scene.cleanup(world, resources);
+                        if let Some(current_scene) = self.scenes.last_mut() {
+                            current_scene.resume(world, resources);
+                        }
+                    }
+                    None => log::warn!("Requested a scene pop but the scene stack is empty"),
+                },
+            }
+        }
     }
-    /*
-    pub fn go_next_scene(&mut self) {
-        let mut n = self.next_scene.unwrap();
-        self.current_scene = Some(n);
-        self.next_scene = None;
+
+    /// Clean up every scene on the stack, top first
+    pub fn cleanup_all_scenes(
+        &mut self,
+        world: &mut World,
+        resources: &Resources,
+    ) {
+        while let Some(mut scene) = self.scenes.pop() {
+            scene.cleanup(world, resources);
+        }
     }
-    */
-}
 
-impl Default for SceneManager {
-    fn default() -> Self {
-        SceneManager {
-            current_scene: None,
-            next_scene: None,
+    fn clear_world(
+        world: &mut World,
+        resources: &Resources,
+    ) {
+        // NOTE(dvd): Legion leaks memory because the entity IDs aren't reset when the
+        // world is cleared and the entity location map will grow without bounds.
+        *world = World::default();
+
+        // NOTE(dvd): The Renderer maintains some per-frame temporary data to avoid
+        // allocating each frame. We can clear this between scene transitions.
+        // There's no renderer when scenes are driven without a window, such as in tests.
+        if let Some(mut renderer) = resources.get_mut::<Renderer>() {
+            renderer.clear_temporary_work();
         }
     }
 }
@@ -114,6 +295,196 @@ fn add_light_debug_draw(resources: &Resources, world: &World) {
     }
 }
 
-pub fn create_scene(world: &mut World, resources: &Resources) -> Box<dyn Scene> {
-    Box::new(UiScene::new(world, resources))
+/// Every built-in scene, in the order Left/Right cycles through them. The first is the default.
+pub fn default_scene_registry() -> SceneRegistry {
+    let mut registry = SceneRegistry::default();
+    registry.register("ui", |world, resources| {
+        Box::new(UiScene::new(world, resources))
+    });
+    registry.register("pbr_test", |world, resources| {
+        Box::new(PbrTestScene::new(world, resources))
+    });
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    type SceneLog = Arc<Mutex<Vec<String>>>;
+
+    // Records each call it receives as "<name> <call>"
+    struct RecordingScene {
+        name: &'static str,
+        log: SceneLog,
+    }
+
+    impl RecordingScene {
+        fn record(
+            &self,
+            call: &str,
+        ) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", self.name, call));
+        }
+    }
+
+    impl Scene for RecordingScene {
+        fn update(
+            &mut self,
+            _world: &mut World,
+            _resources: &mut Resources,
+        ) {
+            self.record("update");
+        }
+
+        fn cleanup(
+            &mut self,
+            _world: &mut World,
+            _resources: &Resources,
+        ) {
+            self.record("cleanup");
+        }
+
+        fn suspend(
+            &mut self,
+            _world: &mut World,
+            _resources: &Resources,
+        ) {
+            self.record("suspend");
+        }
+
+        fn resume(
+            &mut self,
+            _world: &mut World,
+            _resources: &Resources,
+        ) {
+            self.record("resume");
+        }
+    }
+
+    fn recording_scene(
+        name: &'static str,
+        log: &SceneLog,
+    ) -> SceneFactory {
+        let log = log.clone();
+        Box::new(move |_world, _resources| {
+            log.lock().unwrap().push(format!("{} create", name));
+            Box::new(RecordingScene { name, log })
+        })
+    }
+
+    fn take_log(log: &SceneLog) -> Vec<String> {
+        std::mem::take(&mut *log.lock().unwrap())
+    }
+
+    fn resources() -> Resources {
+        let mut resources = Resources::default();
+        resources.insert(SceneTransitionResource::default());
+        resources
+    }
+
+    #[test]
+    fn transitions_wait_until_applied() {
+        let log = SceneLog::default();
+        let resources = resources();
+        let mut world = World::default();
+        let mut scene_manager = SceneManager::default();
+
+        resources
+            .get_mut::<SceneTransitionResource>()
+            .unwrap()
+            .request_push(recording_scene("a", &log));
+        assert!(take_log(&log).is_empty());
+        assert_eq!(scene_manager.scene_count(), 0);
+
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        assert_eq!(take_log(&log), vec!["a create"]);
+        assert_eq!(scene_manager.scene_count(), 1);
+        assert!(!resources
+            .get::<SceneTransitionResource>()
+            .unwrap()
+            .has_pending_transitions());
+    }
+
+    #[test]
+    fn push_suspends_and_pop_resumes() {
+        let log = SceneLog::default();
+        let mut resources = resources();
+        let mut world = World::default();
+        let mut scene_manager = SceneManager::default();
+
+        resources
+            .get_mut::<SceneTransitionResource>()
+            .unwrap()
+            .request_push(recording_scene("a", &log));
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        take_log(&log);
+
+        resources
+            .get_mut::<SceneTransitionResource>()
+            .unwrap()
+            .request_push(recording_scene("b", &log));
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        scene_manager.update_scene(&mut world, &mut resources);
+        assert_eq!(take_log(&log), vec!["a suspend", "b create", "b update"]);
+        assert_eq!(scene_manager.scene_count(), 2);
+
+        resources
+            .get_mut::<SceneTransitionResource>()
+            .unwrap()
+            .request_pop();
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        scene_manager.update_scene(&mut world, &mut resources);
+        assert_eq!(take_log(&log), vec!["b cleanup", "a resume", "a update"]);
+        assert_eq!(scene_manager.scene_count(), 1);
+    }
+
+    #[test]
+    fn switch_cleans_up_the_whole_stack() {
+        let log = SceneLog::default();
+        let resources = resources();
+        let mut world = World::default();
+        let mut scene_manager = SceneManager::default();
+
+        {
+            let mut transitions = resources.get_mut::<SceneTransitionResource>().unwrap();
+            transitions.request_push(recording_scene("a", &log));
+            transitions.request_push(recording_scene("b", &log));
+        }
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        world.push((1u32,));
+        take_log(&log);
+
+        resources
+            .get_mut::<SceneTransitionResource>()
+            .unwrap()
+            .request_switch(recording_scene("c", &log));
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        assert_eq!(take_log(&log), vec!["b cleanup", "a cleanup", "c create"]);
+        assert_eq!(scene_manager.scene_count(), 1);
+        assert!(world.is_empty());
+    }
+
+    #[test]
+    fn popping_an_empty_stack_is_safe() {
+        let log = SceneLog::default();
+        let resources = resources();
+        let mut world = World::default();
+        let mut scene_manager = SceneManager::default();
+
+        {
+            let mut transitions = resources.get_mut::<SceneTransitionResource>().unwrap();
+            transitions.request_pop();
+            transitions.request_push(recording_scene("a", &log));
+            transitions.request_pop();
+            transitions.request_pop();
+        }
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        assert_eq!(take_log(&log), vec!["a create", "a cleanup"]);
+        assert_eq!(scene_manager.scene_count(), 0);
+    }
 }
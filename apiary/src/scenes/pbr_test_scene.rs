@@ -0,0 +1,186 @@
+use crate::app::RenderOptions;
+use crate::input::{CursorMode, CursorResource, InputResource};
+use crate::scenes::util::FlyCamera;
+use crate::scenes::{
+    LoadedScene, PendingSceneLoad, Scene, SceneLoadProgress, SceneLoadingResource,
+};
+use crate::time::TimeState;
+use distill::loader::handle::{AssetHandle, Handle};
+use distill::loader::LoadStatus;
+use legion::{Resources, World};
+use rafx::assets::distill_impl::AssetResource;
+use rafx::assets::ImageAsset;
+use rafx::framework::visibility::{ViewFrustumArc, VisibilityRegion};
+use rafx::renderer::ViewportsResource;
+use rafx_plugins::assets::mesh_basic::PrefabBasicAsset;
+use rafx_plugins::features::mesh_basic::MeshBasicRenderObjectSet;
+
+const PREFAB_PATH: &str = "pbr-test/Scene.001.blender_prefab";
+const SKYBOX_PATH: &str = "textures/skybox.basis";
+
+/// A prefab exported from blender, explored with a `FlyCamera` driven by the bindings in
+/// config/input_bindings.ron
+pub struct PbrTestScene {
+    main_view_frustum: ViewFrustumArc,
+    fly_camera: FlyCamera,
+    // Requested when the scene starts. Its objects are queued into pending_load once it and the
+    // models it references have loaded.
+    prefab: Option<Handle<PrefabBasicAsset>>,
+    pending_load: Option<PendingSceneLoad>,
+    prefab_entities: LoadedScene,
+}
+
+impl PbrTestScene {
+    pub(super) fn new(
+        _world: &mut World,
+        resources: &Resources,
+    ) -> Self {
+        {
+            let mut render_options = resources.get_mut::<RenderOptions>().unwrap();
+            *render_options = RenderOptions::default_3d();
+        }
+
+        let prefab = resources
+            .get::<AssetResource>()
+            .unwrap()
+            .load_asset_path(PREFAB_PATH);
+
+        let main_view_frustum = resources
+            .get::<VisibilityRegion>()
+            .unwrap()
+            .register_view_frustum();
+
+        // Start with the view locked so the cursor isn't grabbed until toggle_lock_view is pressed
+        let fly_camera = FlyCamera {
+            position: glam::Vec3::new(-8.0, 0.0, 3.0),
+            pitch: -0.2,
+            lock_view: true,
+            ..Default::default()
+        };
+
+        PbrTestScene {
+            main_view_frustum,
+            fly_camera,
+            prefab: Some(prefab),
+            pending_load: None,
+            prefab_entities: LoadedScene::default(),
+        }
+    }
+
+    // Queues the prefab's objects once it has loaded, the meshes then load through pending_load
+    fn update_prefab(
+        &mut self,
+        resources: &Resources,
+    ) {
+        let prefab = match &self.prefab {
+            Some(prefab) => prefab,
+            None => return,
+        };
+
+        let asset_resource = resources.get::<AssetResource>().unwrap();
+        let prefab_asset = match asset_resource.asset(prefab) {
+            Some(prefab_asset) => prefab_asset.clone(),
+            None => {
+                match asset_resource
+                    .loader()
+                    .get_load_status(prefab.load_handle())
+                {
+                    LoadStatus::DoesNotExist | LoadStatus::Error(_) => {
+                        log::error!("Failed to load prefab {:?}", PREFAB_PATH);
+                        self.prefab = None;
+                    }
+                    _ => resources.get_mut::<SceneLoadingResource>().unwrap().report(
+                        SceneLoadProgress {
+                            loaded: 0,
+                            total: 1,
+                        },
+                    ),
+                }
+                return;
+            }
+        };
+
+        let mut pending_load = PendingSceneLoad::default();
+        pending_load.set_skybox(Some(
+            asset_resource.load_asset_path::<ImageAsset, _>(SKYBOX_PATH),
+        ));
+        super::util::load_prefab(
+            &mut pending_load,
+            &*asset_resource,
+            &mut *resources.get_mut::<MeshBasicRenderObjectSet>().unwrap(),
+            &*resources.get::<VisibilityRegion>().unwrap(),
+            &prefab_asset,
+        );
+
+        self.prefab = None;
+        self.pending_load = Some(pending_load);
+    }
+}
+
+impl Scene for PbrTestScene {
+    fn cleanup(
+        &mut self,
+        world: &mut World,
+        resources: &Resources,
+    ) {
+        if let Some(pending_load) = self.pending_load.take() {
+            pending_load.cancel(resources);
+        }
+        std::mem::take(&mut self.prefab_entities).remove(world, resources);
+
+        resources
+            .get_mut::<CursorResource>()
+            .unwrap()
+            .set_mode(CursorMode::Free);
+    }
+
+    // Scenes pushed on top expect a free cursor, the camera sets the mode again once resumed
+    fn suspend(
+        &mut self,
+        _world: &mut World,
+        resources: &Resources,
+    ) {
+        resources
+            .get_mut::<CursorResource>()
+            .unwrap()
+            .set_mode(CursorMode::Free);
+    }
+
+    fn update(
+        &mut self,
+        world: &mut World,
+        resources: &mut Resources,
+    ) {
+        self.update_prefab(resources);
+        super::scene_loading::update_pending_load(
+            &mut self.pending_load,
+            &mut self.prefab_entities,
+            world,
+            resources,
+        );
+
+        super::add_light_debug_draw(&resources, &world);
+
+        {
+            let input_resource = resources.get::<InputResource>().unwrap();
+            let mut cursor_resource = resources.get_mut::<CursorResource>().unwrap();
+            let time_state = resources.get::<TimeState>().unwrap();
+            self.fly_camera
+                .update(&*input_resource, &mut *cursor_resource, &*time_state);
+        }
+
+        {
+            let mut viewports_resource = resources.get_mut::<ViewportsResource>().unwrap();
+            let render_options = resources.get::<RenderOptions>().unwrap();
+
+            super::util::update_main_view_3d(
+                &*render_options,
+                &mut self.main_view_frustum,
+                &mut *viewports_resource,
+                self.fly_camera.position,
+                self.fly_camera.position + self.fly_camera.look_dir,
+                self.fly_camera.up_dir,
+            );
+        }
+    }
+}
@@ -0,0 +1,528 @@
+use crate::error::ApiaryResult;
+use crate::scenes::PendingSceneLoad;
+use distill::core::AssetUuid;
+use distill::loader::handle::{AssetHandle, Handle};
+use legion::{Entity, IntoQuery, Read, Resources, World};
+use rafx::assets::distill_impl::AssetResource;
+use rafx::assets::ImageAsset;
+use rafx::framework::render_features::RenderObjectHandle;
+use rafx::visibility::VisibilityRegion;
+use rafx_plugins::assets::mesh_basic::MeshBasicAsset;
+use rafx_plugins::components::{
+    DirectionalLightComponent, MeshComponent, PointLightComponent, SpotLightComponent,
+    TransformComponent,
+};
+use rafx_plugins::features::mesh_basic::{MeshBasicRenderObject, MeshBasicRenderObjectSet};
+use rafx_plugins::features::skybox::SkyboxResource;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Refers to an asset either by its path in the asset directory, or by UUID for assets that
+/// can't be addressed by path, such as one of several meshes imported from the same file. UUIDs
+/// are written as strings, a malformed one fails to parse like any other error in the file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SceneAssetRef {
+    Path(String),
+    Uuid(#[serde(with = "asset_uuid_string")] AssetUuid),
+}
+
+impl SceneAssetRef {
+    fn load_mesh(
+        &self,
+        asset_resource: &AssetResource,
+    ) -> Handle<MeshBasicAsset> {
+        match self {
+            SceneAssetRef::Path(path) => asset_resource.load_asset_path(path.as_str()),
+            SceneAssetRef::Uuid(uuid) => asset_resource.load_asset(*uuid),
+        }
+    }
+
+    fn load_image(
+        &self,
+        asset_resource: &AssetResource,
+    ) -> Handle<ImageAsset> {
+        match self {
+            SceneAssetRef::Path(path) => asset_resource.load_asset_path(path.as_str()),
+            SceneAssetRef::Uuid(uuid) => asset_resource.load_asset(*uuid),
+        }
+    }
+
+    // Used when saving assets that weren't spawned from a scene file, so only the UUID is known
+    fn from_handle<T: AssetHandle>(
+        asset_resource: &AssetResource,
+        handle: &T,
+    ) -> Option<Self> {
+        asset_resource
+            .loader()
+            .get_load_info(handle.load_handle())
+            .map(|load_info| SceneAssetRef::Uuid(load_info.asset_id))
+    }
+}
+
+// Asset UUIDs in the usual hyphenated form, hyphens are optional when parsing
+mod asset_uuid_string {
+    use distill::core::AssetUuid;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        uuid: &AssetUuid,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let hex: String = uuid.0.iter().map(|byte| format!("{:02x}", byte)).collect();
+        serializer.serialize_str(&format!(
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        ))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<AssetUuid, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        parse(&string).ok_or_else(|| D::Error::custom(format!("malformed UUID {:?}", string)))
+    }
+
+    fn parse(string: &str) -> Option<AssetUuid> {
+        let hex: String = string.chars().filter(|c| *c != '-').collect();
+        if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let mut bytes = [0; 16];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+        }
+
+        Some(AssetUuid(bytes))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneTransform {
+    pub translation: glam::Vec3,
+    pub rotation: glam::Quat,
+    pub scale: glam::Vec3,
+}
+
+impl Default for SceneTransform {
+    fn default() -> Self {
+        SceneTransform {
+            translation: glam::Vec3::ZERO,
+            rotation: glam::Quat::IDENTITY,
+            scale: glam::Vec3::ONE,
+        }
+    }
+}
+
+impl From<&TransformComponent> for SceneTransform {
+    fn from(transform: &TransformComponent) -> Self {
+        SceneTransform {
+            translation: transform.translation,
+            rotation: transform.rotation,
+            scale: transform.scale,
+        }
+    }
+}
+
+impl SceneTransform {
+    fn to_component(&self) -> TransformComponent {
+        TransformComponent {
+            translation: self.translation,
+            rotation: self.rotation,
+            scale: self.scale,
+            ..Default::default()
+        }
+    }
+}
+
+/// Point and spot lights are placed at the entity's translation. Directional lights ignore it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SceneLight {
+    Point {
+        color: glam::Vec4,
+        intensity: f32,
+        range: f32,
+    },
+    Spot {
+        color: glam::Vec4,
+        intensity: f32,
+        range: f32,
+        direction: glam::Vec3,
+        half_angle_degrees: f32,
+    },
+    Directional {
+        color: glam::Vec4,
+        intensity: f32,
+        direction: glam::Vec3,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneEntity {
+    pub transform: SceneTransform,
+    pub mesh: Option<SceneAssetRef>,
+    /// Dynamic meshes are registered with the visibility region as objects that may move
+    pub dynamic: bool,
+    pub light: Option<SceneLight>,
+}
+
+/// Remembers how a mesh entity was described in its scene file so saving writes it back the same
+/// way
+pub struct SceneMeshSourceComponent {
+    pub mesh: SceneAssetRef,
+    pub dynamic: bool,
+}
+
+/// A scene description that can be loaded from and saved to RON. Loading it with `begin_load`
+/// produces an entity per mesh and light, with meshes registered with the `VisibilityRegion`.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneFile {
+    pub skybox: Option<SceneAssetRef>,
+    pub entities: Vec<SceneEntity>,
+}
+
+impl SceneFile {
+    /// Parse a scene from a RON string
+    pub fn from_ron_str(ron: &str) -> ApiaryResult<Self> {
+        Ok(ron::de::from_str(ron)?)
+    }
+
+    /// Load a scene from a RON file on disk
+    pub fn load(path: &Path) -> ApiaryResult<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::from_ron_str(&contents)
+    }
+
+    /// Serialize the scene to a human-readable RON string
+    pub fn to_ron_string(&self) -> ApiaryResult<String> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn save(
+        &self,
+        path: &Path,
+    ) -> ApiaryResult<()> {
+        std::fs::write(path, self.to_ron_string()?)?;
+        Ok(())
+    }
+
+    /// Capture the meshes and lights in the world, and the current skybox
+    pub fn from_world(
+        world: &World,
+        resources: &Resources,
+    ) -> Self {
+        let asset_resource = resources.get::<AssetResource>().unwrap();
+        let mesh_render_objects = resources.get::<MeshBasicRenderObjectSet>().unwrap();
+        let mesh_render_objects = mesh_render_objects.read();
+
+        let skybox = resources
+            .get::<SkyboxResource>()
+            .unwrap()
+            .skybox_texture()
+            .as_ref()
+            .and_then(|texture| SceneAssetRef::from_handle(&*asset_resource, texture));
+
+        let mut entities = Vec::default();
+
+        let mut query = <(
+            Entity,
+            Read<TransformComponent>,
+            Read<MeshComponent>,
+            Option<Read<SceneMeshSourceComponent>>,
+        )>::query();
+        for (entity, transform, mesh, mesh_source) in query.iter(world) {
+            let (mesh_ref, dynamic) = match mesh_source {
+                Some(mesh_source) => (Some(mesh_source.mesh.clone()), mesh_source.dynamic),
+                None => {
+                    let mesh_handle = &mesh_render_objects.get(&mesh.render_object_handle).mesh;
+                    (
+                        SceneAssetRef::from_handle(&*asset_resource, mesh_handle),
+                        false,
+                    )
+                }
+            };
+
+            let mesh_ref = match mesh_ref {
+                Some(mesh_ref) => mesh_ref,
+                None => {
+                    log::warn!("Not saving {:?}, its mesh asset is unknown", entity);
+                    continue;
+                }
+            };
+
+            entities.push(SceneEntity {
+                transform: transform.into(),
+                mesh: Some(mesh_ref),
+                dynamic,
+                light: None,
+            });
+        }
+
+        let mut query = <(Read<TransformComponent>, Read<PointLightComponent>)>::query();
+        for (transform, light) in query.iter(world) {
+            entities.push(SceneEntity {
+                transform: transform.into(),
+                light: Some(SceneLight::Point {
+                    color: light.color,
+                    intensity: light.intensity,
+                    range: light.range,
+                }),
+                ..Default::default()
+            });
+        }
+
+        let mut query = <(Read<TransformComponent>, Read<SpotLightComponent>)>::query();
+        for (transform, light) in query.iter(world) {
+            entities.push(SceneEntity {
+                transform: transform.into(),
+                light: Some(SceneLight::Spot {
+                    color: light.color,
+                    intensity: light.intensity,
+                    range: light.range,
+                    direction: light.direction,
+                    half_angle_degrees: light.spotlight_half_angle.to_degrees(),
+                }),
+                ..Default::default()
+            });
+        }
+
+        let mut query = <Read<DirectionalLightComponent>>::query();
+        for light in query.iter(world) {
+            entities.push(SceneEntity {
+                light: Some(SceneLight::Directional {
+                    color: light.color,
+                    intensity: light.intensity,
+                    direction: light.direction,
+                }),
+                ..Default::default()
+            });
+        }
+
+        SceneFile { skybox, entities }
+    }
+
+    /// Start loading the scene without blocking. The entities and skybox are applied by
+    /// `PendingSceneLoad::finish` once the meshes have loaded, so the current scene stays as it is
+    /// until then.
+    pub fn begin_load(
+        &self,
+        resources: &Resources,
+    ) -> PendingSceneLoad {
+        let asset_resource = resources.get::<AssetResource>().unwrap();
+        let mut mesh_render_objects = resources.get_mut::<MeshBasicRenderObjectSet>().unwrap();
+        let visibility_region = resources.get::<VisibilityRegion>().unwrap();
+
+        let mut pending_load = PendingSceneLoad::default();
+        pending_load.set_skybox(
+            self.skybox
+                .as_ref()
+                .map(|skybox| skybox.load_image(&*asset_resource)),
+        );
+
+        // Entities that share a mesh share its render object
+        let mut meshes =
+            HashMap::<SceneAssetRef, (RenderObjectHandle, Handle<MeshBasicAsset>)>::default();
+
+        for scene_entity in &self.entities {
+            let transform_component = scene_entity.transform.to_component();
+
+            if let Some(mesh_ref) = &scene_entity.mesh {
+                let (render_object, mesh_handle) = meshes
+                    .entry(mesh_ref.clone())
+                    .or_insert_with(|| {
+                        let mesh_handle = mesh_ref.load_mesh(&*asset_resource);
+                        let render_object =
+                            mesh_render_objects.register_render_object(MeshBasicRenderObject {
+                                mesh: mesh_handle.clone(),
+                            });
+                        pending_load.add_render_object(render_object.clone());
+                        (render_object, mesh_handle)
+                    })
+                    .clone();
+
+                pending_load.add_mesh(
+                    transform_component.clone(),
+                    render_object,
+                    mesh_handle,
+                    scene_entity.dynamic,
+                    Some(SceneMeshSourceComponent {
+                        mesh: mesh_ref.clone(),
+                        dynamic: scene_entity.dynamic,
+                    }),
+                );
+            }
+
+            if let Some(light) = &scene_entity.light {
+                queue_light(
+                    &mut pending_load,
+                    &visibility_region,
+                    transform_component.translation,
+                    light,
+                );
+            }
+        }
+
+        pending_load
+    }
+}
+
+fn queue_light(
+    pending_load: &mut PendingSceneLoad,
+    visibility_region: &VisibilityRegion,
+    position: glam::Vec3,
+    light: &SceneLight,
+) {
+    match light {
+        SceneLight::Point {
+            color,
+            intensity,
+            range,
+        } => {
+            let view_frustums = [
+                visibility_region.register_view_frustum(),
+                visibility_region.register_view_frustum(),
+                visibility_region.register_view_frustum(),
+                visibility_region.register_view_frustum(),
+                visibility_region.register_view_frustum(),
+                visibility_region.register_view_frustum(),
+            ];
+            pending_load.add_point_light(
+                position,
+                PointLightComponent {
+                    color: *color,
+                    intensity: *intensity,
+                    range: *range,
+                    view_frustums,
+                },
+            )
+        }
+        SceneLight::Spot {
+            color,
+            intensity,
+            range,
+            direction,
+            half_angle_degrees,
+        } => pending_load.add_spot_light(
+            position,
+            SpotLightComponent {
+                direction: direction.normalize(),
+                spotlight_half_angle: half_angle_degrees.to_radians(),
+                range: *range,
+                color: *color,
+                intensity: *intensity,
+                view_frustum: visibility_region.register_view_frustum(),
+            },
+        ),
+        SceneLight::Directional {
+            color,
+            intensity,
+            direction,
+        } => pending_load.add_directional_light(DirectionalLightComponent {
+            direction: direction.normalize(),
+            intensity: *intensity,
+            color: *color,
+            view_frustum: visibility_region.register_view_frustum(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ApiaryError;
+
+    const MESH_UUID: &str = "0a1b2c3d-4e5f-6789-abcd-ef0123456789";
+
+    fn scene_file() -> SceneFile {
+        SceneFile {
+            skybox: Some(SceneAssetRef::Path("textures/skybox.basis".to_string())),
+            entities: vec![
+                SceneEntity {
+                    transform: SceneTransform {
+                        translation: glam::Vec3::new(1.0, 2.0, 3.0),
+                        rotation: glam::Quat::from_rotation_z(0.5),
+                        scale: glam::Vec3::splat(2.0),
+                    },
+                    mesh: Some(SceneAssetRef::Uuid(AssetUuid([
+                        0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x01,
+                        0x23, 0x45, 0x67, 0x89,
+                    ]))),
+                    dynamic: true,
+                    light: None,
+                },
+                SceneEntity {
+                    mesh: Some(SceneAssetRef::Path("models/tree.glb".to_string())),
+                    ..Default::default()
+                },
+                SceneEntity {
+                    transform: SceneTransform {
+                        translation: glam::Vec3::new(0.0, 0.0, 5.0),
+                        ..Default::default()
+                    },
+                    light: Some(SceneLight::Spot {
+                        color: glam::Vec4::new(1.0, 0.5, 0.25, 1.0),
+                        intensity: 20.0,
+                        range: 12.0,
+                        direction: glam::Vec3::new(0.0, 0.0, -1.0),
+                        half_angle_degrees: 30.0,
+                    }),
+                    ..Default::default()
+                },
+                SceneEntity {
+                    light: Some(SceneLight::Directional {
+                        color: glam::Vec4::ONE,
+                        intensity: 5.0,
+                        direction: glam::Vec3::new(1.0, 1.0, -1.0),
+                    }),
+                    ..Default::default()
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let scene_file = scene_file();
+        let ron = scene_file.to_ron_string().unwrap();
+        assert!(ron.contains(MESH_UUID));
+        assert_eq!(SceneFile::from_ron_str(&ron).unwrap(), scene_file);
+    }
+
+    #[test]
+    fn uuid_hyphens_are_optional() {
+        let ron = format!(
+            "(entities: [(mesh: Some(Uuid(\"{}\")))])",
+            MESH_UUID.replace('-', "").to_uppercase()
+        );
+        let scene_file = SceneFile::from_ron_str(&ron).unwrap();
+        assert_eq!(
+            scene_file.entities[0].mesh,
+            self::scene_file().entities[0].mesh
+        );
+    }
+
+    #[test]
+    fn malformed_uuid_is_a_ron_error() {
+        for uuid in &[
+            "not-a-uuid",
+            "0a1b2c3d-4e5f-6789-abcd-ef012345678",
+            "+a1b2c3d4e5f6789abcdef0123456789",
+        ] {
+            let ron = format!("(skybox: Some(Uuid(\"{}\")))", uuid);
+            match SceneFile::from_ron_str(&ron) {
+                Err(ApiaryError::RonError(_)) => {}
+                result => panic!("Expected a RON error for {:?}, got {:?}", uuid, result),
+            }
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use crate::time::PeriodicEvent;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    error: Option<String>,
+}
+
+/// Polls the scene files that scenes have loaded for changes so they can be reloaded without
+/// restarting. Scenes `watch` their files when loading them and are told about changes through
+/// `Scene::scene_files_changed`. Reload errors are reported here so they can be shown on screen.
+pub struct SceneFileWatcher {
+    files: Vec<WatchedFile>,
+    poll_event: PeriodicEvent,
+}
+
+impl SceneFileWatcher {
+    /// How often the watched files' modification times are checked
+    pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    pub fn new() -> Self {
+        SceneFileWatcher {
+            files: Vec::default(),
+            poll_event: PeriodicEvent::default(),
+        }
+    }
+
+    /// Start watching a file, remembering its current modification time
+    pub fn watch(
+        &mut self,
+        path: &Path,
+    ) {
+        if self.files.iter().any(|file| file.path == path) {
+            return;
+        }
+
+        self.files.push(WatchedFile {
+            path: path.to_path_buf(),
+            modified: Self::modified_time(path),
+            error: None,
+        });
+    }
+
+    pub fn unwatch(
+        &mut self,
+        path: &Path,
+    ) {
+        self.files.retain(|file| file.path != path);
+    }
+
+    /// Returns the files that changed since the last poll. Only checks the file system once per
+    /// `POLL_INTERVAL`, `now` should come from the `ClockResource`.
+    pub fn poll_changes(
+        &mut self,
+        now: Instant,
+    ) -> Vec<PathBuf> {
+        if !self.poll_event.try_take_event(now, Self::POLL_INTERVAL) {
+            return Vec::default();
+        }
+
+        let mut changed = Vec::default();
+        for file in &mut self.files {
+            let modified = Self::modified_time(&file.path);
+            if modified != file.modified {
+                file.modified = modified;
+                changed.push(file.path.clone());
+            }
+        }
+
+        changed
+    }
+
+    /// Record that a watched file failed to load, replacing any previous error for it
+    pub fn report_error(
+        &mut self,
+        path: &Path,
+        error: String,
+    ) {
+        log::error!("Failed to load scene file {:?}: {}", path, error);
+        if let Some(file) = self.files.iter_mut().find(|file| file.path == path) {
+            file.error = Some(error);
+        }
+    }
+
+    pub fn clear_error(
+        &mut self,
+        path: &Path,
+    ) {
+        if let Some(file) = self.files.iter_mut().find(|file| file.path == path) {
+            file.error = None;
+        }
+    }
+
+    /// The most recent load error of each watched file that currently fails to load
+    pub fn errors(&self) -> impl Iterator<Item = (&Path, &str)> {
+        self.files.iter().filter_map(|file| {
+            file.error
+                .as_ref()
+                .map(|error| (file.path.as_path(), error.as_str()))
+        })
+    }
+
+    fn modified_time(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+}
+
+impl Default for SceneFileWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenes::{Scene, SceneManager, SceneTransitionResource};
+    use crate::time::{Clock, ClockResource, ManualClock, TimeState};
+    use legion::{Resources, World};
+    use std::sync::{Arc, Mutex};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "apiary_scene_file_watcher_test_{}_{}.ron",
+            std::process::id(),
+            name
+        ))
+    }
+
+    fn set_modified(
+        path: &Path,
+        secs: u64,
+    ) {
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn modified_files_are_found_once_per_poll_interval() {
+        let path = temp_path("modified");
+        std::fs::write(&path, "()").unwrap();
+        set_modified(&path, 1000);
+
+        let clock = ManualClock::new();
+        let mut watcher = SceneFileWatcher::new();
+        watcher.watch(&path);
+        assert!(watcher.poll_changes(clock.now()).is_empty());
+
+        set_modified(&path, 2000);
+        clock.advance(Duration::from_millis(100));
+        assert!(watcher.poll_changes(clock.now()).is_empty());
+
+        clock.advance(SceneFileWatcher::POLL_INTERVAL - Duration::from_millis(100));
+        assert_eq!(watcher.poll_changes(clock.now()), vec![path.clone()]);
+
+        clock.advance(SceneFileWatcher::POLL_INTERVAL);
+        assert!(watcher.poll_changes(clock.now()).is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn missing_files_change_when_they_appear_and_disappear() {
+        let path = temp_path("missing");
+        let _ = std::fs::remove_file(&path);
+
+        let clock = ManualClock::new();
+        let mut watcher = SceneFileWatcher::new();
+        watcher.watch(&path);
+        assert!(watcher.poll_changes(clock.now()).is_empty());
+
+        std::fs::write(&path, "()").unwrap();
+        clock.advance(SceneFileWatcher::POLL_INTERVAL);
+        assert_eq!(watcher.poll_changes(clock.now()), vec![path.clone()]);
+
+        std::fs::remove_file(&path).unwrap();
+        clock.advance(SceneFileWatcher::POLL_INTERVAL);
+        assert_eq!(watcher.poll_changes(clock.now()), vec![path.clone()]);
+
+        clock.advance(SceneFileWatcher::POLL_INTERVAL);
+        assert!(watcher.poll_changes(clock.now()).is_empty());
+
+        // Unwatched files aren't checked
+        watcher.unwatch(&path);
+        std::fs::write(&path, "()").unwrap();
+        clock.advance(SceneFileWatcher::POLL_INTERVAL);
+        assert!(watcher.poll_changes(clock.now()).is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn errors_are_kept_until_cleared() {
+        let path = temp_path("errors");
+        let mut watcher = SceneFileWatcher::new();
+        watcher.watch(&path);
+
+        watcher.report_error(&path, "first".to_string());
+        watcher.report_error(&path, "second".to_string());
+        watcher.report_error(&temp_path("unwatched"), "ignored".to_string());
+        assert_eq!(
+            watcher.errors().collect::<Vec<_>>(),
+            vec![(path.as_path(), "second")]
+        );
+
+        watcher.clear_error(&path);
+        assert_eq!(watcher.errors().count(), 0);
+
+        watcher.report_error(&path, "third".to_string());
+        watcher.unwatch(&path);
+        assert_eq!(watcher.errors().count(), 0);
+    }
+
+    // Records the calls a reload should and shouldn't cause
+    struct ReloadingScene {
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Scene for ReloadingScene {
+        fn update(
+            &mut self,
+            _world: &mut World,
+            _resources: &mut Resources,
+        ) {
+        }
+
+        fn cleanup(
+            &mut self,
+            _world: &mut World,
+            _resources: &Resources,
+        ) {
+            self.log.lock().unwrap().push("cleanup".to_string());
+        }
+
+        fn scene_files_changed(
+            &mut self,
+            _world: &mut World,
+            _resources: &Resources,
+            paths: &[PathBuf],
+        ) {
+            for path in paths {
+                self.log
+                    .lock()
+                    .unwrap()
+                    .push(format!("reload {}", path.display()));
+            }
+        }
+    }
+
+    // The scene is told about the change and kept running, so time carries on from where it was.
+    // The ui scene's camera follows game time, which keeps it in place too.
+    #[test]
+    fn reloading_keeps_the_scene_and_time_contexts() {
+        let path = temp_path("reload");
+        std::fs::write(&path, "()").unwrap();
+        set_modified(&path, 1000);
+
+        let manual_clock = Arc::new(ManualClock::new());
+        let mut time_state = TimeState::new(ClockResource::new(manual_clock.clone()));
+        time_state
+            .add_time_context("ui")
+            .unwrap()
+            .set_time_scale(2.0);
+        manual_clock.advance(Duration::from_secs(1));
+        time_state.update();
+
+        let mut resources = Resources::default();
+        resources.insert(time_state);
+        resources.insert(SceneTransitionResource::default());
+        resources.insert(SceneFileWatcher::new());
+
+        let log = Arc::new(Mutex::new(Vec::<String>::default()));
+        let mut world = World::default();
+        let mut scene_manager = SceneManager::default();
+        let scene_log = log.clone();
+        resources
+            .get_mut::<SceneTransitionResource>()
+            .unwrap()
+            .request_switch(Box::new(move |_world, _resources| {
+                Box::new(ReloadingScene { log: scene_log })
+            }));
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+
+        {
+            let mut watcher = resources.get_mut::<SceneFileWatcher>().unwrap();
+            watcher.watch(&path);
+            assert!(watcher.poll_changes(manual_clock.now()).is_empty());
+        }
+
+        set_modified(&path, 2000);
+        manual_clock.advance(SceneFileWatcher::POLL_INTERVAL);
+        let changed = resources
+            .get_mut::<SceneFileWatcher>()
+            .unwrap()
+            .poll_changes(manual_clock.now());
+        scene_manager.scene_files_changed(&mut world, &resources, &changed);
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![format!("reload {}", path.display())]
+        );
+        assert_eq!(scene_manager.scene_count(), 1);
+
+        let time_state = resources.get::<TimeState>().unwrap();
+        assert_eq!(
+            time_state.game_time_context().total_time(),
+            Duration::from_secs(1)
+        );
+        let ui_time_context = time_state.time_context("ui").unwrap();
+        assert_eq!(ui_time_context.total_time(), Duration::from_secs(2));
+        assert_eq!(ui_time_context.time_scale(), 2.0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,605 @@
+use crate::scenes::util::{add_directional_light, add_point_light, add_spot_light};
+use crate::scenes::SceneMeshSourceComponent;
+use distill::loader::handle::{AssetHandle, Handle};
+use distill::loader::LoadStatus;
+use legion::{Entity, Resources, World};
+use rafx::assets::distill_impl::AssetResource;
+use rafx::assets::{AssetManager, ImageAsset};
+use rafx::framework::render_features::RenderObjectHandle;
+use rafx::visibility::{CullModel, ObjectId, VisibilityRegion};
+use rafx_plugins::assets::mesh_basic::MeshBasicAsset;
+use rafx_plugins::components::{
+    DirectionalLightComponent, MeshComponent, PointLightComponent, SpotLightComponent,
+    TransformComponent, VisibilityComponent,
+};
+use rafx_plugins::features::mesh_basic::MeshBasicRenderObjectSet;
+use rafx_plugins::features::skybox::SkyboxResource;
+
+/// How far along a scene load is, counted in assets
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SceneLoadProgress {
+    pub loaded: usize,
+    pub total: usize,
+}
+
+impl SceneLoadProgress {
+    /// Between 0 and 1, 1 if there is nothing to load
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.loaded as f32 / self.total as f32
+        }
+    }
+}
+
+/// Scenes that are loading report their progress here every frame, and `ApiaryApp` draws a loading
+/// overlay while any progress has been reported
+#[derive(Default)]
+pub struct SceneLoadingResource {
+    progress: Option<SceneLoadProgress>,
+}
+
+impl SceneLoadingResource {
+    /// Report progress for this frame. Reports from several loads are combined.
+    pub fn report(
+        &mut self,
+        progress: SceneLoadProgress,
+    ) {
+        let combined = self.progress.get_or_insert_with(SceneLoadProgress::default);
+        combined.loaded += progress.loaded;
+        combined.total += progress.total;
+    }
+
+    /// The progress reported this frame, resetting it for the next frame
+    pub fn take_progress(&mut self) -> Option<SceneLoadProgress> {
+        self.progress.take()
+    }
+}
+
+/// The entities spawned by `PendingSceneLoad::finish` and the mesh render objects they use. Call
+/// `remove` to take them out of the world again, the render objects aren't freed otherwise.
+#[derive(Default)]
+pub struct LoadedScene {
+    entities: Vec<Entity>,
+    render_objects: Vec<RenderObjectHandle>,
+}
+
+impl LoadedScene {
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Remove the entities from the world and unregister their render objects. Entities that
+    /// were already removed, for example by clearing the world, are skipped.
+    pub fn remove(
+        self,
+        world: &mut World,
+        resources: &Resources,
+    ) {
+        for entity in self.entities {
+            world.remove(entity);
+        }
+
+        unregister_render_objects(resources, &self.render_objects);
+    }
+}
+
+fn unregister_render_objects(
+    resources: &Resources,
+    render_objects: &[RenderObjectHandle],
+) {
+    let mut mesh_render_objects = resources.get_mut::<MeshBasicRenderObjectSet>().unwrap();
+    for render_object in render_objects {
+        mesh_render_objects.unregister_render_object(render_object);
+    }
+}
+
+struct PendingMeshEntity {
+    transform: TransformComponent,
+    render_object: RenderObjectHandle,
+    mesh: Handle<MeshBasicAsset>,
+    dynamic: bool,
+    source: Option<SceneMeshSourceComponent>,
+}
+
+enum PendingLight {
+    Point(glam::Vec3, PointLightComponent),
+    Spot(glam::Vec3, SpotLightComponent),
+    Directional(DirectionalLightComponent),
+}
+
+struct PendingAsset {
+    handle: Handle<MeshBasicAsset>,
+    name: String,
+}
+
+/// The entities of a scene being loaded without blocking. Mesh entities need their mesh's visible
+/// bounds to be registered for visibility, so nothing is spawned until every mesh has been
+/// committed (or failed to load). Call `update` once per frame until `is_finished`, then `finish`
+/// to spawn everything at once. A load that is abandoned must be `cancel`led to free its render
+/// objects.
+#[derive(Default)]
+pub struct PendingSceneLoad {
+    mesh_entities: Vec<PendingMeshEntity>,
+    lights: Vec<PendingLight>,
+    pending_assets: Vec<PendingAsset>,
+    asset_count: usize,
+    render_objects: Vec<RenderObjectHandle>,
+    // Some if the skybox is replaced when the load finishes
+    skybox: Option<Option<Handle<ImageAsset>>>,
+}
+
+impl PendingSceneLoad {
+    /// Take ownership of a mesh render object registered for this load, so that it is
+    /// unregistered when the load is cancelled or its entities are removed. Add each render
+    /// object once, even if several meshes share it.
+    pub fn add_render_object(
+        &mut self,
+        render_object: RenderObjectHandle,
+    ) {
+        self.render_objects.push(render_object);
+    }
+
+    /// Replace the skybox once the load finishes, None removes it
+    pub fn set_skybox(
+        &mut self,
+        skybox: Option<Handle<ImageAsset>>,
+    ) {
+        self.skybox = Some(skybox);
+    }
+
+    /// Queue a mesh entity. Its mesh should already have been requested from the `AssetResource`.
+    pub fn add_mesh(
+        &mut self,
+        transform: TransformComponent,
+        render_object: RenderObjectHandle,
+        mesh: Handle<MeshBasicAsset>,
+        dynamic: bool,
+        source: Option<SceneMeshSourceComponent>,
+    ) {
+        let load_handle = mesh.load_handle();
+        let already_tracked = self
+            .mesh_entities
+            .iter()
+            .any(|entity| entity.mesh.load_handle() == load_handle);
+        if !already_tracked {
+            let name = match &source {
+                Some(source) => format!("mesh {:?}", source.mesh),
+                None => format!("mesh {:?}", load_handle),
+            };
+            self.pending_assets.push(PendingAsset {
+                handle: mesh.clone(),
+                name,
+            });
+            self.asset_count += 1;
+        }
+
+        self.mesh_entities.push(PendingMeshEntity {
+            transform,
+            render_object,
+            mesh,
+            dynamic,
+            source,
+        });
+    }
+
+    pub fn add_point_light(
+        &mut self,
+        position: glam::Vec3,
+        light: PointLightComponent,
+    ) {
+        self.lights.push(PendingLight::Point(position, light));
+    }
+
+    pub fn add_spot_light(
+        &mut self,
+        position: glam::Vec3,
+        light: SpotLightComponent,
+    ) {
+        self.lights.push(PendingLight::Spot(position, light));
+    }
+
+    pub fn add_directional_light(
+        &mut self,
+        light: DirectionalLightComponent,
+    ) {
+        self.lights.push(PendingLight::Directional(light));
+    }
+
+    /// Check which assets have finished loading. Assets that fail are logged and counted as done
+    /// so that the rest of the scene still loads.
+    pub fn update(
+        &mut self,
+        asset_manager: &AssetManager,
+        asset_resource: &AssetResource,
+    ) {
+        self.pending_assets.retain(|pending_asset| {
+            if asset_manager
+                .committed_asset(&pending_asset.handle)
+                .is_some()
+            {
+                return false;
+            }
+
+            match asset_resource
+                .loader()
+                .get_load_status(pending_asset.handle.load_handle())
+            {
+                LoadStatus::DoesNotExist => {
+                    log::error!("Failed to load {}, it does not exist", pending_asset.name);
+                    false
+                }
+                LoadStatus::Error(e) => {
+                    log::error!("Failed to load {}: {:?}", pending_asset.name, e);
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+
+    pub fn progress(&self) -> SceneLoadProgress {
+        SceneLoadProgress {
+            loaded: self.asset_count - self.pending_assets.len(),
+            total: self.asset_count,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pending_assets.is_empty()
+    }
+
+    /// Abandon the load, unregistering the render objects it owns
+    pub fn cancel(
+        self,
+        resources: &Resources,
+    ) {
+        unregister_render_objects(resources, &self.render_objects);
+    }
+
+    /// Spawn every queued entity, registering meshes with the `VisibilityRegion`, and apply the
+    /// skybox. Returns the spawned entities so they can be removed again.
+    pub fn finish(
+        self,
+        world: &mut World,
+        resources: &Resources,
+    ) -> LoadedScene {
+        if let Some(skybox) = self.skybox {
+            *resources
+                .get_mut::<SkyboxResource>()
+                .unwrap()
+                .skybox_texture_mut() = skybox;
+        }
+
+        let mut entities = Vec::with_capacity(self.mesh_entities.len() + self.lights.len());
+
+        // Loads without meshes, such as a skybox change, don't need the asset manager
+        if !self.mesh_entities.is_empty() {
+            let asset_manager = resources.get::<AssetManager>().unwrap();
+            let visibility_region = resources.get::<VisibilityRegion>().unwrap();
+            for mesh_entity in self.mesh_entities {
+                entities.push(spawn_mesh_entity(
+                    world,
+                    &*asset_manager,
+                    &*visibility_region,
+                    mesh_entity,
+                ));
+            }
+        }
+
+        for light in self.lights {
+            let entity = match light {
+                PendingLight::Point(position, light) => {
+                    add_point_light(resources, world, position, light)
+                }
+                PendingLight::Spot(position, light) => {
+                    add_spot_light(resources, world, position, light)
+                }
+                PendingLight::Directional(light) => add_directional_light(resources, world, light),
+            };
+            entities.push(entity);
+        }
+
+        LoadedScene {
+            entities,
+            render_objects: self.render_objects,
+        }
+    }
+}
+
+// Spawn a mesh entity, registering it for visibility once its mesh has loaded
+fn spawn_mesh_entity(
+    world: &mut World,
+    asset_manager: &AssetManager,
+    visibility_region: &VisibilityRegion,
+    mesh_entity: PendingMeshEntity,
+) -> Entity {
+    let mesh_component = MeshComponent {
+        render_object_handle: mesh_entity.render_object.clone(),
+    };
+    let entity = match mesh_entity.source {
+        Some(source) => world.push((mesh_entity.transform.clone(), mesh_component, source)),
+        None => world.push((mesh_entity.transform.clone(), mesh_component)),
+    };
+
+    let visible_bounds = asset_manager
+        .committed_asset(&mesh_entity.mesh)
+        .map(|x| x.inner.asset_data.visible_bounds);
+    let visible_bounds = match visible_bounds {
+        Some(visible_bounds) => visible_bounds,
+        None => {
+            log::warn!(
+                "Mesh {:?} failed to load, it won't be visible",
+                mesh_entity.mesh.load_handle()
+            );
+            return entity;
+        }
+    };
+
+    let transform = &mesh_entity.transform;
+    let mut entry = world.entry(entity).unwrap();
+    entry.add_component(VisibilityComponent {
+        visibility_object_handle: {
+            let handle = if mesh_entity.dynamic {
+                visibility_region.register_dynamic_object(
+                    ObjectId::from(entity),
+                    CullModel::VisibleBounds(visible_bounds),
+                )
+            } else {
+                visibility_region.register_static_object(
+                    ObjectId::from(entity),
+                    CullModel::VisibleBounds(visible_bounds),
+                )
+            };
+            handle.set_transform(transform.translation, transform.rotation, transform.scale);
+            handle.add_render_object(&mesh_entity.render_object);
+            handle
+        },
+    });
+
+    entity
+}
+
+/// Check on a scene load once per frame, reporting its progress to the `SceneLoadingResource`.
+/// Once it finishes, the entities in `loaded_scene` are removed and replaced with the new ones.
+pub(super) fn update_pending_load(
+    pending_load: &mut Option<PendingSceneLoad>,
+    loaded_scene: &mut LoadedScene,
+    world: &mut World,
+    resources: &Resources,
+) {
+    let load = match pending_load {
+        Some(load) => load,
+        None => return,
+    };
+
+    {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let asset_resource = resources.get::<AssetResource>().unwrap();
+        load.update(&*asset_manager, &*asset_resource);
+    }
+
+    if !load.is_finished() {
+        resources
+            .get_mut::<SceneLoadingResource>()
+            .unwrap()
+            .report(load.progress());
+        return;
+    }
+
+    std::mem::take(loaded_scene).remove(world, resources);
+    *loaded_scene = pending_load.take().unwrap().finish(world, resources);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::{Receiver, Sender};
+    use distill::loader::handle::RefOp;
+    use distill::loader::LoadHandle;
+    use rafx_plugins::features::mesh_basic::MeshBasicRenderObject;
+
+    // Makes strong handles that report clones and drops back to it, so a test can tell whether
+    // anything still holds a handle, such as a render object that wasn't unregistered
+    struct HandleCounter {
+        sender: Sender<RefOp>,
+        receiver: Receiver<RefOp>,
+        clones: i64,
+    }
+
+    impl HandleCounter {
+        fn new() -> Self {
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            HandleCounter {
+                sender,
+                receiver,
+                clones: 0,
+            }
+        }
+
+        fn handle<T>(
+            &self,
+            id: u64,
+        ) -> Handle<T> {
+            Handle::new(self.sender.clone(), LoadHandle(id))
+        }
+
+        // Clones of the handles made by this counter that haven't been dropped yet
+        fn live_clones(&mut self) -> i64 {
+            for ref_op in self.receiver.try_iter() {
+                match ref_op {
+                    RefOp::Increase(_) => self.clones += 1,
+                    RefOp::Decrease(_) => self.clones -= 1,
+                    _ => {}
+                }
+            }
+            self.clones
+        }
+    }
+
+    // Queue `count` entities sharing one mesh and render object, the way scene files do
+    fn add_meshes(
+        pending_load: &mut PendingSceneLoad,
+        mesh_render_objects: &mut MeshBasicRenderObjectSet,
+        mesh: &Handle<MeshBasicAsset>,
+        count: usize,
+    ) {
+        let render_object = mesh_render_objects
+            .register_render_object(MeshBasicRenderObject { mesh: mesh.clone() });
+        pending_load.add_render_object(render_object.clone());
+        for _ in 0..count {
+            pending_load.add_mesh(
+                TransformComponent::default(),
+                render_object.clone(),
+                mesh.clone(),
+                false,
+                None,
+            );
+        }
+    }
+
+    // What `update` does once the mesh has been committed
+    fn mesh_loaded(
+        pending_load: &mut PendingSceneLoad,
+        mesh: &Handle<MeshBasicAsset>,
+    ) {
+        pending_load
+            .pending_assets
+            .retain(|pending_asset| pending_asset.handle.load_handle() != mesh.load_handle());
+    }
+
+    #[test]
+    fn progress_counts_each_mesh_once() {
+        let handle_counter = HandleCounter::new();
+        let tree = handle_counter.handle(1);
+        let rock = handle_counter.handle(2);
+        let visibility_region = VisibilityRegion::new();
+        let mut mesh_render_objects = MeshBasicRenderObjectSet::default();
+
+        let mut pending_load = PendingSceneLoad::default();
+        assert!(pending_load.is_finished());
+        assert_eq!(pending_load.progress().fraction(), 1.0);
+
+        add_meshes(&mut pending_load, &mut mesh_render_objects, &tree, 3);
+        add_meshes(&mut pending_load, &mut mesh_render_objects, &rock, 1);
+        pending_load.add_directional_light(DirectionalLightComponent {
+            color: glam::Vec4::ONE,
+            intensity: 1.0,
+            direction: -glam::Vec3::Z,
+            view_frustum: visibility_region.register_view_frustum(),
+        });
+        assert_eq!(
+            pending_load.progress(),
+            SceneLoadProgress {
+                loaded: 0,
+                total: 2
+            }
+        );
+
+        mesh_loaded(&mut pending_load, &tree);
+        assert_eq!(pending_load.progress().fraction(), 0.5);
+        assert!(!pending_load.is_finished());
+
+        mesh_loaded(&mut pending_load, &rock);
+        assert!(pending_load.is_finished());
+
+        // Several loads in a frame are shown as one
+        let mut scene_loading_resource = SceneLoadingResource::default();
+        scene_loading_resource.report(pending_load.progress());
+        scene_loading_resource.report(SceneLoadProgress {
+            loaded: 1,
+            total: 4,
+        });
+        assert_eq!(
+            scene_loading_resource.take_progress(),
+            Some(SceneLoadProgress {
+                loaded: 3,
+                total: 6
+            })
+        );
+        assert_eq!(scene_loading_resource.take_progress(), None);
+    }
+
+    #[test]
+    fn cancel_unregisters_render_objects() {
+        let mut handle_counter = HandleCounter::new();
+        let tree = handle_counter.handle(1);
+        let visibility_region = VisibilityRegion::new();
+        let mut mesh_render_objects = MeshBasicRenderObjectSet::default();
+
+        let mut pending_load = PendingSceneLoad::default();
+        add_meshes(&mut pending_load, &mut mesh_render_objects, &tree, 2);
+        pending_load.add_spot_light(
+            glam::Vec3::ZERO,
+            SpotLightComponent {
+                color: glam::Vec4::ONE,
+                intensity: 1.0,
+                range: 10.0,
+                view_frustum: visibility_region.register_view_frustum(),
+                spotlight_half_angle: 0.5,
+                direction: -glam::Vec3::Z,
+            },
+        );
+        assert!(handle_counter.live_clones() > 0);
+
+        let mut resources = Resources::default();
+        resources.insert(mesh_render_objects);
+
+        // The light's view frustum is released with it, and the render object set drops the
+        // mesh handle it held once the render object is unregistered
+        pending_load.cancel(&resources);
+        assert_eq!(handle_counter.live_clones(), 0);
+    }
+
+    #[test]
+    fn finish_applies_the_skybox() {
+        let handle_counter = HandleCounter::new();
+        let old_skybox: Handle<ImageAsset> = handle_counter.handle(1);
+        let new_skybox: Handle<ImageAsset> = handle_counter.handle(2);
+        let visibility_region = VisibilityRegion::new();
+
+        let mut resources = Resources::default();
+        resources.insert(MeshBasicRenderObjectSet::default());
+        resources.insert(SkyboxResource::default());
+        *resources
+            .get_mut::<SkyboxResource>()
+            .unwrap()
+            .skybox_texture_mut() = Some(old_skybox);
+        let mut world = World::default();
+
+        let skybox_load_handle = |resources: &Resources| {
+            resources
+                .get_mut::<SkyboxResource>()
+                .unwrap()
+                .skybox_texture_mut()
+                .as_ref()
+                .map(|skybox| skybox.load_handle())
+        };
+
+        // Loads that don't set a skybox keep the current one
+        let mut pending_load = PendingSceneLoad::default();
+        pending_load.add_directional_light(DirectionalLightComponent {
+            color: glam::Vec4::ONE,
+            intensity: 1.0,
+            direction: -glam::Vec3::Z,
+            view_frustum: visibility_region.register_view_frustum(),
+        });
+        let loaded_scene = pending_load.finish(&mut world, &resources);
+        assert_eq!(loaded_scene.entities().len(), 1);
+        assert_eq!(skybox_load_handle(&resources), Some(LoadHandle(1)));
+
+        loaded_scene.remove(&mut world, &resources);
+        assert!(world.is_empty());
+
+        let mut pending_load = PendingSceneLoad::default();
+        pending_load.set_skybox(Some(new_skybox));
+        pending_load.finish(&mut world, &resources);
+        assert_eq!(skybox_load_handle(&resources), Some(LoadHandle(2)));
+
+        let mut pending_load = PendingSceneLoad::default();
+        pending_load.set_skybox(None);
+        pending_load.finish(&mut world, &resources);
+        assert_eq!(skybox_load_handle(&resources), None);
+    }
+}
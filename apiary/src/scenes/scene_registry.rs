@@ -0,0 +1,324 @@
+use crate::scenes::{Scene, SceneTransitionResource};
+use legion::{Resources, World};
+
+/// Creates a registered scene against the world it will run in
+pub type SceneFactoryFn = fn(&mut World, &Resources) -> Box<dyn Scene>;
+
+struct RegisteredScene {
+    name: String,
+    factory: SceneFactoryFn,
+}
+
+/// The scenes that can be switched to by name, in the order they are cycled through. Tracks which
+/// registered scene is running so the demos can be stepped through. Keep it in `Resources` so that
+/// switches requested through it update the current scene once they are applied.
+#[derive(Default)]
+pub struct SceneRegistry {
+    scenes: Vec<RegisteredScene>,
+    current_index: Option<usize>,
+    // The most recently requested scene until its switch is applied, so that cycling several times
+    // in one frame steps on from it rather than from the running scene
+    pending_index: Option<usize>,
+}
+
+impl SceneRegistry {
+    /// Add a scene. Registering a name again replaces its factory.
+    pub fn register(
+        &mut self,
+        name: &str,
+        factory: SceneFactoryFn,
+    ) {
+        match self.index_of(name) {
+            Some(index) => self.scenes[index].factory = factory,
+            None => self.scenes.push(RegisteredScene {
+                name: name.to_string(),
+                factory,
+            }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.scenes.iter().map(|scene| scene.name.as_str())
+    }
+
+    pub fn index_of(
+        &self,
+        name: &str,
+    ) -> Option<usize> {
+        self.scenes.iter().position(|scene| scene.name == name)
+    }
+
+    pub fn name(
+        &self,
+        index: usize,
+    ) -> Option<&str> {
+        self.scenes.get(index).map(|scene| scene.name.as_str())
+    }
+
+    pub fn factory(
+        &self,
+        index: usize,
+    ) -> Option<SceneFactoryFn> {
+        self.scenes.get(index).map(|scene| scene.factory)
+    }
+
+    /// Index of the registered scene that is running. Requested switches change this once
+    /// `SceneManager` applies them.
+    pub fn current_index(&self) -> Option<usize> {
+        self.current_index
+    }
+
+    pub fn current_name(&self) -> Option<&str> {
+        self.current_index.and_then(|index| self.name(index))
+    }
+
+    /// Record which scene is running without requesting a transition, for scenes installed
+    /// directly with `SceneManager::set_scene`
+    pub fn set_current_index(
+        &mut self,
+        index: Option<usize>,
+    ) {
+        self.current_index = index;
+    }
+
+    /// Queue a switch to the registered scene at `index`. Returns false if there is no such scene.
+    pub fn request_switch(
+        &mut self,
+        index: usize,
+        transitions: &mut SceneTransitionResource,
+    ) -> bool {
+        let factory = match self.factory(index) {
+            Some(factory) => factory,
+            None => return false,
+        };
+
+        log::info!("Switching to scene {:?}", self.scenes[index].name);
+        self.pending_index = Some(index);
+        transitions.request_switch(Box::new(move |world, resources| {
+            if let Some(mut scene_registry) = resources.get_mut::<SceneRegistry>() {
+                scene_registry.set_current_index(Some(index));
+                if scene_registry.pending_index == Some(index) {
+                    scene_registry.pending_index = None;
+                }
+            }
+            factory(world, resources)
+        }));
+        true
+    }
+
+    /// Queue a switch to the registered scene with the given name. Returns false if there is no
+    /// such scene.
+    pub fn request_switch_named(
+        &mut self,
+        name: &str,
+        transitions: &mut SceneTransitionResource,
+    ) -> bool {
+        match self.index_of(name) {
+            Some(index) => self.request_switch(index, transitions),
+            None => false,
+        }
+    }
+
+    /// Queue a switch to the scene after the current one, wrapping around. Does nothing if that
+    /// is the current scene. A switch that hasn't been applied yet counts as the current scene.
+    pub fn request_next(
+        &mut self,
+        transitions: &mut SceneTransitionResource,
+    ) {
+        if self.is_empty() {
+            return;
+        }
+
+        let index = match self.latest_index() {
+            Some(index) => (index + 1) % self.len(),
+            None => 0,
+        };
+        self.request_cycle(index, transitions);
+    }
+
+    /// Queue a switch to the scene before the current one, wrapping around. Does nothing if that
+    /// is the current scene. A switch that hasn't been applied yet counts as the current scene.
+    pub fn request_previous(
+        &mut self,
+        transitions: &mut SceneTransitionResource,
+    ) {
+        if self.is_empty() {
+            return;
+        }
+
+        let index = match self.latest_index() {
+            Some(index) => (index + self.len() - 1) % self.len(),
+            None => self.len() - 1,
+        };
+        self.request_cycle(index, transitions);
+    }
+
+    // The scene that will be running once queued switches are applied
+    fn latest_index(&self) -> Option<usize> {
+        self.pending_index.or(self.current_index)
+    }
+
+    // Cycling onto the scene that is already running would just restart it
+    fn request_cycle(
+        &mut self,
+        index: usize,
+        transitions: &mut SceneTransitionResource,
+    ) {
+        if self.latest_index() != Some(index) {
+            self.request_switch(index, transitions);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenes::SceneManager;
+
+    struct EmptyScene;
+
+    impl Scene for EmptyScene {
+        fn update(
+            &mut self,
+            _world: &mut World,
+            _resources: &mut Resources,
+        ) {
+        }
+    }
+
+    fn empty_scene(
+        _world: &mut World,
+        _resources: &Resources,
+    ) -> Box<dyn Scene> {
+        Box::new(EmptyScene)
+    }
+
+    fn resources(scene_names: &[&str]) -> Resources {
+        let mut scene_registry = SceneRegistry::default();
+        for name in scene_names {
+            scene_registry.register(name, empty_scene);
+        }
+
+        let mut resources = Resources::default();
+        resources.insert(scene_registry);
+        resources.insert(SceneTransitionResource::default());
+        resources
+    }
+
+    fn request_next(resources: &Resources) {
+        resources
+            .get_mut::<SceneRegistry>()
+            .unwrap()
+            .request_next(&mut resources.get_mut::<SceneTransitionResource>().unwrap());
+    }
+
+    fn request_previous(resources: &Resources) {
+        resources
+            .get_mut::<SceneRegistry>()
+            .unwrap()
+            .request_previous(&mut resources.get_mut::<SceneTransitionResource>().unwrap());
+    }
+
+    fn current_index(resources: &Resources) -> Option<usize> {
+        resources.get::<SceneRegistry>().unwrap().current_index()
+    }
+
+    fn has_pending_transitions(resources: &Resources) -> bool {
+        resources
+            .get::<SceneTransitionResource>()
+            .unwrap()
+            .has_pending_transitions()
+    }
+
+    #[test]
+    fn current_index_changes_when_the_switch_is_applied() {
+        let resources = resources(&["a", "b", "c"]);
+        let mut world = World::default();
+        let mut scene_manager = SceneManager::default();
+
+        request_next(&resources);
+        assert!(has_pending_transitions(&resources));
+        assert_eq!(current_index(&resources), None);
+
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        assert_eq!(current_index(&resources), Some(0));
+        assert_eq!(scene_manager.scene_count(), 1);
+
+        request_previous(&resources);
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        assert_eq!(current_index(&resources), Some(2));
+
+        request_next(&resources);
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        assert_eq!(current_index(&resources), Some(0));
+        assert_eq!(scene_manager.scene_count(), 1);
+    }
+
+    #[test]
+    fn cycling_twice_in_a_frame_steps_twice() {
+        let resources = resources(&["a", "b", "c"]);
+        let mut world = World::default();
+        let mut scene_manager = SceneManager::default();
+        resources
+            .get_mut::<SceneRegistry>()
+            .unwrap()
+            .set_current_index(Some(0));
+
+        request_next(&resources);
+        request_next(&resources);
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        assert_eq!(current_index(&resources), Some(2));
+        assert_eq!(scene_manager.scene_count(), 1);
+
+        // Each step starts from the one before it, so these come back around to the same scene
+        request_previous(&resources);
+        request_next(&resources);
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        assert_eq!(current_index(&resources), Some(2));
+
+        request_previous(&resources);
+        request_previous(&resources);
+        request_previous(&resources);
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        assert_eq!(current_index(&resources), Some(2));
+
+        request_next(&resources);
+        scene_manager.apply_pending_transitions(&mut world, &resources);
+        assert_eq!(current_index(&resources), Some(0));
+    }
+
+    #[test]
+    fn cycling_onto_the_current_scene_does_nothing() {
+        let resources = resources(&["only"]);
+        resources
+            .get_mut::<SceneRegistry>()
+            .unwrap()
+            .set_current_index(Some(0));
+
+        request_next(&resources);
+        request_previous(&resources);
+        assert!(!has_pending_transitions(&resources));
+        assert_eq!(current_index(&resources), Some(0));
+    }
+
+    #[test]
+    fn switching_to_an_unknown_scene_fails() {
+        let resources = resources(&["a"]);
+        let mut scene_registry = resources.get_mut::<SceneRegistry>().unwrap();
+        let mut transitions = resources.get_mut::<SceneTransitionResource>().unwrap();
+
+        assert!(!scene_registry.request_switch(1, &mut transitions));
+        assert!(!scene_registry.request_switch_named("b", &mut transitions));
+        assert!(!transitions.has_pending_transitions());
+        assert!(scene_registry.request_switch_named("a", &mut transitions));
+        assert!(transitions.has_pending_transitions());
+    }
+}
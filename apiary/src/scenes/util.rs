@@ -1,20 +1,37 @@
-use crate::input::{InputState, KeyboardKey};
+use crate::app::RenderOptions;
+use crate::input::{CursorMode, CursorResource, InputResource};
+use crate::scenes::PendingSceneLoad;
 use crate::time::TimeState;
-use distill::loader::handle::{AssetHandle, Handle};
+use distill::loader::handle::AssetHandle;
 use legion::IntoQuery;
-use legion::{Read, Resources, World};
+use legion::{Entity, Read, Resources, World};
 use rafx::assets::distill_impl::AssetResource;
-use rafx::assets::AssetManager;
-use rafx::rafx_visibility::VisibleBounds;
-use rafx::visibility::{CullModel, ObjectId, VisibilityRegion};
+use rafx::framework::render_features::{
+    RenderFeatureFlagMaskBuilder, RenderFeatureMaskBuilder, RenderPhaseMaskBuilder,
+    RenderViewDepthRange,
+};
+use rafx::rafx_visibility::{DepthRange, PerspectiveParameters, Projection};
+use rafx::renderer::{RenderViewMeta, ViewportsResource};
+use rafx::visibility::{ViewFrustumArc, VisibilityRegion};
 use rafx_plugins::assets::mesh_basic::prefab_asset::PrefabBasicAssetDataObjectLightKind;
-use rafx_plugins::assets::mesh_basic::{MeshBasicAsset, PrefabBasicAsset};
+use rafx_plugins::assets::mesh_basic::PrefabBasicAsset;
 use rafx_plugins::components::{
-    DirectionalLightComponent, MeshComponent, PointLightComponent, SpotLightComponent,
-    TransformComponent, VisibilityComponent,
+    DirectionalLightComponent, PointLightComponent, SpotLightComponent, TransformComponent,
+};
+use rafx_plugins::features::debug3d::{Debug3DRenderFeature, Debug3DResource};
+use rafx_plugins::features::mesh_basic::{
+    MeshBasicNoShadowsRenderFeatureFlag, MeshBasicRenderFeature, MeshBasicRenderObject,
+    MeshBasicRenderObjectSet, MeshBasicUnlitRenderFeatureFlag,
+    MeshBasicUntexturedRenderFeatureFlag, MeshBasicWireframeRenderFeatureFlag,
+};
+use rafx_plugins::features::skybox::SkyboxRenderFeature;
+use rafx_plugins::features::sprite::SpriteRenderFeature;
+use rafx_plugins::features::text::TextRenderFeature;
+use rafx_plugins::features::tile_layer::TileLayerRenderFeature;
+use rafx_plugins::phases::{
+    DepthPrepassRenderPhase, OpaqueRenderPhase, TransparentRenderPhase, UiRenderPhase,
+    WireframeRenderPhase,
 };
-use rafx_plugins::features::debug3d::Debug3DResource;
-use rafx_plugins::features::mesh_basic::{MeshBasicRenderObject, MeshBasicRenderObjectSet};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -38,21 +55,32 @@ pub(super) struct FlyCamera {
 }
 
 impl FlyCamera {
-    pub(super) fn update(&mut self, input_state: &InputState, time_state: &TimeState) {
+    pub(super) fn update(
+        &mut self,
+        input: &InputResource,
+        cursor: &mut CursorResource,
+        time_state: &TimeState,
+    ) {
         // Allow locking camera position/rotation
-        if input_state.is_key_just_down(KeyboardKey::F) {
+        if input.action_pressed("toggle_lock_view") {
             self.lock_view = !self.lock_view;
         }
 
+        // Mouse-look needs the cursor locked, free it up while the view is locked
+        cursor.set_mode(if self.lock_view {
+            CursorMode::Free
+        } else {
+            CursorMode::Locked
+        });
+
         const NORMAL_MOVE_SPEED: f32 = 10.0;
         const FAST_MOVE_SPEED: f32 = 30.0;
-        const LOOK_SPEED: f32 = 0.1;
         const TWO_PI: f32 = 2.0 * std::f32::consts::PI;
 
-        // Use mouse motion to rotate the camera
+        // Use mouse motion to rotate the camera, sensitivity is configured on the look axes
         if !self.lock_view {
-            let yaw_dt = input_state.mouse_motion().x as f32 * LOOK_SPEED * -1.0;
-            let pitch_dt = input_state.mouse_motion().y as f32 * LOOK_SPEED * -1.0;
+            let yaw_dt = input.axis_value("look_x");
+            let pitch_dt = input.axis_value("look_y");
 
             self.yaw += yaw_dt * time_state.previous_update_dt();
             while self.yaw > std::f32::consts::PI {
@@ -68,11 +96,6 @@ impl FlyCamera {
                 -std::f32::consts::FRAC_PI_2 + 0.01,
                 std::f32::consts::FRAC_PI_2 - 0.01,
             );
-            self.pitch += pitch_dt * time_state.previous_update_dt();
-            self.pitch = self.pitch.clamp(
-                -std::f32::consts::FRAC_PI_2 + 0.01,
-                std::f32::consts::FRAC_PI_2 - 0.01,
-            );
         }
 
         // Recalculate frenet frame, do this even if the camera is locked so that if the pitch/yaw
@@ -92,9 +115,7 @@ impl FlyCamera {
 
         // Use wasd to move the camera
         if !self.lock_view {
-            let move_speed = if input_state.is_key_down(KeyboardKey::LShift)
-                || input_state.is_key_down(KeyboardKey::RShift)
-            {
+            let move_speed = if input.action_down("move_fast") {
                 FAST_MOVE_SPEED
             } else {
                 NORMAL_MOVE_SPEED
@@ -102,22 +123,11 @@ impl FlyCamera {
 
             //+x = forward
             //+y = right
-            let mut velocity = glam::Vec3::default();
-            if input_state.is_key_down(KeyboardKey::W) {
-                velocity.x += move_speed;
-            }
-
-            if input_state.is_key_down(KeyboardKey::S) {
-                velocity.x -= move_speed;
-            }
-
-            if input_state.is_key_down(KeyboardKey::A) {
-                velocity.y -= move_speed;
-            }
-
-            if input_state.is_key_down(KeyboardKey::D) {
-                velocity.y += move_speed;
-            }
+            let velocity = glam::Vec3::new(
+                input.axis_value("move_forward") * move_speed,
+                input.axis_value("move_right") * move_speed,
+                0.0,
+            );
 
             self.position += velocity.x * self.look_dir * time_state.previous_update_dt();
             self.position += velocity.y * self.right_dir * time_state.previous_update_dt();
@@ -135,30 +145,15 @@ impl FlyCamera {
     }
 }
 
-pub(super) fn spawn_prefab(
-    world: &mut World,
-    resources: &Resources,
-    asset_manager: &mut AssetManager,
-    asset_resource: &mut AssetResource,
+/// Queue the meshes and lights of a prefab into a `PendingSceneLoad`. The prefab's model assets
+/// must already be loaded, but their meshes are loaded without blocking.
+pub(super) fn load_prefab(
+    pending_load: &mut PendingSceneLoad,
+    asset_resource: &AssetResource,
     mesh_render_objects: &mut MeshBasicRenderObjectSet,
     visibility_region: &VisibilityRegion,
     bistro_prefab_asset: &PrefabBasicAsset,
 ) {
-    fn load_visible_bounds(
-        asset_manager: &mut AssetManager,
-        asset_resource: &mut AssetResource,
-        asset_handle: &Handle<MeshBasicAsset>,
-        asset_name: &str,
-    ) -> Option<VisibleBounds> {
-        asset_manager
-            .wait_for_asset_to_load(asset_handle, asset_resource, asset_name)
-            .unwrap();
-
-        asset_manager
-            .committed_asset(asset_handle)
-            .map(|x| x.inner.asset_data.visible_bounds)
-    }
-
     let mut point_light_count = 0;
     let mut spot_light_count = 0;
     let mut directional_light_count = 0;
@@ -168,6 +163,14 @@ pub(super) fn spawn_prefab(
         if let Some(model) = &object.model {
             let model_asset_handle = asset_resource.asset(&model.model);
             if model_asset_handle.is_none() {
+                let load_info = asset_resource
+                    .loader()
+                    .get_load_info(model.model.load_handle());
+                log::warn!(
+                    "Did not find committed asset for {:?} load_info: {:?}",
+                    model.model,
+                    load_info
+                );
                 continue;
             }
             let model_asset = model_asset_handle.unwrap();
@@ -176,6 +179,7 @@ pub(super) fn spawn_prefab(
             let render_object = mesh_render_objects.register_render_object(MeshBasicRenderObject {
                 mesh: mesh_asset.clone(),
             });
+            pending_load.add_render_object(render_object.clone());
 
             let transform_component = TransformComponent {
                 translation: object.transform.position,
@@ -184,46 +188,7 @@ pub(super) fn spawn_prefab(
                 ..Default::default()
             };
 
-            let mesh_component = MeshComponent {
-                render_object_handle: render_object.clone(),
-            };
-
-            let entity = world.push((transform_component.clone(), mesh_component));
-            let mut entry = world.entry(entity).unwrap();
-
-            let visible_bounds = load_visible_bounds(
-                &mut *asset_manager,
-                &mut *asset_resource,
-                &mesh_asset,
-                &format!("visible bounds for {:?}", model.model),
-            );
-
-            if let Some(visible_bounds) = visible_bounds {
-                entry.add_component(VisibilityComponent {
-                    visibility_object_handle: {
-                        let handle = visibility_region.register_static_object(
-                            ObjectId::from(entity),
-                            CullModel::VisibleBounds(visible_bounds),
-                        );
-                        handle.set_transform(
-                            transform_component.translation,
-                            transform_component.rotation,
-                            transform_component.scale,
-                        );
-                        handle.add_render_object(&render_object);
-                        handle
-                    },
-                });
-            } else {
-                let load_info = asset_resource
-                    .loader()
-                    .get_load_info(model.model.load_handle());
-                log::warn!(
-                    "Did not find committed asset for {:?} load_info: {:?}",
-                    model.model,
-                    load_info
-                );
-            }
+            pending_load.add_mesh(transform_component, render_object, mesh_asset, false, None);
         }
 
         if let Some(light) = &object.light {
@@ -238,9 +203,7 @@ pub(super) fn spawn_prefab(
                             visibility_region.register_view_frustum(),
                             visibility_region.register_view_frustum(),
                         ];
-                        add_point_light(
-                            resources,
-                            world,
+                        pending_load.add_point_light(
                             //glam::Vec3::new(-3.0, 3.0, 2.0),
                             object.transform.position,
                             PointLightComponent {
@@ -256,9 +219,7 @@ pub(super) fn spawn_prefab(
                 PrefabBasicAssetDataObjectLightKind::Spot => {
                     if spot_light_count < 15 {
                         let view_frustum = visibility_region.register_view_frustum();
-                        add_spot_light(
-                            resources,
-                            world,
+                        pending_load.add_spot_light(
                             //glam::Vec3::new(-3.0, 3.0, 2.0),
                             object.transform.position,
                             SpotLightComponent {
@@ -276,16 +237,12 @@ pub(super) fn spawn_prefab(
                 PrefabBasicAssetDataObjectLightKind::Directional => {
                     if directional_light_count < 15 {
                         let view_frustum = visibility_region.register_view_frustum();
-                        add_directional_light(
-                            resources,
-                            world,
-                            DirectionalLightComponent {
-                                color: light.color.extend(1.0),
-                                intensity: light.intensity * 0.15,
-                                view_frustum,
-                                direction: object.transform.rotation * -glam::Vec3::Z,
-                            },
-                        );
+                        pending_load.add_directional_light(DirectionalLightComponent {
+                            color: light.color.extend(1.0),
+                            intensity: light.intensity * 0.15,
+                            view_frustum,
+                            direction: object.transform.rotation * -glam::Vec3::Z,
+                        });
                         directional_light_count += 1;
                     }
                 }
@@ -294,6 +251,102 @@ pub(super) fn spawn_prefab(
     }
 }
 
+/// Point the main view from `eye` towards `look_at`, rendering the features enabled in
+/// `RenderOptions`
+#[profiling::function]
+pub(super) fn update_main_view_3d(
+    render_options: &RenderOptions,
+    main_view_frustum: &mut ViewFrustumArc,
+    viewports_resource: &mut ViewportsResource,
+    eye: glam::Vec3,
+    look_at: glam::Vec3,
+    up: glam::Vec3,
+) {
+    let phase_mask_builder = RenderPhaseMaskBuilder::default()
+        .add_render_phase::<DepthPrepassRenderPhase>()
+        .add_render_phase::<OpaqueRenderPhase>()
+        .add_render_phase::<TransparentRenderPhase>()
+        .add_render_phase::<WireframeRenderPhase>()
+        .add_render_phase::<UiRenderPhase>();
+
+    let mut feature_mask_builder = RenderFeatureMaskBuilder::default()
+        .add_render_feature::<MeshBasicRenderFeature>()
+        .add_render_feature::<SpriteRenderFeature>()
+        .add_render_feature::<TileLayerRenderFeature>();
+
+    #[cfg(feature = "egui")]
+    {
+        feature_mask_builder = feature_mask_builder
+            .add_render_feature::<rafx_plugins::features::egui::EguiRenderFeature>();
+    }
+
+    if render_options.show_text {
+        feature_mask_builder = feature_mask_builder.add_render_feature::<TextRenderFeature>();
+    }
+
+    if render_options.show_debug3d {
+        feature_mask_builder = feature_mask_builder.add_render_feature::<Debug3DRenderFeature>();
+    }
+
+    if render_options.show_skybox {
+        feature_mask_builder = feature_mask_builder.add_render_feature::<SkyboxRenderFeature>();
+    }
+
+    let mut feature_flag_mask_builder = RenderFeatureFlagMaskBuilder::default();
+
+    if render_options.show_wireframes {
+        feature_flag_mask_builder = feature_flag_mask_builder
+            .add_render_feature_flag::<MeshBasicWireframeRenderFeatureFlag>();
+    }
+
+    if !render_options.enable_lighting {
+        feature_flag_mask_builder =
+            feature_flag_mask_builder.add_render_feature_flag::<MeshBasicUnlitRenderFeatureFlag>();
+    }
+
+    if !render_options.enable_textures {
+        feature_flag_mask_builder = feature_flag_mask_builder
+            .add_render_feature_flag::<MeshBasicUntexturedRenderFeatureFlag>();
+    }
+
+    if !render_options.show_shadows {
+        feature_flag_mask_builder = feature_flag_mask_builder
+            .add_render_feature_flag::<MeshBasicNoShadowsRenderFeatureFlag>();
+    }
+
+    let aspect_ratio = viewports_resource.main_window_size.width as f32
+        / viewports_resource.main_window_size.height as f32;
+
+    let view = glam::Mat4::look_at_rh(eye, look_at, up);
+
+    let fov_y_radians = std::f32::consts::FRAC_PI_4;
+    let near_plane = 0.01;
+
+    let projection = Projection::Perspective(PerspectiveParameters::new(
+        fov_y_radians,
+        aspect_ratio,
+        near_plane,
+        10000.,
+        DepthRange::InfiniteReverse,
+    ));
+
+    main_view_frustum
+        .set_projection(&projection)
+        .set_transform(eye, look_at, up);
+
+    viewports_resource.main_view_meta = Some(RenderViewMeta {
+        view_frustum: main_view_frustum.clone(),
+        eye_position: eye,
+        view,
+        proj: projection.as_rh_mat4(),
+        depth_range: RenderViewDepthRange::from_projection(&projection),
+        render_phase_mask: phase_mask_builder.build(),
+        render_feature_mask: feature_mask_builder.build(),
+        render_feature_flag_mask: feature_flag_mask_builder.build(),
+        debug_name: "main".to_string(),
+    });
+}
+
 pub(super) fn add_light_debug_draw(resources: &Resources, world: &World) {
     let mut debug_draw = resources.get_mut::<Debug3DResource>().unwrap();
 
@@ -331,8 +384,8 @@ pub(super) fn add_directional_light(
     _resources: &Resources,
     world: &mut World,
     light_component: DirectionalLightComponent,
-) {
-    world.extend(vec![(light_component,)]);
+) -> Entity {
+    world.push((light_component,))
 }
 
 pub(super) fn add_spot_light(
@@ -340,13 +393,13 @@ pub(super) fn add_spot_light(
     world: &mut World,
     position: glam::Vec3,
     light_component: SpotLightComponent,
-) {
+) -> Entity {
     let position_component = TransformComponent {
         translation: position,
         ..Default::default()
     };
 
-    world.extend(vec![(position_component, light_component)]);
+    world.push((position_component, light_component))
 }
 
 pub(super) fn add_point_light(
@@ -354,11 +407,216 @@ pub(super) fn add_point_light(
     world: &mut World,
     position: glam::Vec3,
     light_component: PointLightComponent,
-) {
+) -> Entity {
     let position_component = TransformComponent {
         translation: position,
         ..Default::default()
     };
 
-    world.extend(vec![(position_component, light_component)]);
+    world.push((position_component, light_component))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{
+        ButtonState, GamepadAxis, GamepadEvent, GamepadId, InputBindings, InputEvent, KeyboardKey,
+        PhysicalKey,
+    };
+    use crate::time::{ClockResource, ManualClock};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    const PAD: GamepadId = GamepadId(0);
+
+    // Input with the shipped bindings and a connected gamepad
+    fn input_resource() -> InputResource {
+        let mut input_resource = InputResource::new();
+        input_resource.set_bindings(
+            InputBindings::from_ron_str(include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../config/input_bindings.ron"
+            )))
+            .unwrap(),
+        );
+        input_resource.handle_event_at(
+            &InputEvent::Gamepad(GamepadEvent::Connected(PAD)),
+            Instant::now(),
+        );
+        input_resource.end_frame();
+        input_resource
+    }
+
+    fn move_stick(
+        input_resource: &mut InputResource,
+        axis: GamepadAxis,
+        value: f32,
+    ) {
+        input_resource.handle_event_at(
+            &InputEvent::Gamepad(GamepadEvent::Axis {
+                id: PAD,
+                axis,
+                value,
+            }),
+            Instant::now(),
+        );
+    }
+
+    fn press_key(
+        input_resource: &mut InputResource,
+        key: KeyboardKey,
+    ) {
+        for state in [ButtonState::Pressed, ButtonState::Released].iter() {
+            input_resource
+                .handle_event_at(&InputEvent::Keyboard { key, state: *state }, Instant::now());
+        }
+    }
+
+    // A TimeState that has just finished a 100ms frame
+    fn time_state() -> TimeState {
+        let manual_clock = Arc::new(ManualClock::new());
+        let mut time_state = TimeState::new(ClockResource::new(manual_clock.clone()));
+        manual_clock.advance(Duration::from_millis(100));
+        time_state.update();
+        time_state
+    }
+
+    fn update_frame(
+        camera: &mut FlyCamera,
+        input_resource: &mut InputResource,
+        cursor: &mut CursorResource,
+        time_state: &TimeState,
+    ) {
+        input_resource.update_axes(time_state.previous_update_dt());
+        camera.update(input_resource, cursor, time_state);
+        input_resource.end_frame();
+    }
+
+    #[test]
+    fn gamepad_sticks_move_and_turn_the_camera() {
+        let mut input_resource = input_resource();
+        let mut cursor = CursorResource::new();
+        let time_state = time_state();
+        let mut camera = FlyCamera {
+            lock_view: false,
+            ..Default::default()
+        };
+
+        move_stick(&mut input_resource, GamepadAxis::LeftStickY, 1.0);
+        move_stick(&mut input_resource, GamepadAxis::RightStickX, 1.0);
+        move_stick(&mut input_resource, GamepadAxis::RightStickY, 1.0);
+        update_frame(&mut camera, &mut input_resource, &mut cursor, &time_state);
+
+        // Stick right turns right (negative yaw), stick up looks up
+        assert!(camera.yaw < 0.0, "yaw {}", camera.yaw);
+        assert!(camera.pitch > 0.0, "pitch {}", camera.pitch);
+        // Full stick forward covers the move speed in the 100ms frame
+        assert!((camera.position - camera.look_dir).length() < 1e-4);
+    }
+
+    #[test]
+    fn gamepad_dead_zone_keeps_the_camera_still() {
+        let mut input_resource = input_resource();
+        let mut cursor = CursorResource::new();
+        let time_state = time_state();
+        let mut camera = FlyCamera {
+            lock_view: false,
+            ..Default::default()
+        };
+
+        let dead_zone = input_resource.gamepads().dead_zones().left_stick;
+        move_stick(
+            &mut input_resource,
+            GamepadAxis::LeftStickY,
+            dead_zone * 0.5,
+        );
+        move_stick(
+            &mut input_resource,
+            GamepadAxis::RightStickX,
+            dead_zone * 0.5,
+        );
+        update_frame(&mut camera, &mut input_resource, &mut cursor, &time_state);
+
+        assert_eq!(camera.position, glam::Vec3::ZERO);
+        assert_eq!(camera.yaw, 0.0);
+    }
+
+    #[test]
+    fn locked_view_ignores_input() {
+        let mut input_resource = input_resource();
+        let mut cursor = CursorResource::new();
+        let time_state = time_state();
+        let mut camera = FlyCamera {
+            lock_view: true,
+            ..Default::default()
+        };
+
+        move_stick(&mut input_resource, GamepadAxis::LeftStickY, 1.0);
+        move_stick(&mut input_resource, GamepadAxis::RightStickX, 1.0);
+        update_frame(&mut camera, &mut input_resource, &mut cursor, &time_state);
+
+        assert_eq!(camera.position, glam::Vec3::ZERO);
+        assert_eq!(camera.yaw, 0.0);
+    }
+
+    #[test]
+    fn toggling_the_view_lock_sets_the_cursor_mode() {
+        let mut input_resource = input_resource();
+        let mut cursor = CursorResource::new();
+        let time_state = time_state();
+        let mut camera = FlyCamera {
+            lock_view: true,
+            ..Default::default()
+        };
+
+        update_frame(&mut camera, &mut input_resource, &mut cursor, &time_state);
+        assert_eq!(cursor.mode(), CursorMode::Free);
+
+        press_key(&mut input_resource, KeyboardKey::F);
+        update_frame(&mut camera, &mut input_resource, &mut cursor, &time_state);
+        assert!(!camera.lock_view);
+        assert_eq!(cursor.mode(), CursorMode::Locked);
+        assert_eq!(cursor.take_pending_change(), Some(CursorMode::Locked));
+
+        press_key(&mut input_resource, KeyboardKey::F);
+        update_frame(&mut camera, &mut input_resource, &mut cursor, &time_state);
+        assert!(camera.lock_view);
+        assert_eq!(cursor.mode(), CursorMode::Free);
+        assert_eq!(cursor.take_pending_change(), Some(CursorMode::Free));
+    }
+
+    #[test]
+    fn mouse_look_and_keyboard_movement_use_axis_settings() {
+        let mut input_resource = input_resource();
+        let mut cursor = CursorResource::new();
+        let time_state = time_state();
+        let mut camera = FlyCamera {
+            lock_view: false,
+            ..Default::default()
+        };
+
+        // Let the smoothed axes settle at rest first
+        update_frame(&mut camera, &mut input_resource, &mut cursor, &time_state);
+
+        // look_x and look_y are scaled by 0.1 and inverted
+        input_resource.handle_event_at(
+            &InputEvent::MouseMotion(glam::Vec2::new(10.0, -5.0)),
+            Instant::now(),
+        );
+        input_resource.handle_event_at(
+            &InputEvent::PhysicalKey {
+                key: PhysicalKey::KeyW,
+                state: ButtonState::Pressed,
+            },
+            Instant::now(),
+        );
+        update_frame(&mut camera, &mut input_resource, &mut cursor, &time_state);
+
+        assert!((camera.yaw + 0.1).abs() < 1e-5, "yaw {}", camera.yaw);
+        assert!((camera.pitch - 0.05).abs() < 1e-5, "pitch {}", camera.pitch);
+
+        // move_forward is smoothed over 0.05s, so after 0.1s it has covered 1 - e^-2 of the way
+        let expected_distance = 1.0 - (-2.0f32).exp();
+        assert!((camera.position.length() - expected_distance).abs() < 1e-4);
+    }
 }
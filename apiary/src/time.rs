@@ -1,28 +1,304 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+/// A source of the current time. Everything time-dependent should read time through a
+/// `ClockResource` rather than calling `Instant::now()` so that a `ManualClock` can be swapped in
+/// to make it deterministic.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// Block until `deadline`. Implementations that really wait should sleep until
+    /// `spin_threshold` before the deadline and busy-wait the rest, for precision.
+    fn wait_until(
+        &self,
+        deadline: Instant,
+        spin_threshold: Duration,
+    );
+}
+
+/// The system clock
+pub struct RealClock;
+
+impl Clock for RealClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wait_until(
+        &self,
+        deadline: Instant,
+        spin_threshold: Duration,
+    ) {
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+
+            let remaining = deadline - now;
+            if remaining > spin_threshold {
+                std::thread::sleep(remaining - spin_threshold);
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+    }
+}
+
+/// A clock that only moves when told to. Waiting on it jumps straight to the deadline.
+pub struct ManualClock {
+    now: Mutex<Instant>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock {
+            now: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn advance(
+        &self,
+        duration: Duration,
+    ) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    pub fn set(
+        &self,
+        now: Instant,
+    ) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    fn wait_until(
+        &self,
+        deadline: Instant,
+        _spin_threshold: Duration,
+    ) {
+        let mut now = self.now.lock().unwrap();
+        if *now < deadline {
+            *now = deadline;
+        }
+    }
+}
+
+/// The clock used by the app, shared through resources. Cloning shares the same clock.
+#[derive(Clone)]
+pub struct ClockResource {
+    clock: Arc<dyn Clock>,
+}
+
+impl ClockResource {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        ClockResource { clock }
+    }
+
+    pub fn real() -> Self {
+        Self::new(Arc::new(RealClock))
+    }
+
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    pub fn wait_until(
+        &self,
+        deadline: Instant,
+        spin_threshold: Duration,
+    ) {
+        self.clock.wait_until(deadline, spin_threshold);
+    }
+}
+
+/// The accessors on `TimeState` itself report real time, which is never paused or scaled. Other
+/// time contexts can be paused and scaled independently, for example so gameplay can freeze while
+/// UI keeps animating.
 #[derive(Clone)]
 pub struct TimeState {
+    clock: ClockResource,
     app_start: Instant,
     previous_update: Instant,
     app_time_context: TimeContext,
+    time_contexts: HashMap<String, TimeContext>,
+    fixed_timestep: FixedTimestep,
+    frame_time_history: FrameTimeHistory,
 }
 
 impl TimeState {
-    pub fn new() -> TimeState {
-        let now = Instant::now();
+    /// Name of the real time context, for APIs that take a time context by name
+    pub const APP_TIME_CONTEXT: &'static str = "app";
+
+    /// Time context that drives gameplay and the fixed timestep. Always exists.
+    pub const GAME_TIME_CONTEXT: &'static str = "game";
+
+    pub fn new(clock: ClockResource) -> TimeState {
+        let now = clock.now();
+
+        let mut time_contexts = HashMap::default();
+        time_contexts.insert(Self::GAME_TIME_CONTEXT.to_string(), TimeContext::new(now));
 
         TimeState {
+            clock,
             app_start: now,
             previous_update: now,
-            app_time_context: TimeContext::new(),
+            app_time_context: TimeContext::new(now),
+            time_contexts,
+            fixed_timestep: FixedTimestep::default(),
+            frame_time_history: FrameTimeHistory::default(),
         }
     }
 
+    /// The clock this reads time from
+    pub fn clock(&self) -> &ClockResource {
+        &self.clock
+    }
+
     pub fn update(&mut self) {
-        let now = Instant::now();
+        let now = self.clock.now();
         let elapsed = now - self.previous_update;
         self.previous_update = now;
+        self.update_contexts(elapsed);
+    }
+
+    /// Advance time by the given amount instead of measuring it. Used when replaying recorded
+    /// input so that each frame sees the same dt it did when it was recorded.
+    pub fn update_with_elapsed(
+        &mut self,
+        elapsed: Duration,
+    ) {
+        self.previous_update = self.clock.now();
+        self.update_contexts(elapsed);
+    }
+
+    fn update_contexts(
+        &mut self,
+        elapsed: Duration,
+    ) {
+        self.frame_time_history.push(elapsed);
         self.app_time_context.update(elapsed);
+        for time_context in self.time_contexts.values_mut() {
+            time_context.update(elapsed);
+        }
+
+        // Fixed ticks follow game time so they stop while the game is paused
+        let game_elapsed = self.game_time_context().previous_update_time();
+        self.fixed_timestep.update(game_elapsed);
+    }
+
+    /// Durations of recent frames in real time
+    pub fn frame_time_history(&self) -> &FrameTimeHistory {
+        &self.frame_time_history
+    }
+
+    /// Use to change the hitch budget
+    pub fn frame_time_history_mut(&mut self) -> &mut FrameTimeHistory {
+        &mut self.frame_time_history
+    }
+
+    /// Real time, never paused or scaled
+    pub fn app_time_context(&self) -> &TimeContext {
+        &self.app_time_context
+    }
+
+    pub fn game_time_context(&self) -> &TimeContext {
+        &self.time_contexts[Self::GAME_TIME_CONTEXT]
+    }
+
+    pub fn game_time_context_mut(&mut self) -> &mut TimeContext {
+        self.time_contexts.get_mut(Self::GAME_TIME_CONTEXT).unwrap()
+    }
+
+    /// Add a time context that starts advancing on the next update. Replaces any existing context
+    /// with the same name. Real time can't be replaced, so this returns None for `APP_TIME_CONTEXT`
+    pub fn add_time_context(
+        &mut self,
+        name: &str,
+    ) -> Option<&mut TimeContext> {
+        if name == Self::APP_TIME_CONTEXT {
+            return None;
+        }
+
+        self.time_contexts
+            .insert(name.to_string(), TimeContext::new(self.clock.now()));
+        self.time_contexts.get_mut(name)
+    }
+
+    /// Remove the named time context. The game time context can't be removed.
+    pub fn remove_time_context(
+        &mut self,
+        name: &str,
+    ) -> Option<TimeContext> {
+        if name == Self::GAME_TIME_CONTEXT {
+            None
+        } else {
+            self.time_contexts.remove(name)
+        }
+    }
+
+    /// Look up a time context by name, `APP_TIME_CONTEXT` gives real time
+    pub fn time_context(
+        &self,
+        name: &str,
+    ) -> Option<&TimeContext> {
+        if name == Self::APP_TIME_CONTEXT {
+            Some(&self.app_time_context)
+        } else {
+            self.time_contexts.get(name)
+        }
+    }
+
+    /// Real time can't be modified, so this returns None for `APP_TIME_CONTEXT`
+    pub fn time_context_mut(
+        &mut self,
+        name: &str,
+    ) -> Option<&mut TimeContext> {
+        self.time_contexts.get_mut(name)
+    }
+
+    /// Names and states of all time contexts other than real time
+    pub fn time_contexts(&self) -> impl Iterator<Item = (&str, &TimeContext)> {
+        self.time_contexts
+            .iter()
+            .map(|(name, time_context)| (name.as_str(), time_context))
+    }
+
+    pub fn fixed_timestep(&self) -> &FixedTimestep {
+        &self.fixed_timestep
+    }
+
+    /// Use to change the tick rate or catch-up limit
+    pub fn fixed_timestep_mut(&mut self) -> &mut FixedTimestep {
+        &mut self.fixed_timestep
+    }
+
+    /// Number of fixed ticks to run during this update
+    pub fn fixed_ticks_this_update(&self) -> u32 {
+        self.fixed_timestep.ticks_this_update()
+    }
+
+    /// Fixed tick duration in f32 seconds
+    pub fn fixed_dt(&self) -> f32 {
+        self.fixed_timestep.tick_dt()
+    }
+
+    /// How far between the previous and next fixed tick this update is, 0.0..1.0. Use to
+    /// interpolate state simulated in fixed ticks when rendering.
+    pub fn interpolation_alpha(&self) -> f32 {
+        self.fixed_timestep.interpolation_alpha()
     }
 
     pub fn current_instant(&self) -> Instant {
@@ -60,6 +336,9 @@ impl TimeState {
 /// possible to track a separate "context" of time, for example "unpaused" time in a game
 #[derive(Copy, Clone)]
 pub struct TimeContext {
+    paused: bool,
+    time_scale: f32,
+    pending_step: Option<Duration>,
     total_time: Duration,
     current_instant: Instant,
     previous_update_time: Duration,
@@ -70,12 +349,13 @@ pub struct TimeContext {
 }
 
 impl TimeContext {
-    /// Create a new TimeState. Default is not allowed because the current time affects the object
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> Self {
-        let now_instant = Instant::now();
+    /// Create a new TimeContext starting at the given time, normally `ClockResource::now`
+    pub fn new(now_instant: Instant) -> Self {
         let zero_duration = Duration::from_secs(0);
         TimeContext {
+            paused: false,
+            time_scale: 1.0,
+            pending_step: None,
             total_time: zero_duration,
             current_instant: now_instant,
             previous_update_time: zero_duration,
@@ -86,8 +366,18 @@ impl TimeContext {
         }
     }
 
-    /// Call to capture time passing and update values
-    pub fn update(&mut self, elapsed: Duration) {
+    /// Call to capture time passing and update values. `real_elapsed` is scaled by the time scale,
+    /// or replaced by a requested step (or zero) while paused.
+    pub fn update(
+        &mut self,
+        real_elapsed: Duration,
+    ) {
+        let elapsed = if self.paused {
+            self.pending_step.take().unwrap_or_default()
+        } else {
+            real_elapsed.mul_f32(self.time_scale)
+        };
+
         self.total_time += elapsed;
         self.current_instant += elapsed;
         self.previous_update_time = elapsed;
@@ -99,7 +389,7 @@ impl TimeContext {
 
         let fps = if dt > 0.0 { 1.0 / dt } else { 0.0 };
 
-        //TODO: Replace with a circular buffer
+        // Cheap running estimate, `TimeState::frame_time_history` keeps the full distribution
         const SMOOTHING_FACTOR: f32 = 0.95;
         self.updates_per_second = fps;
         self.updates_per_second_smoothed = (self.updates_per_second_smoothed * SMOOTHING_FACTOR)
@@ -108,6 +398,45 @@ impl TimeContext {
         self.update_count += 1;
     }
 
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(
+        &mut self,
+        paused: bool,
+    ) {
+        self.paused = paused;
+        self.pending_step = None;
+    }
+
+    pub fn toggle_paused(&mut self) {
+        self.set_paused(!self.paused);
+    }
+
+    /// Multiplier applied to real time, e.g. 0.5 for slow motion or 2.0 for fast forward
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    pub fn set_time_scale(
+        &mut self,
+        time_scale: f32,
+    ) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    /// While paused, advance by the given duration on the next update only. Has no effect when
+    /// not paused.
+    pub fn request_step(
+        &mut self,
+        duration: Duration,
+    ) {
+        if self.paused {
+            self.pending_step = Some(duration);
+        }
+    }
+
     /// Duration of time passed in this time context
     pub fn total_time(&self) -> Duration {
         self.total_time
@@ -145,6 +474,259 @@ impl TimeContext {
     }
 }
 
+/// Summary of the frames currently held in a `FrameTimeHistory`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FrameTimeStats {
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    /// Frames that took longer than the budget
+    pub hitch_count: usize,
+}
+
+/// Fixed-size ring buffer of recent frame durations
+#[derive(Clone)]
+pub struct FrameTimeHistory {
+    frame_times: Vec<Duration>,
+    // Index the next frame time will be written to once the buffer is full
+    next_index: usize,
+    capacity: usize,
+    budget: Duration,
+    total_hitch_count: u64,
+}
+
+impl FrameTimeHistory {
+    pub const DEFAULT_CAPACITY: usize = 240;
+
+    pub fn new(
+        capacity: usize,
+        budget: Duration,
+    ) -> Self {
+        assert!(capacity > 0);
+        FrameTimeHistory {
+            frame_times: Vec::with_capacity(capacity),
+            next_index: 0,
+            capacity,
+            budget,
+            total_hitch_count: 0,
+        }
+    }
+
+    pub fn push(
+        &mut self,
+        frame_time: Duration,
+    ) {
+        if frame_time > self.budget {
+            self.total_hitch_count += 1;
+        }
+
+        if self.frame_times.len() < self.capacity {
+            self.frame_times.push(frame_time);
+        } else {
+            self.frame_times[self.next_index] = frame_time;
+            self.next_index = (self.next_index + 1) % self.capacity;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frame_times.clear();
+        self.next_index = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.frame_times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frame_times.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Frames longer than this are counted as hitches
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    pub fn set_budget(
+        &mut self,
+        budget: Duration,
+    ) {
+        self.budget = budget;
+    }
+
+    /// Hitches since startup, including frames no longer in the history
+    pub fn total_hitch_count(&self) -> u64 {
+        self.total_hitch_count
+    }
+
+    /// Frame times from oldest to newest
+    pub fn iter(&self) -> impl Iterator<Item = Duration> + '_ {
+        let (newest, oldest) = self.frame_times.split_at(self.next_index);
+        oldest.iter().chain(newest.iter()).copied()
+    }
+
+    /// Statistics over the frames in the history, None if it is empty
+    pub fn stats(&self) -> Option<FrameTimeStats> {
+        if self.frame_times.is_empty() {
+            return None;
+        }
+
+        let mut sorted = self.frame_times.clone();
+        sorted.sort_unstable();
+
+        let percentile = |p: f32| {
+            let index = ((sorted.len() - 1) as f32 * p).round() as usize;
+            sorted[index]
+        };
+
+        let total: Duration = sorted.iter().sum();
+        Some(FrameTimeStats {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: total / sorted.len() as u32,
+            p50: percentile(0.50),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            hitch_count: sorted.iter().filter(|t| **t > self.budget).count(),
+        })
+    }
+}
+
+impl Default for FrameTimeHistory {
+    /// Budget defaults to 60 FPS
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY, Duration::from_secs(1) / 60)
+    }
+}
+
+/// Splits variable frame times into a whole number of fixed-length ticks so that simulation is
+/// independent of frame rate. Time that doesn't add up to a full tick carries over to the next
+/// update.
+#[derive(Copy, Clone)]
+pub struct FixedTimestep {
+    tick_duration: Duration,
+    max_ticks_per_update: u32,
+    accumulator: Duration,
+    ticks_this_update: u32,
+    tick_count: u64,
+    dropped_tick_count: u64,
+}
+
+impl FixedTimestep {
+    pub const DEFAULT_TICKS_PER_SECOND: f32 = 60.0;
+    pub const DEFAULT_MAX_TICKS_PER_UPDATE: u32 = 8;
+
+    pub fn new(ticks_per_second: f32) -> Self {
+        FixedTimestep {
+            tick_duration: Self::ticks_per_second_to_duration(ticks_per_second),
+            max_ticks_per_update: Self::DEFAULT_MAX_TICKS_PER_UPDATE,
+            accumulator: Duration::default(),
+            ticks_this_update: 0,
+            tick_count: 0,
+            dropped_tick_count: 0,
+        }
+    }
+
+    /// Add elapsed time and work out how many ticks should run during this update. If more than
+    /// `max_ticks_per_update` ticks are owed, for example after a long hitch, the extra time is
+    /// dropped rather than trying to catch up over many frames.
+    pub fn update(
+        &mut self,
+        elapsed: Duration,
+    ) {
+        self.accumulator += elapsed;
+
+        // Drop whole ticks beyond the limit up front so a long hitch doesn't spin here, keeping
+        // the fraction of a tick so the interpolation alpha stays continuous
+        let max_accumulated = self.tick_duration * self.max_ticks_per_update;
+        if self.accumulator >= max_accumulated + self.tick_duration {
+            let excess_nanos = (self.accumulator - max_accumulated).as_nanos();
+            let tick_nanos = self.tick_duration.as_nanos();
+            self.dropped_tick_count += (excess_nanos / tick_nanos) as u64;
+            self.accumulator =
+                max_accumulated + Duration::from_nanos((excess_nanos % tick_nanos) as u64);
+        }
+
+        let mut ticks = 0;
+        while self.accumulator >= self.tick_duration {
+            self.accumulator -= self.tick_duration;
+            ticks += 1;
+        }
+
+        self.ticks_this_update = ticks;
+        self.tick_count += ticks as u64;
+    }
+
+    pub fn ticks_per_second(&self) -> f32 {
+        1.0 / self.tick_duration.as_secs_f32()
+    }
+
+    pub fn set_ticks_per_second(
+        &mut self,
+        ticks_per_second: f32,
+    ) {
+        self.tick_duration = Self::ticks_per_second_to_duration(ticks_per_second);
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        self.tick_duration
+    }
+
+    /// Tick duration in f32 seconds
+    pub fn tick_dt(&self) -> f32 {
+        self.tick_duration.as_secs_f32()
+    }
+
+    pub fn max_ticks_per_update(&self) -> u32 {
+        self.max_ticks_per_update
+    }
+
+    /// Limit how many ticks can run in a single update. Must be at least 1.
+    pub fn set_max_ticks_per_update(
+        &mut self,
+        max_ticks_per_update: u32,
+    ) {
+        self.max_ticks_per_update = max_ticks_per_update.max(1);
+    }
+
+    /// Number of ticks to run during this update
+    pub fn ticks_this_update(&self) -> u32 {
+        self.ticks_this_update
+    }
+
+    /// Total number of ticks run, including the ones for this update
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    /// Total number of ticks skipped because of the catch-up limit
+    pub fn dropped_tick_count(&self) -> u64 {
+        self.dropped_tick_count
+    }
+
+    /// Fraction of a tick left in the accumulator, 0.0..1.0
+    pub fn interpolation_alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.tick_duration.as_secs_f32()
+    }
+
+    fn ticks_per_second_to_duration(ticks_per_second: f32) -> Duration {
+        assert!(ticks_per_second > 0.0);
+        Duration::from_secs_f32(1.0 / ticks_per_second)
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_TICKS_PER_SECOND)
+    }
+}
+
 /// Useful for cases where you want to do something once per time interval.
 #[derive(Default)]
 pub struct PeriodicEvent {
@@ -153,7 +735,8 @@ pub struct PeriodicEvent {
 
 impl PeriodicEvent {
     /// Call try_take_event to see if the required time has elapsed. It will return true only once
-    /// enough time has passed since it last returned true.
+    /// enough time has passed since it last returned true. `current_time` should come from the
+    /// `ClockResource` (or a time context) rather than `Instant::now()`.
     pub fn try_take_event(&mut self, current_time: Instant, wait_duration: Duration) -> bool {
         match self.last_time_triggered {
             None => {
@@ -171,3 +754,211 @@ impl PeriodicEvent {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(
+        actual: f32,
+        expected: f32,
+    ) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn fixed_timestep_carries_partial_ticks() {
+        let mut fixed_timestep = FixedTimestep::new(4.0);
+
+        fixed_timestep.update(Duration::from_millis(600));
+        assert_eq!(fixed_timestep.ticks_this_update(), 2);
+        assert_near(fixed_timestep.interpolation_alpha(), 0.4);
+
+        fixed_timestep.update(Duration::from_millis(100));
+        assert_eq!(fixed_timestep.ticks_this_update(), 0);
+        assert_near(fixed_timestep.interpolation_alpha(), 0.8);
+
+        fixed_timestep.update(Duration::from_millis(100));
+        assert_eq!(fixed_timestep.ticks_this_update(), 1);
+        assert_near(fixed_timestep.interpolation_alpha(), 0.2);
+        assert_eq!(fixed_timestep.tick_count(), 3);
+        assert_eq!(fixed_timestep.dropped_tick_count(), 0);
+    }
+
+    #[test]
+    fn fixed_timestep_drops_ticks_beyond_the_limit() {
+        let mut fixed_timestep = FixedTimestep::new(4.0);
+        fixed_timestep.set_max_ticks_per_update(3);
+
+        // 40 ticks and a bit owed after a hitch
+        fixed_timestep.update(Duration::from_millis(10_100));
+        assert_eq!(fixed_timestep.ticks_this_update(), 3);
+        assert_eq!(fixed_timestep.dropped_tick_count(), 37);
+        assert_near(fixed_timestep.interpolation_alpha(), 0.4);
+
+        // Nothing is carried over to catch up on later
+        fixed_timestep.update(Duration::default());
+        assert_eq!(fixed_timestep.ticks_this_update(), 0);
+        assert_eq!(fixed_timestep.tick_count(), 3);
+
+        // Exactly the limit is not dropped
+        fixed_timestep.update(Duration::from_millis(650));
+        assert_eq!(fixed_timestep.ticks_this_update(), 3);
+        assert_eq!(fixed_timestep.dropped_tick_count(), 37);
+        assert_near(fixed_timestep.interpolation_alpha(), 0.0);
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn frame_time_history_wraps_around() {
+        let mut history = FrameTimeHistory::new(3, ms(20));
+        for millis in 1..=2 {
+            history.push(ms(millis));
+        }
+        assert_eq!(history.iter().collect::<Vec<_>>(), vec![ms(1), ms(2)]);
+
+        for millis in 3..=5 {
+            history.push(ms(millis));
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(
+            history.iter().collect::<Vec<_>>(),
+            vec![ms(3), ms(4), ms(5)]
+        );
+
+        history.push(ms(30));
+        assert_eq!(
+            history.iter().collect::<Vec<_>>(),
+            vec![ms(4), ms(5), ms(30)]
+        );
+        assert_eq!(history.total_hitch_count(), 1);
+
+        history.clear();
+        assert!(history.is_empty());
+        assert!(history.stats().is_none());
+        history.push(ms(7));
+        assert_eq!(history.iter().collect::<Vec<_>>(), vec![ms(7)]);
+    }
+
+    #[test]
+    fn frame_time_stats_percentiles() {
+        let mut history = FrameTimeHistory::new(100, ms(95));
+        // Pushed out of order, and wrapped so the oldest frames are dropped
+        for millis in (1..=100).rev() {
+            history.push(ms(millis));
+        }
+        history.push(ms(200));
+
+        let stats = history.stats().unwrap();
+        assert_eq!(stats.min, ms(1));
+        assert_eq!(stats.max, ms(200));
+        assert_eq!(stats.p50, ms(51));
+        assert_eq!(stats.p95, ms(95));
+        assert_eq!(stats.p99, ms(99));
+        // 99 + ... + 1 + 200
+        assert_eq!(stats.mean, ms(5150) / 100);
+        assert_eq!(stats.hitch_count, 5);
+        assert_eq!(history.total_hitch_count(), 6);
+    }
+
+    #[test]
+    fn time_state_reads_the_clock() {
+        let manual_clock = Arc::new(ManualClock::new());
+        let clock = ClockResource::new(manual_clock.clone());
+        let start = clock.now();
+        let mut time_state = TimeState::new(clock);
+
+        manual_clock.advance(ms(250));
+        time_state.update();
+        assert_eq!(time_state.previous_update_time(), ms(250));
+        assert_near(time_state.previous_update_dt(), 0.25);
+        assert_near(time_state.updates_per_second(), 4.0);
+        assert_eq!(time_state.current_instant(), start + ms(250));
+
+        manual_clock.advance(ms(500));
+        time_state.update();
+        assert_eq!(time_state.total_time(), ms(750));
+        assert_eq!(time_state.update_count(), 2);
+        assert_eq!(
+            time_state.frame_time_history().iter().collect::<Vec<_>>(),
+            vec![ms(250), ms(500)]
+        );
+
+        // Replayed frames use the recorded elapsed time instead of the clock
+        manual_clock.advance(ms(10));
+        time_state.update_with_elapsed(ms(100));
+        assert_eq!(time_state.previous_update_time(), ms(100));
+        manual_clock.advance(ms(50));
+        time_state.update();
+        assert_eq!(time_state.previous_update_time(), ms(50));
+    }
+
+    #[test]
+    fn game_time_pauses_scales_and_steps() {
+        let manual_clock = Arc::new(ManualClock::new());
+        let mut time_state = TimeState::new(ClockResource::new(manual_clock.clone()));
+        time_state.fixed_timestep_mut().set_ticks_per_second(4.0);
+
+        time_state.game_time_context_mut().set_time_scale(0.5);
+        manual_clock.advance(ms(1000));
+        time_state.update();
+        assert_eq!(time_state.total_time(), ms(1000));
+        assert_eq!(time_state.game_time_context().total_time(), ms(500));
+        assert_eq!(time_state.fixed_ticks_this_update(), 2);
+
+        time_state.game_time_context_mut().set_paused(true);
+        manual_clock.advance(ms(1000));
+        time_state.update();
+        assert_eq!(time_state.game_time_context().total_time(), ms(500));
+        assert_eq!(time_state.fixed_ticks_this_update(), 0);
+
+        time_state.game_time_context_mut().request_step(ms(250));
+        manual_clock.advance(ms(1000));
+        time_state.update();
+        assert_eq!(time_state.game_time_context().total_time(), ms(750));
+        assert_eq!(time_state.fixed_ticks_this_update(), 1);
+
+        manual_clock.advance(ms(1000));
+        time_state.update();
+        assert_eq!(time_state.game_time_context().total_time(), ms(750));
+        assert_eq!(time_state.total_time(), ms(4000));
+    }
+
+    #[test]
+    fn named_time_contexts_exclude_real_time() {
+        let manual_clock = Arc::new(ManualClock::new());
+        let mut time_state = TimeState::new(ClockResource::new(manual_clock.clone()));
+
+        assert!(time_state
+            .add_time_context(TimeState::APP_TIME_CONTEXT)
+            .is_none());
+        assert!(time_state
+            .time_contexts()
+            .all(|(name, _)| name != TimeState::APP_TIME_CONTEXT));
+
+        time_state.add_time_context("ui").unwrap().set_paused(true);
+        manual_clock.advance(ms(100));
+        time_state.update();
+        assert_eq!(time_state.time_context("ui").unwrap().total_time(), ms(0));
+        assert_eq!(
+            time_state
+                .time_context(TimeState::APP_TIME_CONTEXT)
+                .unwrap()
+                .total_time(),
+            ms(100)
+        );
+
+        assert!(time_state
+            .remove_time_context(TimeState::GAME_TIME_CONTEXT)
+            .is_none());
+        assert!(time_state.remove_time_context("ui").is_some());
+    }
+}
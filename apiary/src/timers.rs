@@ -0,0 +1,336 @@
+use std::time::Duration;
+
+use crate::time::TimeState;
+
+/// Identifies a timer so it can be cancelled or matched against fired events
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TimerHandle(u64);
+
+/// Produced when a timer fires. A repeating timer that is due several times in one update, for
+/// example after a hitch, produces a single event with `count` set rather than one per interval.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimerEvent {
+    pub handle: TimerHandle,
+    pub name: String,
+    /// 1 the first time a timer fires, counting up for repeating timers. Includes `count`.
+    pub fire_count: u64,
+    /// Number of intervals that elapsed during this update, at least 1
+    pub count: u64,
+}
+
+struct Timer {
+    handle: TimerHandle,
+    name: String,
+    time_context: String,
+    // In the bound time context's total time
+    fire_at: Duration,
+    interval: Option<Duration>,
+    fire_count: u64,
+}
+
+/// One-shot and repeating timers, each measured in a time context so that timers on game time
+/// stop while the game is paused. Call `update` once per frame after `TimeState` is updated, then
+/// read `fired_events` (scenes also receive them through `Scene::timers_fired`).
+#[derive(Default)]
+pub struct TimerResource {
+    next_handle: u64,
+    timers: Vec<Timer>,
+    fired_events: Vec<TimerEvent>,
+}
+
+impl TimerResource {
+    /// Repeating timers can't fire more often than this, so a tiny interval can't stall a frame
+    pub const MIN_REPEAT_INTERVAL: Duration = Duration::from_millis(1);
+
+    /// Fire once after `delay` has passed in the given time context
+    pub fn start_one_shot(
+        &mut self,
+        time_state: &TimeState,
+        name: &str,
+        time_context: &str,
+        delay: Duration,
+    ) -> TimerHandle {
+        self.start(time_state, name, time_context, delay, None)
+    }
+
+    /// Fire every `interval` in the given time context until cancelled. If a frame covers several
+    /// intervals the timer fires once, with the number of intervals in `TimerEvent::count`.
+    pub fn start_repeating(
+        &mut self,
+        time_state: &TimeState,
+        name: &str,
+        time_context: &str,
+        interval: Duration,
+    ) -> TimerHandle {
+        let interval = interval.max(Self::MIN_REPEAT_INTERVAL);
+        self.start(time_state, name, time_context, interval, Some(interval))
+    }
+
+    /// Stop a timer. Returns false if it already finished or was cancelled.
+    pub fn cancel(
+        &mut self,
+        handle: TimerHandle,
+    ) -> bool {
+        let timer_count = self.timers.len();
+        self.timers.retain(|timer| timer.handle != handle);
+        self.timers.len() != timer_count
+    }
+
+    /// Stop every timer with the given name
+    pub fn cancel_named(
+        &mut self,
+        name: &str,
+    ) {
+        self.timers.retain(|timer| timer.name != name);
+    }
+
+    /// Returns true if the timer has not finished or been cancelled
+    pub fn is_active(
+        &self,
+        handle: TimerHandle,
+    ) -> bool {
+        self.timers.iter().any(|timer| timer.handle == handle)
+    }
+
+    /// Time left until the timer next fires, None if it isn't active
+    pub fn remaining(
+        &self,
+        time_state: &TimeState,
+        handle: TimerHandle,
+    ) -> Option<Duration> {
+        let timer = self.timers.iter().find(|timer| timer.handle == handle)?;
+        let now = time_state.time_context(&timer.time_context)?.total_time();
+        Some(timer.fire_at.checked_sub(now).unwrap_or_default())
+    }
+
+    /// Fire every timer that is due
+    pub fn update(
+        &mut self,
+        time_state: &TimeState,
+    ) {
+        self.fired_events.clear();
+
+        let mut index = 0;
+        while index < self.timers.len() {
+            if self.update_timer(index, time_state) {
+                index += 1;
+            } else {
+                self.timers.remove(index);
+            }
+        }
+    }
+
+    /// Timers that fired during the most recent update, in the order they fired
+    pub fn fired_events(&self) -> &[TimerEvent] {
+        &self.fired_events
+    }
+
+    /// Returns true if the timer fired during the most recent update
+    pub fn fired(
+        &self,
+        handle: TimerHandle,
+    ) -> bool {
+        self.fired_events.iter().any(|event| event.handle == handle)
+    }
+
+    /// Returns true if any timer with the given name fired during the most recent update
+    pub fn fired_named(
+        &self,
+        name: &str,
+    ) -> bool {
+        self.fired_events.iter().any(|event| event.name == name)
+    }
+
+    // Returns false once the timer is finished
+    fn update_timer(
+        &mut self,
+        index: usize,
+        time_state: &TimeState,
+    ) -> bool {
+        let timer = &mut self.timers[index];
+        let now = match time_state.time_context(&timer.time_context) {
+            Some(time_context) => time_context.total_time(),
+            None => {
+                log::warn!(
+                    "Cancelling timer {:?} because time context {:?} no longer exists",
+                    timer.name,
+                    timer.time_context
+                );
+                return false;
+            }
+        };
+
+        if now < timer.fire_at {
+            return true;
+        }
+
+        let count = match timer.interval {
+            Some(interval) => {
+                let count = ((now - timer.fire_at).as_nanos() / interval.as_nanos()) as u64 + 1;
+                timer.fire_at += Duration::from_nanos((interval.as_nanos() * count as u128) as u64);
+                count
+            }
+            None => 1,
+        };
+
+        timer.fire_count += count;
+        self.fired_events.push(TimerEvent {
+            handle: timer.handle,
+            name: timer.name.clone(),
+            fire_count: timer.fire_count,
+            count,
+        });
+
+        timer.interval.is_some()
+    }
+
+    fn start(
+        &mut self,
+        time_state: &TimeState,
+        name: &str,
+        time_context: &str,
+        delay: Duration,
+        interval: Option<Duration>,
+    ) -> TimerHandle {
+        let handle = TimerHandle(self.next_handle);
+        self.next_handle += 1;
+
+        let (time_context, now) = match time_state.time_context(time_context) {
+            Some(context) => (time_context, context.total_time()),
+            None => {
+                log::warn!(
+                    "Timer {:?} uses unknown time context {:?}, using app time instead",
+                    name,
+                    time_context
+                );
+                (
+                    TimeState::APP_TIME_CONTEXT,
+                    time_state.app_time_context().total_time(),
+                )
+            }
+        };
+
+        self.timers.push(Timer {
+            handle,
+            name: name.to_string(),
+            time_context: time_context.to_string(),
+            fire_at: now + delay,
+            interval,
+            fire_count: 0,
+        });
+
+        handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::{ClockResource, ManualClock};
+    use std::sync::Arc;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn time_state() -> TimeState {
+        TimeState::new(ClockResource::new(Arc::new(ManualClock::new())))
+    }
+
+    fn update(
+        timers: &mut TimerResource,
+        time_state: &mut TimeState,
+        elapsed: Duration,
+    ) {
+        time_state.update_with_elapsed(elapsed);
+        timers.update(time_state);
+    }
+
+    #[test]
+    fn one_shot_fires_once() {
+        let mut time_state = time_state();
+        let mut timers = TimerResource::default();
+        let handle =
+            timers.start_one_shot(&time_state, "once", TimeState::APP_TIME_CONTEXT, ms(100));
+
+        update(&mut timers, &mut time_state, ms(60));
+        assert!(!timers.fired(handle));
+        assert_eq!(timers.remaining(&time_state, handle), Some(ms(40)));
+
+        update(&mut timers, &mut time_state, ms(500));
+        assert_eq!(
+            timers.fired_events(),
+            &[TimerEvent {
+                handle,
+                name: "once".to_string(),
+                fire_count: 1,
+                count: 1,
+            }]
+        );
+        assert!(!timers.is_active(handle));
+
+        update(&mut timers, &mut time_state, ms(500));
+        assert!(timers.fired_events().is_empty());
+    }
+
+    #[test]
+    fn repeating_coalesces_missed_intervals() {
+        let mut time_state = time_state();
+        let mut timers = TimerResource::default();
+        let handle =
+            timers.start_repeating(&time_state, "tick", TimeState::APP_TIME_CONTEXT, ms(100));
+
+        update(&mut timers, &mut time_state, ms(100));
+        assert_eq!(timers.fired_events()[0].count, 1);
+        assert_eq!(timers.fired_events()[0].fire_count, 1);
+
+        // A hitch covering several intervals fires once
+        update(&mut timers, &mut time_state, ms(350));
+        assert_eq!(timers.fired_events().len(), 1);
+        assert_eq!(timers.fired_events()[0].count, 3);
+        assert_eq!(timers.fired_events()[0].fire_count, 4);
+        assert_eq!(timers.remaining(&time_state, handle), Some(ms(50)));
+
+        update(&mut timers, &mut time_state, ms(50));
+        assert!(timers.fired_named("tick"));
+        assert_eq!(timers.fired_events()[0].count, 1);
+        assert!(timers.is_active(handle));
+    }
+
+    #[test]
+    fn cancelled_timers_do_not_fire() {
+        let mut time_state = time_state();
+        let mut timers = TimerResource::default();
+        let handle =
+            timers.start_repeating(&time_state, "tick", TimeState::APP_TIME_CONTEXT, ms(100));
+        timers.start_one_shot(&time_state, "other", TimeState::APP_TIME_CONTEXT, ms(100));
+
+        assert!(timers.cancel(handle));
+        assert!(!timers.cancel(handle));
+        timers.cancel_named("other");
+
+        update(&mut timers, &mut time_state, ms(1000));
+        assert!(timers.fired_events().is_empty());
+        assert_eq!(timers.remaining(&time_state, handle), None);
+    }
+
+    #[test]
+    fn timers_stop_while_their_context_is_paused() {
+        let mut time_state = time_state();
+        let mut timers = TimerResource::default();
+        let game_timer =
+            timers.start_one_shot(&time_state, "game", TimeState::GAME_TIME_CONTEXT, ms(100));
+        let app_timer =
+            timers.start_one_shot(&time_state, "app", TimeState::APP_TIME_CONTEXT, ms(100));
+
+        time_state.game_time_context_mut().set_paused(true);
+        update(&mut timers, &mut time_state, ms(500));
+        assert!(timers.fired(app_timer));
+        assert!(!timers.fired(game_timer));
+        assert_eq!(timers.remaining(&time_state, game_timer), Some(ms(100)));
+
+        time_state.game_time_context_mut().set_paused(false);
+        update(&mut timers, &mut time_state, ms(100));
+        assert!(timers.fired(game_timer));
+    }
+}